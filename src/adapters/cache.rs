@@ -0,0 +1,375 @@
+// ============================================================================
+// RESPONSE CACHE - Caché de respuestas direccionada por contenido
+// ============================================================================
+// `CachingAdapter` se pone delante de `CodeGenerationFlow::execute`. La clave
+// es un hash del prompt normalizado, la huella del adaptador (modelo y
+// configuración de generación, `cache_fingerprint`) y `TOOL_SCHEMA_VERSION`.
+// Las respuestas se guardan en `~/.enjambre/cache/responses/<clave>.json`,
+// caducan tras el TTL y, al superar el límite de entradas o de bytes, se
+// descartan las más antiguas. Un acierto devuelve el resultado marcado con
+// `cache_hit` y costo 0; el orquestador lo verifica igual que una respuesta
+// nueva. Una tarea se salta la caché ejecutándose dentro de `cache::scope`.
+// ============================================================================
+
+use crate::performance::exporter;
+use crate::tools::TOOL_SCHEMA_VERSION;
+use crate::{AdapterCapabilities, CodeGenerationFlow, CodeGenerationResult, FlowError, VerificationResult};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+tokio::task_local! {
+    static SKIP_CACHE: bool;
+}
+
+/// Ejecuta `future` sin consultar ni llenar la caché si `skip` es cierto
+pub async fn scope<F: Future>(skip: bool, future: F) -> F::Output {
+    SKIP_CACHE.scope(skip, future).await
+}
+
+fn skipped() -> bool {
+    SKIP_CACHE.try_with(|skip| *skip).unwrap_or(false)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub ttl_secs: u64,
+    pub max_entries: usize,
+    pub max_bytes: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_secs: 24 * 60 * 60,
+            max_entries: 1000,
+            max_bytes: 50 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub stores: u64,
+    pub evictions: u64,
+}
+
+/// Formato en disco de una respuesta guardada
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    key: String,
+    stored_at: u64,
+    result: CodeGenerationResult,
+}
+
+struct Slot {
+    stored_at: u64,
+    bytes: u64,
+    result: CodeGenerationResult,
+}
+
+#[derive(Default)]
+struct CacheState {
+    slots: HashMap<String, Slot>,
+    stats: CacheStats,
+}
+
+pub struct ResponseCache {
+    config: CacheConfig,
+    dir: Option<PathBuf>,
+    state: Mutex<CacheState>,
+}
+
+impl ResponseCache {
+    /// Caché en memoria, sin persistencia
+    pub fn in_memory(config: CacheConfig) -> Self {
+        Self { config, dir: None, state: Mutex::new(CacheState::default()) }
+    }
+
+    /// Directorio por defecto de la caché
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".enjambre").join("cache").join("responses"))
+    }
+
+    /// Carga la caché del directorio por defecto (en memoria si no hay home)
+    pub fn load_or_default(config: CacheConfig) -> Self {
+        match Self::default_dir() {
+            Some(dir) => Self::load_from(&dir, config),
+            None => Self::in_memory(config),
+        }
+    }
+
+    /// Carga las respuestas vigentes de un directorio; las nuevas se
+    /// guardarán en el mismo sitio
+    pub fn load_from(dir: &Path, config: CacheConfig) -> Self {
+        let cache = Self { config, dir: Some(dir.to_path_buf()), state: Mutex::new(CacheState::default()) };
+        let now = now_secs();
+        let Ok(files) = std::fs::read_dir(dir) else {
+            return cache;
+        };
+        if let Ok(mut state) = cache.state.lock() {
+            for path in files.flatten().map(|file| file.path()).filter(|path| path.extension().is_some_and(|e| e == "json")) {
+                let cached = std::fs::read(&path).ok()
+                    .and_then(|bytes| serde_json::from_slice::<CachedResponse>(&bytes).ok().map(|c| (c, bytes.len() as u64)));
+                match cached {
+                    Some((cached, bytes)) if !cache.expired(cached.stored_at, now) => {
+                        state.slots.insert(cached.key, Slot { stored_at: cached.stored_at, bytes, result: cached.result });
+                    }
+                    _ => {
+                        let _ = std::fs::remove_file(&path);
+                    }
+                }
+            }
+            cache.evict(&mut state);
+        }
+        cache
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Respuesta vigente para la clave, si la hay
+    pub fn get(&self, key: &str) -> Option<CodeGenerationResult> {
+        let mut state = self.state.lock().ok()?;
+        let fresh = state.slots.get(key).map(|slot| !self.expired(slot.stored_at, now_secs()));
+        match fresh {
+            Some(true) => {
+                state.stats.hits += 1;
+                state.slots.get(key).map(|slot| slot.result.clone())
+            }
+            Some(false) => {
+                state.slots.remove(key);
+                self.remove_file(key);
+                state.stats.misses += 1;
+                None
+            }
+            None => {
+                state.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Guarda una respuesta y aplica los límites de tamaño
+    pub fn put(&self, key: &str, result: &CodeGenerationResult) {
+        let cached = CachedResponse { key: key.to_string(), stored_at: now_secs(), result: result.clone() };
+        let Ok(content) = serde_json::to_vec(&cached) else {
+            return;
+        };
+        if let Some(dir) = &self.dir {
+            if let Err(e) = write_atomically(&dir.join(format!("{}.json", key)), &content) {
+                log::warn!("⚠️ No se pudo guardar la respuesta en la caché: {}", e);
+            }
+        }
+        if let Ok(mut state) = self.state.lock() {
+            state.slots.insert(cached.key, Slot { stored_at: cached.stored_at, bytes: content.len() as u64, result: cached.result });
+            state.stats.stores += 1;
+            self.evict(&mut state);
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.state.lock().map(|state| state.stats).unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().map(|state| state.slots.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn expired(&self, stored_at: u64, now: u64) -> bool {
+        now.saturating_sub(stored_at) >= self.config.ttl_secs
+    }
+
+    /// Descarta las respuestas más antiguas hasta cumplir los límites
+    fn evict(&self, state: &mut CacheState) {
+        let mut total_bytes: u64 = state.slots.values().map(|slot| slot.bytes).sum();
+        while state.slots.len() > self.config.max_entries || total_bytes > self.config.max_bytes {
+            let Some(oldest) = state.slots.iter()
+                .min_by(|a, b| a.1.stored_at.cmp(&b.1.stored_at).then_with(|| a.0.cmp(b.0)))
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(slot) = state.slots.remove(&oldest) {
+                total_bytes -= slot.bytes;
+            }
+            self.remove_file(&oldest);
+            state.stats.evictions += 1;
+        }
+    }
+
+    fn remove_file(&self, key: &str) {
+        if let Some(dir) = &self.dir {
+            let _ = std::fs::remove_file(dir.join(format!("{}.json", key)));
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+fn write_atomically(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(tmp, path)
+}
+
+// ============================================================================
+// CLAVE
+// ============================================================================
+
+/// Prompt normalizado: saltos de línea `\n`, sin espacios al final de cada
+/// línea ni líneas en blanco al principio o al final. La indentación se
+/// conserva porque cambia el significado del código.
+pub fn normalize_prompt(prompt: &str) -> String {
+    let lines: Vec<&str> = prompt.lines().map(str::trim_end).collect();
+    let start = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|line| !line.is_empty()).map_or(start, |end| end + 1);
+    lines[start..end].join("\n")
+}
+
+/// Clave de la caché: FNV-1a de 128 bits, estable entre versiones de Rust
+pub fn cache_key(prompt: &str, fingerprint: &str) -> String {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+    let material = format!("{}\0{}\0{}", TOOL_SCHEMA_VERSION, fingerprint, normalize_prompt(prompt));
+    let hash = material.bytes().fold(OFFSET, |hash, byte| (hash ^ byte as u128).wrapping_mul(PRIME));
+    format!("{:032x}", hash)
+}
+
+// ============================================================================
+// ADAPTADOR CON CACHÉ
+// ============================================================================
+
+/// Decorador que sirve desde la caché las respuestas ya vistas
+pub struct CachingAdapter {
+    name: String,
+    inner: Arc<dyn CodeGenerationFlow>,
+    cache: Arc<ResponseCache>,
+}
+
+impl CachingAdapter {
+    pub fn new(name: &str, inner: Arc<dyn CodeGenerationFlow>, cache: Arc<ResponseCache>) -> Self {
+        Self { name: name.to_string(), inner, cache }
+    }
+}
+
+#[async_trait]
+impl CodeGenerationFlow for CachingAdapter {
+    async fn execute(&self, problem_description: &str) -> Result<CodeGenerationResult, FlowError> {
+        self.execute_with_tools(problem_description, &[]).await
+    }
+
+    async fn execute_with_tools(
+        &self,
+        problem_description: &str,
+        tools: &[serde_json::Value],
+    ) -> Result<CodeGenerationResult, FlowError> {
+        if skipped() {
+            return self.inner.execute_with_tools(problem_description, tools).await;
+        }
+
+        // Las herramientas ofrecidas cambian la respuesta: forman parte de la huella
+        let mut fingerprint = self.inner.cache_fingerprint();
+        if !tools.is_empty() {
+            fingerprint.push_str(&serde_json::to_string(tools).unwrap_or_default());
+        }
+        let key = cache_key(problem_description, &fingerprint);
+        if let Some(mut result) = self.cache.get(&key) {
+            exporter::cache_lookup(&self.name, true);
+            log::debug!("💾 Respuesta servida desde la caché ({})", key);
+            result.cache_hit = true;
+            result.execution_time_ms = 0;
+            if let Some(estimate) = result.cost_estimate.as_mut() {
+                estimate.cache_hit = true;
+                estimate.estimated_cost_usd = 0.0;
+            }
+            return Ok(result);
+        }
+        exporter::cache_lookup(&self.name, false);
+
+        let result = self.inner.execute_with_tools(problem_description, tools).await?;
+        // Solo se guarda lo que pasó la verificación del propio adaptador
+        if result.verification_passed {
+            self.cache.put(&key, &result);
+        }
+        Ok(result)
+    }
+
+    fn verify_code(&self, code: &str) -> VerificationResult {
+        self.inner.verify_code(code)
+    }
+
+    fn get_capabilities(&self) -> AdapterCapabilities {
+        self.inner.get_capabilities()
+    }
+
+    fn cache_fingerprint(&self) -> String {
+        self.inner.cache_fingerprint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(code: &str) -> CodeGenerationResult {
+        CodeGenerationResult {
+            code: code.to_string(),
+            language: "rust".to_string(),
+            confidence_score: 1.0,
+            attempts_made: 1,
+            execution_time_ms: 10,
+            verification_passed: true,
+            cost_estimate: None,
+            model_used: None,
+            metrics: Default::default(),
+            attempt_history: Vec::new(),
+            cache_hit: false,
+        }
+    }
+
+    #[test]
+    fn test_keys_limits_and_persistence() {
+        // La normalización ignora espacios finales y CRLF, no la indentación
+        assert_eq!(cache_key("\n  fn a() {}  \r\n", "m"), cache_key("  fn a() {}", "m"));
+        assert_ne!(cache_key("  fn a() {}", "m"), cache_key("fn a() {}", "m"));
+        assert_ne!(cache_key("fn a() {}", "flash"), cache_key("fn a() {}", "pro"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = CacheConfig { max_entries: 2, ..CacheConfig::default() };
+        let cache = ResponseCache::load_from(dir.path(), config.clone());
+        cache.put("a", &result("fn a() {}"));
+        cache.put("b", &result("fn b() {}"));
+        cache.put("c", &result("fn c() {}"));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats().evictions, 1);
+
+        let reloaded = ResponseCache::load_from(dir.path(), config);
+        assert_eq!(reloaded.len(), 2);
+        assert_eq!(reloaded.get("c").map(|r| r.code), Some("fn c() {}".to_string()));
+        assert_eq!(reloaded.stats(), CacheStats { hits: 1, misses: 0, stores: 0, evictions: 0 });
+
+        let expired = ResponseCache::load_from(dir.path(), CacheConfig { ttl_secs: 0, ..CacheConfig::default() });
+        assert!(expired.is_empty());
+        assert!(std::fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+}
@@ -8,13 +8,15 @@
 
 use crate::{
     adapters::gemini_process_manager::GeminiProcessManager,
-    AdapterCapabilities, AdapterConfig, CodeGenerationFlow, FlowError, CodeGenerationResult,
+    AdapterCapabilities, AdapterConfig, AttemptRecord, CodeGenerationFlow, FlowError, CodeGenerationResult,
     VerificationResult, ThinkingFlow, ThinkingResult, ReasoningStep, ThinkingMode, CostEstimate,
     cost_optimizer::ModelChoice,
+    tools::{caller, ToolError, ToolResult},
 };
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use similar::TextDiff;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
@@ -22,12 +24,6 @@ use uuid::Uuid;
 // ESTRUCTURAS PARA LA API DE GEMINI Y HERRAMIENTAS
 // ============================================================================
 
-#[derive(Debug)]
-struct ToolResult {
-    function_name: String,
-    output: String,
-}
-
 #[derive(Debug, Serialize)]
 struct GeminiRequest {
     contents: Vec<GeminiContent>,
@@ -46,9 +42,28 @@ struct GeminiSystemInstruction {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct GeminiContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
     parts: Vec<GeminiPart>,
 }
 
+impl GeminiContent {
+    /// Turno de conversación con un único fragmento de texto
+    fn text(role: &str, text: &str) -> Self {
+        Self {
+            role: Some(role.to_string()),
+            parts: vec![GeminiPart {
+                text: Some(text.to_string()),
+                function_call: None,
+                function_response: None,
+            }],
+        }
+    }
+}
+
+/// Rondas máximas de function calling dentro de un mismo intento
+const MAX_FUNCTION_CALL_ROUNDS: u32 = 5;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 struct GeminiPart {
@@ -88,19 +103,44 @@ struct GeminiSafetySetting {
     threshold: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 struct Tool {
     function_declarations: Vec<FunctionDeclaration>,
 }
 
-#[derive(Debug, Serialize)]
+impl Tool {
+    /// Declaraciones de función a partir de los esquemas de `ToolRegistry`
+    /// (`name`, `description`, `parameters`); sin esquemas no se ofrece nada
+    fn from_schemas(schemas: &[serde_json::Value]) -> Option<Self> {
+        let function_declarations: Vec<FunctionDeclaration> = schemas.iter()
+            .filter_map(|schema| {
+                let parameters = schema.get("parameters")?;
+                Some(FunctionDeclaration {
+                    name: schema.get("name")?.as_str()?.to_string(),
+                    description: schema.get("description").and_then(|d| d.as_str()).unwrap_or_default().to_string(),
+                    parameters: FunctionParameters {
+                        param_type: parameters.get("type").and_then(|t| t.as_str()).unwrap_or("object").to_string(),
+                        properties: parameters.get("properties").cloned().unwrap_or_else(|| serde_json::json!({})),
+                        required: parameters.get("required")
+                            .and_then(|r| r.as_array())
+                            .map(|r| r.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+                            .unwrap_or_default(),
+                    },
+                })
+            })
+            .collect();
+        (!function_declarations.is_empty()).then_some(Self { function_declarations })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 struct FunctionDeclaration {
     name: String,
     description: String,
     parameters: FunctionParameters,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 struct FunctionParameters {
     #[serde(rename = "type")]
     param_type: String,
@@ -157,132 +197,138 @@ pub struct GeminiCLIFlow {
 #[async_trait]
 impl CodeGenerationFlow for GeminiCLIFlow {
     async fn execute(&self, problem_description: &str) -> Result<CodeGenerationResult, FlowError> {
+        self.execute_with_tools(problem_description, &[]).await
+    }
+
+    async fn execute_with_tools(
+        &self,
+        problem_description: &str,
+        tools: &[serde_json::Value],
+    ) -> Result<CodeGenerationResult, FlowError> {
         let start_time = Instant::now();
         log::info!(
             "🚀 Iniciando Gemini CLI Flow - Sesión: {} - Modelo: {:?}",
             self.session_id, self.model_choice
         );
 
-        if let (GeminiMode::CliInteractive, Some(manager)) = (&self.mode, &self.process_manager) {
-            log::info!("⚡ Ejecutando tarea a través de Gemini CLI interactivo.");
-            let code = manager
-                .execute_command(problem_description)
-                .await
-                .map_err(|e| FlowError::ApiError(format!("Error en Gemini CLI: {}", e)))?;
-            
-            let execution_time_ms = start_time.elapsed().as_millis() as u64;
-            
-            // Estimar costo para CLI mode
-            let estimated_tokens = problem_description.split_whitespace().count() as u32;
-            let cost_estimate = self.estimate_cost(estimated_tokens, code.split_whitespace().count() as u32);
-            
-            return Ok(CodeGenerationResult {
-                code,
-                language: "unknown".to_string(),
-                confidence_score: 0.95,
-                attempts_made: 1,
-                execution_time_ms,
-                verification_passed: true,
-                cost_estimate: Some(cost_estimate),
-                model_used: Some(format!("{:?}", self.model_choice)),
-                metrics: Default::default(),
-            });
-        }
-
-        log::info!("⚡ Ejecutando tarea a través de la API directa de Gemini.");
-        let mut attempts = 0;
-        let max_attempts = 3;
-        let mut parts = vec![GeminiPart {
-            text: Some(problem_description.to_string()),
-            function_call: None,
-            function_response: None,
-        }];
+        let max_attempts = self.config.max_attempts.max(1);
 
         // Preparar prompt para thinking mode si está habilitado
+        let function_tools = Tool::from_schemas(tools);
         let enhanced_prompt = self.prepare_thinking_prompt(problem_description);
+        let mut contents = vec![GeminiContent::text("user", &enhanced_prompt)];
+        let mut cli_prompt = problem_description.to_string();
+
+        let mut history: Vec<AttemptRecord> = Vec::new();
+        let mut input_tokens = 0;
+        let mut output_tokens = 0;
+
+        // Bucle Generar -> Verificar -> Refinar
+        for attempt in 1..=max_attempts {
+            let text = match (&self.mode, &self.process_manager) {
+                (GeminiMode::CliInteractive, Some(manager)) => {
+                    log::info!("⚡ Ejecutando tarea a través de Gemini CLI interactivo.");
+                    input_tokens += cli_prompt.split_whitespace().count() as u32;
+                    manager
+                        .execute_command(&cli_prompt)
+                        .await
+                        .map_err(|e| FlowError::ApiError(format!("Error en Gemini CLI: {}", e)))?
+                }
+                _ => {
+                    log::info!("⚡ Ejecutando tarea a través de la API directa de Gemini.");
+                    input_tokens += contents.iter()
+                        .flat_map(|content| content.parts.iter())
+                        .filter_map(|part| part.text.as_ref())
+                        .map(|text| text.split_whitespace().count() as u32)
+                        .sum::<u32>();
+                    self.generate_turn(&mut contents, function_tools.as_ref()).await?
+                }
+            };
+            output_tokens += text.split_whitespace().count() as u32;
 
-        loop {
-            attempts += 1;
-            if attempts > max_attempts {
-                return Err(FlowError::MaxAttemptsReached(max_attempts));
-            }
-
-            let response_part = self.call_generative_api(&parts, &enhanced_prompt).await?;
+            let verification = if self.config.enable_verification {
+                self.verify_code(&text)
+            } else {
+                VerificationResult {
+                    is_valid: true,
+                    compilation_success: true,
+                    tests_passed: true,
+                    quality_score: 1.0,
+                    errors: Vec::new(),
+                    warnings: Vec::new(),
+                }
+            };
+
+            let diff_from_previous = history.last().map(|previous| {
+                TextDiff::from_lines(&previous.code, &text)
+                    .unified_diff()
+                    .context_radius(2)
+                    .header(&format!("intento {}", previous.attempt), &format!("intento {}", attempt))
+                    .to_string()
+            });
 
-            if let Some(function_call) = response_part.function_call.clone() {
-                let tool_result = self.handle_function_call(function_call).await?;
-                
-                parts.push(response_part);
-                parts.push(GeminiPart {
-                    text: None,
-                    function_call: None,
-                    function_response: Some(FunctionResponse {
-                        name: tool_result.function_name,
-                        response: serde_json::json!({ "output": tool_result.output }),
-                    })
-                });
+            history.push(AttemptRecord {
+                attempt,
+                code: text.clone(),
+                quality_score: verification.quality_score,
+                verification_passed: verification.is_valid,
+                errors: verification.errors.clone(),
+                warnings: verification.warnings.clone(),
+                diff_from_previous,
+            });
 
-            } else if let Some(ref text) = response_part.text {
-                log::info!("✅ Código generado exitosamente");
-                let execution_time_ms = start_time.elapsed().as_millis() as u64;
-
-                // Estimar tokens y costo
-                let input_tokens = problem_description.split_whitespace().count() as u32;
-                let output_tokens = text.split_whitespace().count() as u32;
-                let cost_estimate = self.estimate_cost(input_tokens, output_tokens);
-
-                return Ok(CodeGenerationResult {
-                    code: text.to_string(),
-                    language: "rust".to_string(),
-                    confidence_score: 0.9,
-                    attempts_made: attempts,
-                    execution_time_ms,
-                    verification_passed: self.verify_code(&text).is_valid,
-                    cost_estimate: Some(cost_estimate),
-                    model_used: Some(format!("{:?}", self.model_choice)),
-                    metrics: Default::default(),
-                });
-            } else {
-                return Err(FlowError::ApiError("Respuesta inesperada sin texto ni llamada a función".to_string()));
+            match next_step(&verification, attempt, max_attempts) {
+                AttemptOutcome::Accept => {
+                    log::info!("✅ Código generado y verificado en el intento {}", attempt);
+                    break;
+                }
+                AttemptOutcome::GiveUp => {
+                    log::warn!("⚠️ La verificación sigue con problemas tras {} intentos", attempt);
+                    break;
+                }
+                AttemptOutcome::Refine => {}
             }
+
+            log::info!("🔁 Verificación con problemas, refinando (intento {}/{})", attempt + 1, max_attempts);
+            let feedback = Self::refinement_feedback(&verification);
+            contents.push(GeminiContent::text("model", &text));
+            contents.push(GeminiContent::text("user", &feedback));
+            cli_prompt = format!(
+                "{}\n\nRespuesta anterior:\n{}\n\n{}",
+                problem_description, text, feedback
+            );
         }
+
+        let best = best_attempt(&history)
+            .cloned()
+            .ok_or(FlowError::MaxAttemptsReached(max_attempts))?;
+
+        let execution_time_ms = start_time.elapsed().as_millis() as u64;
+        let cost_estimate = self.estimate_cost(input_tokens, output_tokens);
+
+        Ok(CodeGenerationResult {
+            code: best.code.clone(),
+            language: detect_language(problem_description, &best.code),
+            confidence_score: best.quality_score,
+            attempts_made: history.len() as u32,
+            execution_time_ms,
+            verification_passed: best.verification_passed,
+            cost_estimate: Some(cost_estimate),
+            model_used: Some(format!("{:?}", self.model_choice)),
+            metrics: Default::default(),
+            attempt_history: history,
+            cache_hit: false,
+        })
     }
 
     fn verify_code(&self, code: &str) -> VerificationResult {
-        // Enhanced verification with quality scoring
-        let is_valid = !code.trim().is_empty();
-        let has_functions = code.contains("fn ") || code.contains("function") || code.contains("def ");
-        let has_comments = code.contains("//") || code.contains("#") || code.contains("/*");
-        let has_error_handling = code.contains("Result") || code.contains("try") || code.contains("catch");
-        
-        let quality_score = [
-            if is_valid { 0.25 } else { 0.0 },
-            if has_functions { 0.25 } else { 0.0 },
-            if has_comments { 0.25 } else { 0.0 },
-            if has_error_handling { 0.25 } else { 0.0 },
-        ].iter().sum();
-
-        VerificationResult {
-            is_valid,
-            compilation_success: true, // Placeholder - implementar verificación real
-            tests_passed: true,        // Placeholder - implementar testing
-            quality_score,
-            errors: Vec::new(),
-            warnings: if !has_comments { 
-                vec!["Considera agregar comentarios al código".to_string()] 
-            } else { 
-                Vec::new() 
-            },
-        }
+        verify_generated_code(code)
     }
 
     fn get_capabilities(&self) -> AdapterCapabilities {
-        let (cost_input, cost_output, supports_thinking, max_tokens) = match self.model_choice {
-            ModelChoice::Gemini15Pro => (1.25, 10.00, true, 1_000_000),
-            ModelChoice::Gemini15Flash => (0.075, 0.30, false, 1_000_000),
-            ModelChoice::Gemini15ProExp => (1.25, 10.00, true, 1_000_000),
-            _ => (1.25, 10.00, false, 1_000_000), // Default
-        };
+        let (cost_input, cost_output) = self.model_choice.price_per_million();
+        let supports_thinking = matches!(self.model_choice, ModelChoice::Gemini15Pro | ModelChoice::Gemini15ProExp);
+        let max_tokens = 1_000_000;
 
         AdapterCapabilities {
             name: "GeminiCLIFlow".to_string(),
@@ -303,6 +349,13 @@ impl CodeGenerationFlow for GeminiCLIFlow {
             cost_per_million_output: cost_output,
         }
     }
+
+    fn cache_fingerprint(&self) -> String {
+        format!(
+            "{:?}|{:?}|intentos={}|verificación={}",
+            self.model_choice, Self::generation_config(), self.config.max_attempts, self.config.enable_verification
+        )
+    }
 }
 
 #[async_trait]
@@ -481,27 +534,80 @@ impl GeminiCLIFlow {
             output_tokens,
             estimated_cost_usd: input_cost + output_cost,
             model_used: format!("{:?}", self.model_choice),
+            cache_hit: false,
+            compressed_tokens: 0,
         }
     }
 
-    async fn call_generative_api(&self, parts: &[GeminiPart], enhanced_prompt: &str) -> Result<GeminiPart, FlowError> {
-        let mut request_parts = parts.to_vec();
-        
-        // Si es thinking mode, usar el prompt mejorado
-        if enhanced_prompt != parts[0].text.as_ref().unwrap_or(&String::new()) {
-            request_parts[0].text = Some(enhanced_prompt.to_string());
+    /// Genera una respuesta de texto para la conversación, resolviendo las
+    /// llamadas a funciones intermedias. Añade a `contents` los turnos de
+    /// function calling, pero no la respuesta final.
+    async fn generate_turn(&self, contents: &mut Vec<GeminiContent>, tools: Option<&Tool>) -> Result<String, FlowError> {
+        for _ in 0..MAX_FUNCTION_CALL_ROUNDS {
+            let response_part = self.call_generative_api(contents, tools).await?;
+
+            if let Some(function_call) = response_part.function_call.clone() {
+                let function_response = Self::handle_function_call(&function_call).await;
+
+                contents.push(GeminiContent {
+                    role: Some("model".to_string()),
+                    parts: vec![response_part],
+                });
+                contents.push(GeminiContent {
+                    role: Some("user".to_string()),
+                    parts: vec![GeminiPart {
+                        text: None,
+                        function_call: None,
+                        function_response: Some(function_response),
+                    }],
+                });
+            } else if let Some(text) = response_part.text {
+                return Ok(text);
+            } else {
+                return Err(FlowError::ApiError("Respuesta inesperada sin texto ni llamada a función".to_string()));
+            }
+        }
+
+        Err(FlowError::MaxAttemptsReached(MAX_FUNCTION_CALL_ROUNDS))
+    }
+
+    /// Construye el mensaje de corrección a partir del resultado del verificador
+    fn refinement_feedback(verification: &VerificationResult) -> String {
+        let mut feedback = format!(
+            "La respuesta anterior no superó la verificación (puntuación de calidad: {:.2}).\n",
+            verification.quality_score
+        );
+        if !verification.errors.is_empty() {
+            feedback.push_str("Errores:\n");
+            for error in &verification.errors {
+                feedback.push_str(&format!("- {}\n", error));
+            }
         }
+        if !verification.warnings.is_empty() {
+            feedback.push_str("Advertencias:\n");
+            for warning in &verification.warnings {
+                feedback.push_str(&format!("- {}\n", warning));
+            }
+        }
+        feedback.push_str("Corrige estos problemas y devuelve la solución completa.");
+        feedback
+    }
+
+    fn generation_config() -> GeminiGenerationConfig {
+        GeminiGenerationConfig {
+            temperature: 0.7,
+            top_k: 40,
+            top_p: 0.95,
+            max_output_tokens: 8192,
+            response_mime_type: None,
+        }
+    }
 
+    async fn call_generative_api(&self, contents: &[GeminiContent], tools: Option<&Tool>) -> Result<GeminiPart, FlowError> {
         let request = GeminiRequest {
-            contents: vec![GeminiContent { parts: request_parts }],
-            tools: None, // Simplificado para esta implementación
-            generation_config: GeminiGenerationConfig {
-                temperature: 0.7,
-                top_k: 40,
-                top_p: 0.95,
-                max_output_tokens: 8192,
-                response_mime_type: None,
-            },
+            contents: contents.to_vec(),
+            tools: tools.map(|tool| vec![tool.clone()]),
+            generation_config: Self::generation_config(),
             safety_settings: vec![
                 GeminiSafetySetting {
                     category: "HARM_CATEGORY_HARASSMENT".to_string(),
@@ -553,11 +659,270 @@ impl GeminiCLIFlow {
         Err(FlowError::ApiError("No response content".to_string()))
     }
 
-    async fn handle_function_call(&self, _function_call: FunctionCall) -> Result<ToolResult, FlowError> {
-        // Placeholder implementation
-        Ok(ToolResult {
-            function_name: "placeholder".to_string(),
-            output: "Function call handled".to_string(),
-        })
+    /// Ejecuta la herramienta que pidió el modelo y le responde con el mismo
+    /// nombre de función. Los errores (parámetros, aprobación, ejecución) se le
+    /// devuelven como respuesta para que pueda corregir la llamada.
+    async fn handle_function_call(function_call: &FunctionCall) -> FunctionResponse {
+        log::info!("🔧 El modelo llama a la herramienta '{}'", function_call.name);
+        let result = match caller::params_from_args(&function_call.args) {
+            Ok(params) => caller::call_tool(&function_call.name, params).await,
+            Err(e) => Err(e),
+        };
+        function_response(&function_call.name, result)
+    }
+} 
+/// Respuesta a una llamada a función con el resultado de la herramienta
+fn function_response(name: &str, result: Result<ToolResult, ToolError>) -> FunctionResponse {
+    let response = match result {
+        Ok(result) => serde_json::json!({
+            "success": result.success,
+            "output": result.data,
+            "message": result.message,
+        }),
+        Err(e) => serde_json::json!({ "success": false, "error": e.to_string() }),
+    };
+    FunctionResponse { name: name.to_string(), response }
+}
+
+// ============================================================================
+// VERIFICACIÓN Y REFINAMIENTO
+// ============================================================================
+
+/// Qué hacer tras verificar un intento
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttemptOutcome {
+    Accept,
+    Refine,
+    GiveUp,
+}
+
+/// Se acepta un intento válido; si no, se refina mientras queden intentos.
+/// Las advertencias no fuerzan un refinamiento: solo acompañan al feedback
+/// de uno que ya pidieron los errores.
+fn next_step(verification: &VerificationResult, attempt: u32, max_attempts: u32) -> AttemptOutcome {
+    if verification.is_valid {
+        AttemptOutcome::Accept
+    } else if attempt >= max_attempts {
+        AttemptOutcome::GiveUp
+    } else {
+        AttemptOutcome::Refine
+    }
+}
+
+/// El último intento válido y, si ninguno pasó, el de mayor puntuación
+fn best_attempt(history: &[AttemptRecord]) -> Option<&AttemptRecord> {
+    history.iter().rev()
+        .find(|record| record.verification_passed)
+        .or_else(|| history.iter().max_by(|a, b| a.quality_score.total_cmp(&b.quality_score)))
+}
+
+/// Código del primer bloque ``` y su lenguaje; sin bloque, el texto completo
+fn fenced_code(raw: &str) -> (Option<String>, &str) {
+    if let Some(start) = raw.find("```") {
+        let after_fence = &raw[start + 3..];
+        let (header, body) = after_fence.split_once('\n').unwrap_or(("", after_fence));
+        if let Some(end) = body.find("```") {
+            let language = header.trim();
+            return ((!language.is_empty()).then(|| language.to_lowercase()), &body[..end]);
+        }
+    }
+    (None, raw)
+}
+
+/// Verificación estática de la respuesta: los errores la invalidan y las
+/// advertencias solo se informan
+fn verify_generated_code(raw: &str) -> VerificationResult {
+    let (_, code) = fenced_code(raw);
+    let code = code.trim();
+
+    let has_functions = ["fn ", "def ", "function", "func ", "=>"].iter().any(|k| code.contains(k));
+    let has_structure = has_functions
+        || ["struct ", "class ", "impl ", "enum ", "interface ", "import ", "use "].iter().any(|k| code.contains(k));
+    let has_comments = code.contains("//") || code.contains('#') || code.contains("/*") || code.contains("\"\"\"");
+    let has_error_handling = ["Result", "try", "catch", "except", "err != nil"].iter().any(|k| code.contains(k));
+
+    let mut errors = Vec::new();
+    if code.is_empty() {
+        errors.push("La respuesta no contiene código".to_string());
+    } else if !has_structure {
+        errors.push("No se encontraron definiciones de código (funciones, tipos o imports)".to_string());
+    }
+    if let Some(problem) = unbalanced_delimiters(code) {
+        errors.push(problem);
+    }
+
+    let mut warnings = Vec::new();
+    if !code.is_empty() && !has_comments {
+        warnings.push("Considera agregar comentarios al código".to_string());
+    }
+    if ["todo!()", "unimplemented!()", "TODO", "FIXME"].iter().any(|k| code.contains(k)) {
+        warnings.push("El código deja partes sin implementar (TODO/unimplemented)".to_string());
+    }
+
+    let is_valid = errors.is_empty();
+    let quality_score = [is_valid, has_functions, has_comments, has_error_handling]
+        .iter()
+        .filter(|check| **check)
+        .count() as f64 * 0.25;
+
+    VerificationResult {
+        is_valid,
+        compilation_success: is_valid,
+        tests_passed: true, // Placeholder - implementar testing
+        quality_score,
+        errors,
+        warnings,
+    }
+}
+
+/// Comprueba que (), [] y {} estén equilibrados, ignorando cadenas simples
+fn unbalanced_delimiters(code: &str) -> Option<String> {
+    let mut stack = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in code.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' => stack.push(c),
+            ')' | ']' | '}' => {
+                let expected = match c { ')' => '(', ']' => '[', _ => '{' };
+                if stack.pop() != Some(expected) {
+                    return Some(format!("Delimitador '{}' sin abrir", c));
+                }
+            }
+            _ => {}
+        }
+    }
+    stack.last().map(|open| format!("Delimitador '{}' sin cerrar", open))
+}
+
+/// Lenguaje del código: el del bloque ```, el que nombra la tarea o, en su
+/// defecto, el que sugiere la sintaxis
+fn detect_language(task: &str, raw: &str) -> String {
+    let (fence, code) = fenced_code(raw);
+    if let Some(language) = fence {
+        return language;
     }
-} 
\ No newline at end of file
+
+    let task = task.to_lowercase();
+    let words: Vec<&str> = task.split(|c: char| !c.is_alphanumeric() && c != '+' && c != '#').collect();
+    const NAMED: &[(&str, &str)] = &[
+        ("rust", "rust"), ("python", "python"), ("typescript", "typescript"), ("javascript", "javascript"),
+        ("golang", "go"), ("java", "java"), ("kotlin", "kotlin"), ("c++", "cpp"), ("c#", "csharp"),
+    ];
+    if let Some((_, language)) = NAMED.iter().find(|(name, _)| words.contains(name)) {
+        return language.to_string();
+    }
+
+    let language = if code.contains("fn ") || code.contains("impl ") {
+        "rust"
+    } else if code.contains("def ") {
+        "python"
+    } else if code.contains("func ") && code.contains("package ") {
+        "go"
+    } else if code.contains("function") || code.contains("=>") {
+        "javascript"
+    } else {
+        "unknown"
+    };
+    language.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(attempt: u32, raw: &str) -> AttemptRecord {
+        let verification = verify_generated_code(raw);
+        AttemptRecord {
+            attempt,
+            code: raw.to_string(),
+            quality_score: verification.quality_score,
+            verification_passed: verification.is_valid,
+            errors: verification.errors,
+            warnings: verification.warnings,
+            diff_from_previous: None,
+        }
+    }
+
+    #[test]
+    fn test_errors_drive_refinement_and_warnings_do_not() {
+        // Texto sin código: no es válido aunque no esté vacío
+        let prose = verify_generated_code("Claro, aquí tienes una explicación de cómo hacerlo.");
+        assert!(!prose.is_valid);
+        assert_eq!(next_step(&prose, 1, 3), AttemptOutcome::Refine);
+
+        assert_eq!(next_step(&prose, 3, 3), AttemptOutcome::GiveUp);
+
+        // Código válido con advertencias (sin comentarios, TODO): se acepta
+        let bare = verify_generated_code("fn suma(a: i32, b: i32) -> i32 { todo!() }");
+        assert!(bare.is_valid);
+        assert_eq!(bare.warnings.len(), 2);
+        assert_eq!(next_step(&bare, 1, 3), AttemptOutcome::Accept);
+
+        // Un refinamiento que piden los errores lleva también las advertencias
+        let broken = verify_generated_code("fn suma(a: i32, b: i32) -> i32 { a + b");
+        assert!(!broken.is_valid);
+        assert_eq!(next_step(&broken, 1, 3), AttemptOutcome::Refine);
+        let feedback = GeminiCLIFlow::refinement_feedback(&broken);
+        assert!(feedback.contains("Errores:") && feedback.contains("comentarios"));
+    }
+
+    #[test]
+    fn test_valid_code_is_accepted_and_preferred() {
+        let clean = "```rust\n// Suma dos enteros\nfn suma(a: i32, b: i32) -> i32 { a + b }\n```";
+        let verification = verify_generated_code(clean);
+        assert!(verification.is_valid, "{:?}", verification.errors);
+        assert_eq!(next_step(&verification, 1, 3), AttemptOutcome::Accept);
+
+        let history = vec![
+            record(1, "Claro, aquí tienes la idea."),
+            record(2, clean),
+            record(3, "fn suma(a: i32, b: i32) -> i32 { a + b"),
+        ];
+        assert_eq!(best_attempt(&history).unwrap().attempt, 2);
+    }
+
+    #[test]
+    fn test_function_response_answers_the_called_function() {
+        let ok = function_response("read_file", Ok(ToolResult::success("contenido", "leído".to_string())));
+        assert_eq!(ok.name, "read_file");
+        assert_eq!(ok.response["output"], "contenido");
+
+        let denied = function_response("write_file", Err(ToolError::PermissionDenied("rechazada".to_string())));
+        assert_eq!(denied.name, "write_file");
+        assert_eq!(denied.response["success"], false);
+        assert!(denied.response["error"].as_str().unwrap().contains("rechazada"));
+    }
+
+    #[test]
+    fn test_tool_schemas_become_function_declarations() {
+        let schemas = vec![serde_json::json!({
+            "name": "read_file",
+            "description": "Lee un fichero",
+            "parameters": { "type": "object", "properties": { "path": { "type": "string" } }, "required": ["path"] }
+        })];
+        let tool = Tool::from_schemas(&schemas).unwrap();
+        assert_eq!(tool.function_declarations[0].name, "read_file");
+        assert_eq!(tool.function_declarations[0].parameters.required, vec!["path".to_string()]);
+        assert!(Tool::from_schemas(&[]).is_none());
+    }
+
+    #[test]
+    fn test_language_comes_from_fence_task_or_syntax() {
+        assert_eq!(detect_language("Escribe un parser", "```python\ndef f():\n    pass\n```"), "python");
+        assert_eq!(detect_language("Implementa un servidor en golang", "package main"), "go");
+        // "go" suelto no es un lenguaje
+        assert_eq!(detect_language("Let's go: ordena la lista", "def ordena(xs):\n    return sorted(xs)"), "python");
+        assert_eq!(detect_language("Ordena la lista", "fn ordena(xs: &mut [i32]) { xs.sort() }"), "rust");
+    }
+}
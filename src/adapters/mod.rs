@@ -5,14 +5,17 @@
 // siguiendo el patrón CodeGenerationFlow del sistema ruvnet.
 // ============================================================================
 
+pub mod cache;
 pub mod gemini_cli;
 pub mod gemini_process_manager;
 // pub mod claude_flow; // Para futuras implementaciones (pendiente)
 
 // Re-exports públicos
+pub use cache::{CacheConfig, CachingAdapter, ResponseCache};
 pub use gemini_cli::GeminiCLIFlow;
 
 // Función factory para crear adaptadores dinámicamente
+use crate::cost_optimizer::ModelChoice;
 use crate::{CodeGenerationFlow, FlowError};
 use std::sync::Arc;
 
@@ -38,6 +41,42 @@ pub async fn create_adapter(adapter_type: &str, config: AdapterConfig) -> Result
     }
 }
 
+/// Nombre con el que se registra el adaptador dedicado a un modelo concreto
+/// (p. ej. `gemini-flash`). `Auto` no tiene adaptador propio.
+pub fn model_adapter_name(adapter_type: &str, model: &ModelChoice) -> Option<String> {
+    let suffix = match model {
+        ModelChoice::Gemini15Flash => "flash",
+        ModelChoice::Gemini15Pro => "pro",
+        ModelChoice::Gemini15ProExp => "pro-exp",
+        ModelChoice::Auto => return None,
+    };
+    Some(format!("{}-{}", adapter_type, suffix))
+}
+
+/// Crea un adaptador fijado a un modelo. En modo CLI interactivo el modelo
+/// lo decide el propio CLI, así que no hay adaptador por modelo.
+pub async fn create_model_adapter(
+    adapter_type: &str,
+    config: AdapterConfig,
+    model: &ModelChoice,
+) -> Result<Option<Arc<dyn CodeGenerationFlow>>, FlowError> {
+    let use_interactive = std::env::var("GEMINI_USE_INTERACTIVE")
+        .unwrap_or_default()
+        .parse::<bool>()
+        .unwrap_or(false);
+    if use_interactive || matches!(model, ModelChoice::Auto) {
+        return Ok(None);
+    }
+
+    match adapter_type.to_lowercase().as_str() {
+        "gemini" | "gemini-cli" => {
+            let adapter = GeminiCLIFlow::new_with_model(config, model.clone()).await?;
+            Ok(Some(Arc::new(adapter)))
+        }
+        _ => Ok(None),
+    }
+}
+
 // Configuración común para todos los adaptadores
 #[derive(Debug, Clone)]
 pub struct AdapterConfig {
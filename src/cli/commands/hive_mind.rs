@@ -1,8 +1,9 @@
 use super::{print_success, print_info, print_header, print_warning};
-use crate::swarm::{SwarmOrchestrator, SwarmConfig, TaskBuilder};
+use crate::swarm::{context::ContextItem, SwarmExecutionResult, SwarmOrchestrator, SwarmConfig, TaskBuilder, TaskType};
 use crate::cli::HiveMindCommands;
-use crate::tools::{ToolParams, get_registry};
+use crate::tools::ToolParams;
 use crate::adapters::AdapterConfig;
+use crate::cost_optimizer::SpendLedger;
 use colored::*;
 use std::error::Error;
 use std::collections::HashMap;
@@ -63,7 +64,7 @@ async fn handle_spawn_iterative(
     
     let config = SwarmConfig::default();
     let mut orchestrator = SwarmOrchestrator::new(config);
-    let registry = get_registry();
+    orchestrator.set_ledger(SpendLedger::load_or_default());
     
     // Configurar adaptadores
     let mut adapter_configs = HashMap::new();
@@ -92,7 +93,7 @@ async fn handle_spawn_iterative(
         .insert("objective", &initial_task)
         .insert("context", &format!("agents={}, strategy={}, namespace={}", agents, strategy, namespace));
     
-    let pre_task_result = registry.execute("ruv_swarm_orchestrate", pre_task_params).await;
+    let pre_task_result = orchestrator.execute_tool("ruv_swarm_orchestrate", pre_task_params).await;
     match pre_task_result {
         Ok(result) => {
             print_success("Hook pre-task completado");
@@ -115,7 +116,7 @@ async fn handle_spawn_iterative(
         .insert("operation", "store_memory")
         .insert("content", &memory_content);
     
-    let safla_result = registry.execute("safla_memory", safla_params).await;
+    let safla_result = orchestrator.execute_tool("safla_memory", safla_params).await;
     match safla_result {
         Ok(_) => print_success("Contexto almacenado en SAFLA"),
         Err(e) => print_warning(&format!("SAFLA storage falló: {}", e)),
@@ -145,10 +146,11 @@ async fn handle_spawn_iterative(
     print_info("🔧 Paso 5: Ejecutando hook post-edit...");
     
     let post_edit_params = ToolParams::new()
+        .insert("objective", format!("post-edit: {}", initial_task))
         .insert("result", &serde_json::to_string(&result).unwrap_or_default())
         .insert("success", &result.success.to_string());
     
-    let post_edit_result = registry.execute("ruv_swarm_orchestrate", post_edit_params).await;
+    let post_edit_result = orchestrator.execute_tool("ruv_swarm_orchestrate", post_edit_params).await;
     match post_edit_result {
         Ok(_) => print_success("Hook post-edit completado"),
         Err(e) => print_warning(&format!("Hook post-edit falló: {}", e)),
@@ -163,8 +165,13 @@ async fn handle_spawn_iterative(
     print_info("• Escribir 'exit' para terminar");
     println!();
     
-    // Paso 6: Bucle iterativo (como Claude Code Flow)
+    // Paso 6: Bucle iterativo (como Claude Code Flow). El historial viaja
+    // como contexto: si no cabe, los turnos antiguos se resumen o descartan.
     let mut iteration_count = 1;
+    let mut history = vec![ContextItem::turn("usuario", &initial_task)];
+    if let Some(code_result) = &result.result {
+        history.push(ContextItem::turn("enjambre", &code_result.code));
+    }
     
     loop {
         print!("{} ", format!("🐝[{}]>", iteration_count).bright_cyan().bold());
@@ -192,7 +199,7 @@ async fn handle_spawn_iterative(
             .insert("context", &format!("iteration={}, previous_success={}", iteration_count, result.success))
             .insert("namespace", &namespace);
         
-        if let Ok(_) = registry.execute("ruv_swarm_orchestrate", iter_pre_params).await {
+        if let Ok(_) = orchestrator.execute_tool("ruv_swarm_orchestrate", iter_pre_params).await {
             print_success("Hook pre-task ejecutado");
         }
         
@@ -201,23 +208,20 @@ async fn handle_spawn_iterative(
             .insert("operation", "retrieve_memories")
             .insert("query", user_input);
         
-        if let Ok(memories) = registry.execute("safla_memory", safla_retrieve_params).await {
+        let mut builder = TaskBuilder::new(TaskType::CodeGeneration, user_input.to_string());
+        // Si SAFLA falla, basta con el historial
+        if let Ok(memories) = orchestrator.execute_tool("safla_memory", safla_retrieve_params).await {
             print_info("📚 Contexto recuperado de SAFLA");
-            
-            // Combinar input del usuario con contexto
-            let enhanced_prompt = format!(
-                "Contexto previo:\n{}\n\nNueva solicitud del usuario:\n{}",
-                memories.message,
-                user_input
-            );
-            
-            // Ejecutar nueva tarea con contexto
-            let iteration_task = TaskBuilder::code_generation(&enhanced_prompt);
-            result = orchestrator.execute_task(iteration_task).await;
-        } else {
-            // Si SAFLA falla, usar solo el input del usuario
-            let iteration_task = TaskBuilder::code_generation(user_input);
-            result = orchestrator.execute_task(iteration_task).await;
+            builder = builder.with_context(ContextItem::memory(&memories.message));
+        }
+        for turn in &history {
+            builder = builder.with_context(turn.clone());
+        }
+        result = orchestrator.execute_task(builder.build()).await;
+        print_context_report(&result);
+        history.push(ContextItem::turn("usuario", user_input));
+        if let Some(code_result) = &result.result {
+            history.push(ContextItem::turn("enjambre", &code_result.code));
         }
         
         // Mostrar resultado
@@ -245,14 +249,15 @@ async fn handle_spawn_iterative(
             .insert("operation", "store_memory")
             .insert("content", &iteration_memory);
         
-        let _ = registry.execute("safla_memory", safla_store_params).await;
+        let _ = orchestrator.execute_tool("safla_memory", safla_store_params).await;
         
         // Hook post-edit
         let iter_post_params = ToolParams::new()
+            .insert("objective", format!("post-edit: {}", user_input))
             .insert("iteration", &iteration_count.to_string())
             .insert("result", &serde_json::to_string(&result).unwrap_or_default());
         
-        let _ = registry.execute("ruv_swarm_orchestrate", iter_post_params).await;
+        let _ = orchestrator.execute_tool("ruv_swarm_orchestrate", iter_post_params).await;
         
         iteration_count += 1;
         println!();
@@ -261,6 +266,15 @@ async fn handle_spawn_iterative(
     Ok(())
 }
 
+fn print_context_report(result: &SwarmExecutionResult) {
+    if let Some(report) = result.context_report.as_ref().filter(|report| !report.actions.is_empty()) {
+        print_info(&format!("✂️ Contexto ajustado: {}", report.describe()));
+        for action in &report.actions {
+            println!("   • {}", action.describe());
+        }
+    }
+}
+
 async fn handle_status(real_time: bool, dashboard: bool) -> Result<(), Box<dyn Error + Send + Sync>> {
     print_header("📊 HIVE-MIND STATUS");
     
@@ -1,21 +1,325 @@
-use super::print_success;
+use super::{print_info, print_success, print_warning};
+use crate::cli::config::CliConfig;
 use crate::cli::PerformanceCommands;
+use crate::cost_optimizer::{ledger, SpendLedger};
+use crate::performance::bottleneck::{analyze, BottleneckThresholds, ConfigChange};
+use crate::performance::report::{aggregate, is_replay, load_journals, parse_time_bound, render_html, render_text, ReportFilter};
+use crate::performance::trace::{load_otlp, render_flame};
+use crate::performance::AlertThresholds;
+use crate::swarm::journal::RunJournal;
+use colored::*;
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::io::IsTerminal;
+use std::path::PathBuf;
 
 pub async fn handle_performance_command(cmd: PerformanceCommands) -> Result<(), Box<dyn Error + Send + Sync>> {
     match cmd {
-        PerformanceCommands::Report { format: _, output: _ } => {
-            print_success("Performance report generated");
+        PerformanceCommands::Report { format, output, trace, since, until } => {
+            generate_report(&format, output, trace, since, until)?;
         }
-        PerformanceCommands::Bottleneck { auto_optimize: _ } => {
-            print_success("Bottleneck analysis completed");
-        }
-        PerformanceCommands::Tokens => {
-            print_success("Token usage: 0 tokens used");
+        PerformanceCommands::Bottleneck { auto_optimize, trace, since, yes } => {
+            analyze_bottlenecks(auto_optimize, trace, since, yes)?;
         }
+        PerformanceCommands::Tokens { days, budget, group_by, format, output } => match group_by {
+            Some(group_by) => export_chargeback(days, &group_by, &format, output)?,
+            None => show_token_usage(days, budget)?,
+        },
         PerformanceCommands::Benchmark { bench_type: _ } => {
             print_success("Benchmark completed");
         }
     }
     Ok(())
-} 
\ No newline at end of file
+}
+
+/// Informe agregado de los journals de `~/.enjambre/runs` en el rango pedido
+fn generate_report(
+    format: &str,
+    output: Option<PathBuf>,
+    trace: Option<PathBuf>,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let filter = ReportFilter {
+        since: since.as_deref().map(|since| parse_time_bound(since, false)).transpose()?,
+        until: until.as_deref().map(|until| parse_time_bound(until, true)).transpose()?,
+    };
+    let runs_dir = RunJournal::runs_dir().ok_or("No se encontró el directorio home")?;
+    let report = aggregate(&load_journals(&runs_dir), &filter, AlertThresholds::default());
+
+    let (content, default_output) = match format.to_lowercase().as_str() {
+        "text" => (render_text(&report), None),
+        "json" => (serde_json::to_string_pretty(&report)?, None),
+        // El HTML siempre va a fichero
+        "html" => (
+            render_html(&report),
+            Some(PathBuf::from(format!("enjambre_report_{}.html", chrono::Utc::now().format("%Y%m%d_%H%M%S")))),
+        ),
+        other => return Err(format!("Formato de informe desconocido: '{}' (usa text, json o html)", other).into()),
+    };
+
+    match output.or(default_output) {
+        Some(path) => {
+            std::fs::write(&path, content)?;
+            print_success(&format!("Informe guardado en {}", path.display()));
+        }
+        None => print!("{}", content),
+    }
+
+    if let Some(path) = trace {
+        let spans = load_otlp(&path)?;
+        println!();
+        println!("{}", "🔥 Desglose de trazas:".bright_cyan().bold());
+        print!("{}", render_flame(&spans));
+    }
+    Ok(())
+}
+
+/// Cuellos de botella de las ejecuciones grabadas y, con `--auto-optimize`,
+/// cambios de configuración seguros escritos en `~/.enjambre/config.toml`
+fn analyze_bottlenecks(
+    auto_optimize: bool,
+    trace: Option<PathBuf>,
+    since: Option<String>,
+    yes: bool,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let filter = ReportFilter {
+        since: since.as_deref().map(|since| parse_time_bound(since, false)).transpose()?,
+        until: None,
+    };
+    let runs_dir = RunJournal::runs_dir().ok_or("No se encontró el directorio home")?;
+    let journals: Vec<_> = load_journals(&runs_dir)
+        .into_iter()
+        .filter(|journal| !is_replay(journal))
+        .filter(|journal| journal.first().is_some_and(|entry| filter.contains(&entry.timestamp)))
+        .collect();
+    let spans = trace.as_deref().map(load_otlp).transpose()?.unwrap_or_default();
+
+    let analysis = analyze(&journals, &spans, &BottleneckThresholds::default());
+
+    println!("{}", "🔍 Análisis de cuellos de botella".bright_cyan().bold());
+    println!("  {} ejecuciones, {} spans", analysis.runs, analysis.spans);
+
+    if !analysis.time_sinks.is_empty() {
+        println!();
+        println!("{}", "⏱️  Mayores consumidores de tiempo:".bright_cyan());
+        for sink in analysis.time_sinks.iter().take(5) {
+            println!(
+                "  {:>5.1}%  {:>10.0}ms  {:>4}x  {}",
+                sink.share * 100.0, sink.self_ms, sink.calls, sink.name
+            );
+        }
+    }
+
+    println!();
+    if analysis.findings.is_empty() {
+        print_success("No se encontraron cuellos de botella");
+        return Ok(());
+    }
+    for (index, finding) in analysis.findings.iter().enumerate() {
+        println!("{}. {} {}", index + 1, format!("[{:?}]", finding.severity).yellow(), finding.title.bold());
+        println!("   {}", finding.detail);
+        if let Some(savings) = finding.estimated_savings_ms {
+            println!("   Ahorro estimado: {}ms", savings);
+        }
+        println!("   {} {}", "→".bright_green(), finding.recommendation);
+    }
+
+    let changes = analysis.config_changes();
+    if !auto_optimize {
+        if !changes.is_empty() {
+            println!();
+            print_info("Usa --auto-optimize para aplicar los cambios de configuración propuestos");
+        }
+        return Ok(());
+    }
+    if changes.is_empty() {
+        println!();
+        print_info("Ningún hallazgo tiene un cambio de configuración seguro");
+        return Ok(());
+    }
+
+    let path = CliConfig::config_file().ok_or("No se encontró el directorio home")?;
+    let current = CliConfig::load()?;
+    let mut optimized = current.clone();
+    for change in &changes {
+        apply_change(&mut optimized, change);
+    }
+
+    let before = current.to_toml()?;
+    let after = optimized.to_toml()?;
+    if before == after {
+        println!();
+        print_info("La configuración ya incluye los cambios propuestos");
+        return Ok(());
+    }
+
+    println!();
+    println!("{}", format!("📝 Cambios en {}:", path.display()).bright_cyan());
+    let diff = similar::TextDiff::from_lines(&before, &after)
+        .unified_diff()
+        .context_radius(2)
+        .header("actual", "optimizada")
+        .to_string();
+    for line in diff.lines() {
+        match line.chars().next() {
+            Some('+') if !line.starts_with("+++") => println!("{}", line.green()),
+            Some('-') if !line.starts_with("---") => println!("{}", line.red()),
+            _ => println!("{}", line),
+        }
+    }
+
+    if !yes {
+        if !std::io::stdin().is_terminal() {
+            print_warning("Sin terminal interactiva; usa --yes para aplicar los cambios");
+            return Ok(());
+        }
+        let confirmed = dialoguer::Confirm::new()
+            .with_prompt("¿Aplicar los cambios?")
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            print_info("Cambios descartados");
+            return Ok(());
+        }
+    }
+
+    optimized.save_to(&path)?;
+    print_success(&format!("Configuración guardada en {}", path.display()));
+    Ok(())
+}
+
+fn apply_change(config: &mut CliConfig, change: &ConfigChange) {
+    match change {
+        ConfigChange::ModelOverride { task_type, complexity, model } => {
+            config.complexity_overrides
+                .entry(task_type.clone())
+                .or_default()
+                .insert(format!("{:?}", complexity), model.clone());
+        }
+    }
+}
+
+/// Gasto del ledger de los últimos días frente al presupuesto diario
+fn show_token_usage(days: u32, budget: Option<f64>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let ledger = SpendLedger::load_or_default();
+    let budget = budget.or(CliConfig::load()?.daily_budget);
+    let today = chrono::Local::now().date_naive();
+    let first_day = today - chrono::Duration::days(days.saturating_sub(1) as i64);
+
+    println!("{}", format!("🪙 Gasto de {}", ledger.user()).bright_cyan().bold());
+    let spent_today = ledger.spent_today();
+    match budget {
+        Some(budget) if budget > 0.0 => {
+            let used = spent_today / budget;
+            let bar = "█".repeat(((used.min(1.0) * 30.0).round()) as usize);
+            let line = format!("  Hoy: ${:.4} de ${:.2} ({:.1}%) {:<30}", spent_today, budget, used * 100.0, bar);
+            match used {
+                u if u >= 1.0 => println!("{}", line.red()),
+                u if u >= 0.8 => println!("{}", line.yellow()),
+                _ => println!("{}", line.green()),
+            }
+        }
+        _ => println!("  Hoy: ${:.4} (sin presupuesto diario)", spent_today),
+    }
+
+    // Por día y por proyecto, solo del usuario actual
+    let mut daily: BTreeMap<chrono::NaiveDate, (u64, u64, u64, u64, f64, f64)> = BTreeMap::new();
+    let mut projects: BTreeMap<&str, f64> = BTreeMap::new();
+    let (mut saved_by_cache, mut saved_by_compression) = (0.0, 0.0);
+    for entry in ledger.entries().filter(|entry| entry.user == ledger.user() && entry.day >= first_day) {
+        let day = daily.entry(entry.day).or_default();
+        day.0 += entry.requests;
+        day.1 += entry.refused;
+        day.2 += entry.input_tokens;
+        day.3 += entry.output_tokens;
+        day.4 += entry.cost_usd;
+        day.5 += entry.cost_saved_usd;
+        saved_by_cache += entry.saved_by_cache_usd;
+        saved_by_compression += entry.saved_by_compression_usd;
+        *projects.entry(entry.project.as_str()).or_default() += entry.cost_usd;
+    }
+
+    if daily.is_empty() {
+        println!();
+        print_info(&format!("Sin gasto registrado en los últimos {} días", days));
+        return Ok(());
+    }
+
+    println!();
+    println!(
+        "  {:<10}  {:>8}  {:>9}  {:>12}  {:>12}  {:>10}  {:>10}",
+        "Día", "Tareas", "Rechazos", "Tokens in", "Tokens out", "Gasto", "Ahorro"
+    );
+    for (day, (requests, refused, input, output, cost, saved)) in daily.iter().rev() {
+        let over = budget.is_some_and(|budget| *cost > budget);
+        let line = format!(
+            "  {:<10}  {:>8}  {:>9}  {:>12}  {:>12}  {:>10}  {:>10}",
+            day, requests, refused, input, output, format!("${:.4}", cost), format!("${:.4}", saved)
+        );
+        if over { println!("{}", line.red()) } else { println!("{}", line) }
+    }
+
+    println!();
+    println!("{}", format!("📁 Por proyecto ({} días):", days).bright_cyan());
+    for (project, cost) in &projects {
+        println!("  {:<24} ${:.4}", project, cost);
+    }
+
+    let saved: f64 = daily.values().map(|day| day.5).sum();
+    if saved != 0.0 {
+        println!();
+        println!("{}", format!("💚 Ahorro vs modelo de referencia ({} días): ${:.4}", days, saved).bright_cyan());
+        println!("  {:<24} ${:.4}", "selección de modelo", saved - saved_by_cache - saved_by_compression);
+        println!("  {:<24} ${:.4}", "caché", saved_by_cache);
+        println!("  {:<24} ${:.4}", "compresión", saved_by_compression);
+    }
+    Ok(())
+}
+
+/// Informe de refacturación: gasto de todos los usuarios en los últimos
+/// días, agrupado por el valor de una etiqueta
+fn export_chargeback(
+    days: u32,
+    group_by: &str,
+    format: &str,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let ledger = SpendLedger::load_or_default();
+    let since = chrono::Local::now().date_naive() - chrono::Duration::days(days.saturating_sub(1) as i64);
+    let rows = ledger::chargeback(ledger.entries(), group_by, since);
+
+    let content = match format.to_lowercase().as_str() {
+        "csv" => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record([group_by, "requests", "refused", "input_tokens", "output_tokens", "cost_usd", "cost_saved_usd"])?;
+            for row in &rows {
+                writer.write_record([
+                    row.group.clone(),
+                    row.requests.to_string(),
+                    row.refused.to_string(),
+                    row.input_tokens.to_string(),
+                    row.output_tokens.to_string(),
+                    format!("{:.6}", row.cost_usd),
+                    format!("{:.6}", row.cost_saved_usd),
+                ])?;
+            }
+            String::from_utf8(writer.into_inner().map_err(|e| e.to_string())?)?
+        }
+        "json" => serde_json::to_string_pretty(&serde_json::json!({
+            "group_by": group_by,
+            "since": since,
+            "rows": rows,
+        }))? + "\n",
+        other => return Err(format!("Formato de refacturación desconocido: '{}' (usa csv o json)", other).into()),
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, content)?;
+            print_success(&format!("Refacturación por '{}' guardada en {}", group_by, path.display()));
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
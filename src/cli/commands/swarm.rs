@@ -3,23 +3,44 @@
 // ============================================================================
 
 use crate::{
-    swarm::{SwarmOrchestrator, SwarmConfig, TaskBuilder, TaskType, TaskPriority},
-    adapters::AdapterConfig,
-    cost_optimizer::{CostConstraints, PriorityLevel, ModelChoice},
-    performance::AlertThresholds,
+    swarm::{
+        SwarmOrchestrator, SwarmConfig, TaskBuilder, TaskType, TaskPriority,
+        ExecutionPlan, PlanExecutionResult,
+        checkpoint::PlanCheckpoint,
+        estimate::RunEstimate,
+        journal::{self, JournalEvent, RunInput, RunJournal},
+        context::ContextItem,
+        routing::TaskOutput,
+    },
+    adapters::{AdapterConfig, CacheConfig, ResponseCache},
+    cost_optimizer::{CostConstraints, PriorityLevel, ModelChoice, OptimizationRecommendation, SpendLedger, TaskComplexity},
+    performance::{AlertThresholds, PercentileThreshold},
     ThinkingMode,
 };
+use crate::cli::config::CliConfig;
 use chrono;
-use clap::Args;
+use clap::{Args, Subcommand};
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
 #[derive(Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct SwarmArgs {
+    #[command(subcommand)]
+    pub command: Option<SwarmCommands>,
+
     /// La tarea a ejecutar
-    pub task: String,
+    #[arg(required_unless_present = "plan")]
+    pub task: Option<String>,
+
+    /// Ejecutar un plan multi-paso (JSON de `ExecutionPlan`) con checkpoints
+    #[arg(long, value_name = "FILE")]
+    pub plan: Option<PathBuf>,
 
     /// Activar modo Gemini CLI
     #[arg(long)]
@@ -37,10 +58,30 @@ pub struct SwarmArgs {
     #[arg(long, value_name = "USD")]
     pub max_cost: Option<f64>,
 
+    /// Tiempo máximo de ejecución de la tarea en milisegundos
+    #[arg(long, value_name = "MS")]
+    pub timeout_ms: Option<u64>,
+
+    /// Calidad mínima (0.0 - 1.0); por debajo se regenera con el feedback del verificador
+    #[arg(long, value_name = "SCORE")]
+    pub quality_threshold: Option<f64>,
+
+    /// Lenguaje de programación preferido para el código generado
+    #[arg(long)]
+    pub language: Option<String>,
+
+    /// Desactivar la verificación del resultado
+    #[arg(long)]
+    pub no_verify: bool,
+
     /// Presupuesto diario en USD
     #[arg(long, value_name = "USD")]
     pub daily_budget: Option<f64>,
 
+    /// Tipo de tarea: determina plantilla de prompt, herramientas y formato de salida
+    #[arg(long, value_enum, default_value = "code-generation")]
+    pub task_type: CliTaskType,
+
     /// Prioridad de la tarea (low, medium, high, critical)
     #[arg(long, value_enum, default_value = "medium")]
     pub priority: CliPriority,
@@ -49,6 +90,10 @@ pub struct SwarmArgs {
     #[arg(long, value_enum)]
     pub model: Option<CliModelChoice>,
 
+    /// Modelo de referencia contra el que se mide el ahorro (por defecto Pro)
+    #[arg(long, value_enum)]
+    pub baseline_model: Option<CliModelChoice>,
+
     /// Mostrar métricas de performance en tiempo real
     #[arg(long)]
     pub metrics: bool,
@@ -57,15 +102,64 @@ pub struct SwarmArgs {
     #[arg(long)]
     pub export_report: bool,
 
+    /// Alerta sobre un percentil de latencia (repetible), p. ej. "p95>8s@5m"
+    #[arg(long = "latency-alert", value_name = "SPEC")]
+    pub latency_alerts: Vec<PercentileThreshold>,
+
     /// Mostrar recomendaciones de optimización
     #[arg(long)]
     pub recommendations: bool,
 
+    /// Estimar tokens y costo (esperado y peor caso) sin ejecutar nada. Sin
+    /// --plan ni --generate-plan se estima la tarea como una sola llamada
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Con --dry-run, generar el plan de ejecución de la tarea y estimarlo
+    /// paso a paso (el plan se crea localmente, sin llamar al modelo)
+    #[arg(long, requires = "dry_run", conflicts_with = "plan")]
+    pub generate_plan: bool,
+
+    /// No usar la caché de respuestas en esta ejecución
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Archivo a incluir como contexto de la tarea (repetible)
+    #[arg(long = "context-file", value_name = "FILE")]
+    pub context_files: Vec<PathBuf>,
+
+    /// Presupuesto de tokens del prompt (por defecto se deriva de --max-cost)
+    #[arg(long)]
+    pub max_context_tokens: Option<u32>,
+
+    /// Etiqueta de imputación del gasto (repetible), p. ej. "project=web" o "ticket=OPS-42"
+    #[arg(long = "label", value_name = "KEY=VALUE", value_parser = parse_label)]
+    pub labels: Vec<(String, String)>,
+
     /// Modo verboso para debugging
     #[arg(long, short)]
     pub verbose: bool,
 }
 
+#[derive(Subcommand)]
+pub enum SwarmCommands {
+    /// Reproduce una ejecución grabada contra sus respuestas, sin conexión
+    Replay {
+        /// Id de sesión de la ejecución (directorio en ~/.enjambre/runs)
+        id: String,
+    },
+
+    /// Reanuda un plan desde su último paso completado
+    Resume {
+        /// Id de sesión del plan interrumpido
+        session_id: String,
+
+        /// Plan editado (JSON) con el que continuar
+        #[arg(long, value_name = "FILE")]
+        plan: Option<PathBuf>,
+    },
+}
+
 #[derive(clap::ValueEnum, Clone, Debug, Copy)]
 pub enum CliPriority {
     Low,
@@ -85,6 +179,29 @@ impl From<CliPriority> for TaskPriority {
     }
 }
 
+#[derive(clap::ValueEnum, Clone, Debug, Copy)]
+pub enum CliTaskType {
+    CodeGeneration,
+    DataAnalysis,
+    Forecasting,
+    TextProcessing,
+    Classification,
+    Regression,
+}
+
+impl From<CliTaskType> for TaskType {
+    fn from(cli_task_type: CliTaskType) -> Self {
+        match cli_task_type {
+            CliTaskType::CodeGeneration => TaskType::CodeGeneration,
+            CliTaskType::DataAnalysis => TaskType::DataAnalysis,
+            CliTaskType::Forecasting => TaskType::Forecasting,
+            CliTaskType::TextProcessing => TaskType::TextProcessing,
+            CliTaskType::Classification => TaskType::Classification,
+            CliTaskType::Regression => TaskType::Regression,
+        }
+    }
+}
+
 #[derive(clap::ValueEnum, Clone, Debug)]
 pub enum CliModelChoice {
     Gemini15Flash,
@@ -105,6 +222,13 @@ impl From<CliModelChoice> for ModelChoice {
 }
 
 pub async fn execute_swarm_command(args: SwarmArgs) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match &args.command {
+        Some(SwarmCommands::Replay { id }) => return replay_swarm_run(id).await,
+        Some(SwarmCommands::Resume { session_id, plan }) => return resume_swarm_plan(session_id, plan.as_deref()).await,
+        None => {}
+    }
+    let task_description = args.task.clone().unwrap_or_default();
+
     if args.verbose {
         println!("{}", "🔍 Modo verboso activado".bright_blue());
         env_logger::builder()
@@ -116,21 +240,31 @@ pub async fn execute_swarm_command(args: SwarmArgs) -> Result<(), Box<dyn std::e
     println!("{}", "💡 Con Cost Optimization y Performance Monitoring".bright_cyan());
     println!();
 
+    // ~/.enjambre/config.toml (p. ej. escrito por `performance bottleneck --auto-optimize`)
+    let cli_config = CliConfig::load()?;
+
     let cost_constraints = CostConstraints {
         max_cost_per_request: args.max_cost,
-        daily_budget: args.daily_budget,
+        daily_budget: args.daily_budget.or(cli_config.daily_budget),
         priority: match args.priority {
             CliPriority::Low => PriorityLevel::Low,
             CliPriority::Medium => PriorityLevel::Medium,
             CliPriority::High => PriorityLevel::High,
             CliPriority::Critical => PriorityLevel::Critical,
         },
+        baseline_model: args.baseline_model.clone().map(ModelChoice::from)
+            .or(cli_config.baseline_model.clone())
+            .unwrap_or(ModelChoice::Gemini15Pro),
+        label_budgets: cli_config.label_budgets.clone(),
     };
 
-    let alert_thresholds = AlertThresholds::default();
+    let alert_thresholds = AlertThresholds {
+        latency_percentiles: args.latency_alerts.clone(),
+        ..Default::default()
+    };
 
     let swarm_config = SwarmConfig {
-        max_concurrent_tasks: 4,
+        max_concurrent_tasks: cli_config.max_concurrent_tasks,
         default_adapter: if args.gemini { "gemini".to_string() } else { "gemini".to_string() },
         enable_neural_selection: true,
         enable_adaptive_learning: true,
@@ -140,55 +274,36 @@ pub async fn execute_swarm_command(args: SwarmArgs) -> Result<(), Box<dyn std::e
         alert_thresholds,
     };
 
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(ProgressStyle::default_spinner()
-        .template("{spinner:.green} {msg}")
-        .unwrap());
-    spinner.set_message("Inicializando adaptadores optimizados...");
-    spinner.enable_steady_tick(Duration::from_millis(100));
-
     let mut orchestrator = SwarmOrchestrator::new(swarm_config);
-
-    let mut adapter_configs = HashMap::new();
-    
-    let api_key = std::env::var("GEMINI_API_KEY")
-        .or_else(|_| std::env::var("GOOGLE_API_KEY"))
-        .unwrap_or_else(|_| {
-            spinner.finish_with_message("⚠️ No se encontró API key");
-            eprintln!("{}", "⚠️  ADVERTENCIA: No se encontró GEMINI_API_KEY en variables de entorno".yellow());
-            eprintln!("{}", "   Configura tu API key con: export GEMINI_API_KEY=your_api_key".cyan());
-            String::new()
-        });
-
-    if !api_key.is_empty() {
-        let adapter_config = AdapterConfig {
-            api_key,
-            base_url: None,
-            timeout_seconds: 120,
-            max_attempts: 3,
-            enable_verification: true,
-            project_id: std::env::var("GOOGLE_PROJECT_ID").ok(),
-            location: std::env::var("GOOGLE_LOCATION").ok(),
-        };
-
-        adapter_configs.insert("gemini".to_string(), adapter_config);
+    orchestrator.set_model_overrides(model_overrides(&cli_config));
+    orchestrator.set_complexity_overrides(complexity_overrides(&cli_config));
+    orchestrator.set_ledger(SpendLedger::load_or_default());
+    let mut labels = cli_config.labels.clone();
+    labels.extend(args.labels.iter().cloned());
+    orchestrator.set_labels(labels);
+    if !args.no_cache {
+        install_response_cache(&mut orchestrator, &cli_config.response_cache);
     }
 
-    match orchestrator.initialize(adapter_configs).await {
-        Ok(_) => {
-            spinner.finish_with_message("✅ Adaptadores inicializados correctamente");
-        }
-        Err(e) => {
-            spinner.finish_with_message("❌ Error en inicialización");
-            eprintln!("{} {}", "Error:".red().bold(), e);
-            return Err(e.into());
+    if let Some(plan_path) = &args.plan {
+        let plan = load_plan(plan_path)?;
+        if args.dry_run {
+            println!("{}", format!("🧾 Estimación del plan: {}", plan.original_objective).bright_cyan().bold());
+            print_estimate(&orchestrator.estimate_plan(&plan)?);
+            return Ok(());
         }
+        prepare_execution(&mut orchestrator).await?;
+        println!();
+        println!("{}", format!("🗺️  Ejecutando plan de {} pasos: {}", plan.steps.len(), plan.original_objective).bright_green().bold());
+        orchestrator.record_run_started(&RunInput::Plan(plan.clone()), None);
+        let result = orchestrator.execute_plan(&plan).await;
+        return finish_plan_run(&orchestrator, result);
     }
 
     println!();
     println!("{}", "📋 Configurando tarea...".bright_blue());
 
-    let mut task_builder = TaskBuilder::new(TaskType::CodeGeneration, args.task.clone())
+    let mut task_builder = TaskBuilder::new(args.task_type.into(), task_description.clone())
         .with_priority(args.priority.into());
 
     if args.thinking_verbose {
@@ -204,9 +319,41 @@ pub async fn execute_swarm_command(args: SwarmArgs) -> Result<(), Box<dyn std::e
         println!("  💰 Límite de costo: ${:.3}", max_cost);
     }
 
+    for path in &args.context_files {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("No se pudo leer el contexto {}: {}", path.display(), e))?;
+        task_builder = task_builder.with_context(ContextItem::file(&path.display().to_string(), &content));
+        println!("  📎 Contexto: {}", path.display());
+    }
+
+    if let Some(max_context_tokens) = args.max_context_tokens {
+        task_builder = task_builder.with_context_budget(max_context_tokens);
+        println!("  ✂️ Presupuesto de contexto: {} tokens", max_context_tokens);
+    }
+
+    if let Some(timeout_ms) = args.timeout_ms {
+        task_builder = task_builder.with_timeout_ms(timeout_ms);
+        println!("  ⏱️ Tiempo máximo: {}ms", timeout_ms);
+    }
+
+    if let Some(threshold) = args.quality_threshold {
+        task_builder = task_builder.with_quality_threshold(threshold);
+        println!("  🎯 Calidad mínima: {:.2}", threshold);
+    }
+
+    if let Some(language) = &args.language {
+        task_builder = task_builder.with_language(language);
+        println!("  📋 Lenguaje: {}", language);
+    }
+
+    if args.no_verify {
+        task_builder = task_builder.with_verification(false);
+    }
+
     let task = task_builder.build();
 
-    println!("  📝 Descripción: {}", args.task.bright_white());
+    println!("  📝 Descripción: {}", task_description.bright_white());
+    println!("  🧭 Tipo de tarea: {:?}", args.task_type);
     println!("  🎯 Prioridad: {:?}", args.priority);
     if let Some(model) = &args.model {
         println!("  🤖 Modelo específico: {:?}", model);
@@ -214,28 +361,51 @@ pub async fn execute_swarm_command(args: SwarmArgs) -> Result<(), Box<dyn std::e
         println!("  🤖 Modelo: Selección automática optimizada");
     }
 
-    if args.metrics || args.recommendations {
+    if let Some(model) = args.model.clone().map(ModelChoice::from).filter(|model| *model != ModelChoice::Auto) {
+        orchestrator.override_model(task.task_type.clone(), model);
+    }
+
+    if args.metrics {
         println!();
         println!("{}", "📊 Análisis de optimización...".bright_cyan());
         
         let current_metrics = orchestrator.get_performance_metrics();
         let optimization_stats = orchestrator.get_optimization_stats();
 
-        if args.metrics {
-            println!("  📈 Success Rate Actual: {:.1}%", current_metrics.success_rate * 100.0);
-            println!("  ⏱️ Avg. Response Time: {}ms", current_metrics.average_response_time_ms);
-            println!("  💰 Ahorro Total: ${:.3}", optimization_stats.total_cost_saved);
-        }
+        println!("  📈 Success Rate Actual: {:.1}%", current_metrics.success_rate * 100.0);
+        println!("  ⏱️ Avg. Response Time: {}ms", current_metrics.average_response_time_ms);
+        println!("  💰 Ahorro Total: ${:.3} (acumulado: ${:.3})",
+            optimization_stats.total_cost_saved, optimization_stats.lifetime_cost_saved);
+    }
 
-        if args.recommendations && !optimization_stats.recommendations.is_empty() {
-            println!("  💡 Recomendaciones:");
-            for rec in &optimization_stats.recommendations {
-                println!("    - Modelo {:?}: {}", rec.model, rec.reason.bright_yellow());
-                println!("      Costo Estimado: ${:.4}, Confianza: {:.1}%", rec.estimated_cost, rec.confidence * 100.0);
+    if args.recommendations {
+        let recommendations = orchestrator.recommend_models(&task);
+        print_recommendations(&recommendations);
+        if args.model.is_none() {
+            if let Some(model) = pick_recommendation(&recommendations)? {
+                println!("  🤖 Modelo elegido: {:?}", model);
+                orchestrator.override_model(task.task_type.clone(), model);
             }
         }
     }
 
+    if args.dry_run && args.generate_plan {
+        let plan = orchestrator.create_execution_plan(&task_description).await?;
+        println!();
+        println!("{}", format!("🧾 Estimación del plan generado ({} pasos)", plan.steps.len()).bright_cyan().bold());
+        println!("  🗺️  Generación del plan: $0.0000 (local, sin llamada al modelo)");
+        print_estimate(&orchestrator.estimate_plan(&plan)?);
+        return Ok(());
+    }
+
+    if args.dry_run {
+        println!();
+        println!("{}", "🧾 Estimación de la tarea (una sola llamada)".bright_cyan().bold());
+        print_estimate(&orchestrator.estimate_task(&task));
+        return Ok(());
+    }
+    prepare_execution(&mut orchestrator).await?;
+
     println!();
     println!("{}", "⚡ Ejecutando tarea con optimizaciones...".bright_green().bold());
 
@@ -246,9 +416,11 @@ pub async fn execute_swarm_command(args: SwarmArgs) -> Result<(), Box<dyn std::e
     execution_bar.set_message("Analizando complejidad y seleccionando modelo óptimo...");
     execution_bar.enable_steady_tick(Duration::from_millis(120));
 
+    orchestrator.record_run_started(&RunInput::Task(task.clone()), None);
     let start_time = std::time::Instant::now();
     let result = orchestrator.execute_task(task).await;
     let execution_time = start_time.elapsed();
+    orchestrator.record_run_finished(result.success, execution_time.as_millis() as u64);
 
     execution_bar.finish_and_clear();
 
@@ -262,10 +434,23 @@ pub async fn execute_swarm_command(args: SwarmArgs) -> Result<(), Box<dyn std::e
         println!("  🔧 Adaptador: {}", result.selected_adapter);
         println!("  ⏱️  Tiempo total: {:.2}s", execution_time.as_secs_f64());
         println!("  💰 Costo real: ${:.4}", result.cost_actual);
+        if result.result.as_ref().is_some_and(|r| r.cache_hit) {
+            println!("  🗃️ Respuesta servida desde la caché (verificada de nuevo)");
+        }
+        if let Some(report) = result.context_report.as_ref().filter(|report| !report.actions.is_empty()) {
+            println!("  ✂️ Contexto: {}", report.describe());
+            for action in &report.actions {
+                println!("     • {}", action.describe());
+            }
+        }
         
-        if result.cost_saved > 0.0 {
-            println!("  💚 Ahorro vs modelo caro: ${:.4}", result.cost_saved);
-            let savings_percent = (result.cost_saved / (result.cost_actual + result.cost_saved)) * 100.0;
+        if let Some(savings) = result.savings.as_ref().filter(|savings| savings.total_usd() > 0.0) {
+            println!("  💚 Ahorro vs {:?}: ${:.4}", savings.baseline_model, savings.total_usd());
+            println!(
+                "     selección de modelo ${:.4} · caché ${:.4} · compresión ${:.4}",
+                savings.model_selection_usd, savings.cache_usd, savings.compression_usd
+            );
+            let savings_percent = (savings.total_usd() / savings.baseline_cost_usd) * 100.0;
             println!("  📈 Ahorro porcentual: {:.1}%", savings_percent);
         }
         
@@ -286,6 +471,17 @@ pub async fn execute_swarm_command(args: SwarmArgs) -> Result<(), Box<dyn std::e
             println!("  ✅ Verificación: {}", if code_result.verification_passed { "Pasó ✓" } else { "Falló ✗" });
         }
 
+        match &result.output {
+            Some(TaskOutput::Labels(labels)) => {
+                println!("  🏷️  Etiquetas: {}", labels.join(", ").bright_white());
+            }
+            Some(TaskOutput::Series(series)) => {
+                let values: Vec<String> = series.iter().map(|v| format!("{:.3}", v)).collect();
+                println!("  📈 Serie ({} valores): [{}]", series.len(), values.join(", "));
+            }
+            _ => {}
+        }
+
         if let Some(thinking_result) = &result.thinking_result {
             println!();
             println!("{}", "🧠 Proceso de Razonamiento:".bright_magenta().bold());
@@ -344,6 +540,40 @@ pub async fn execute_swarm_command(args: SwarmArgs) -> Result<(), Box<dyn std::e
     }
 
     if args.metrics {
+        let report = orchestrator.get_performance_report();
+        println!();
+        println!("{}", "🖥️ Recursos del proceso:".bright_cyan().bold());
+        println!("  💾 Memoria (con hijos): {} MB", report.metrics.memory_usage_mb);
+        println!("  🔥 CPU: {:.1}%", report.metrics.cpu_usage_percent);
+        println!("  👶 Procesos hijos: {}", report.metrics.child_processes);
+        if let Some(fds) = report.metrics.open_file_descriptors {
+            println!("  📂 Descriptores abiertos: {}", fds);
+        }
+        if let Some(stats) = orchestrator.cache_stats() {
+            println!("  🗃️ Caché de respuestas: {} aciertos, {} fallos", stats.hits, stats.misses);
+        }
+
+        if report.metrics.latency.iter().any(|window| window.count > 0) {
+            println!();
+            println!("{}", "⏱️ Percentiles de latencia:".bright_cyan().bold());
+            for window in report.metrics.latency.iter().filter(|window| window.count > 0) {
+                println!("  {:>7}: p50 {}ms, p90 {}ms, p99 {}ms, máx {}ms ({} muestras)",
+                    window.window, window.p50_ms, window.p90_ms, window.p99_ms, window.max_ms, window.count);
+            }
+        }
+
+        let series = report.series;
+        if !series.is_empty() {
+            println!();
+            println!("{}", "📊 Métricas por adaptador y herramienta:".bright_cyan().bold());
+            for entry in &series {
+                let p95 = entry.latency.last().map(|session| session.p95_ms).unwrap_or(0);
+                println!("  {:?} {}: {} llamadas, {:.1}% éxito, media {}ms, p95 {}ms, máx {}ms",
+                    entry.kind, entry.name.bright_white(), entry.total_requests,
+                    entry.success_rate * 100.0, entry.average_response_time_ms, p95, entry.max_response_time_ms);
+            }
+        }
+
         println!();
         println!("{}", "📊 Comparación de Performance:".bright_cyan().bold());
         let comparison = orchestrator.get_optimization_stats().claude_flow_comparison;
@@ -369,8 +599,341 @@ pub async fn execute_swarm_command(args: SwarmArgs) -> Result<(), Box<dyn std::e
         }
     }
 
+    if let Some(journal) = orchestrator.journal() {
+        println!();
+        println!("  🗂️  Journal: {}", journal.dir().display().to_string().bright_black());
+        println!("  🔁 Reproducir con: enjambre swarm replay {}", orchestrator.session_id());
+    }
+
     println!();
     println!("{}", "🎯 Ejecución completada".bright_green().bold());
     
     Ok(())
-} 
\ No newline at end of file
+}
+
+/// `enjambre swarm resume <session_id>`: continúa un plan desde su checkpoint
+async fn resume_swarm_plan(session_id: &str, plan_path: Option<&Path>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!("{}", format!("⏯️  Reanudando plan de la sesión {}", session_id).bright_green().bold());
+
+    let mut checkpoint = PlanCheckpoint::load(session_id)?;
+    if let Some(path) = plan_path {
+        println!("  ✏️  Usando plan editado: {}", path.display());
+        checkpoint = checkpoint.with_plan(load_plan(path)?)?;
+    }
+    println!(
+        "  💾 {} de {} pasos completados, ${:.4} gastados",
+        checkpoint.completed.len(), checkpoint.plan.steps.len(), checkpoint.spent_usd
+    );
+
+    // Misma configuración que la ejecución original, si quedó en el journal
+    let config = RunJournal::load(session_id)
+        .ok()
+        .and_then(|entries| entries.into_iter().find_map(|entry| match entry.event {
+            JournalEvent::RunStarted { config, .. } => Some(*config),
+            _ => None,
+        }))
+        .unwrap_or_default();
+
+    let cli_config = CliConfig::load()?;
+    let mut orchestrator = SwarmOrchestrator::new(config);
+    orchestrator.set_ledger(SpendLedger::load_or_default());
+    orchestrator.set_labels(cli_config.labels.clone());
+    install_response_cache(&mut orchestrator, &cli_config.response_cache);
+    orchestrator.set_session_id(session_id);
+    match RunJournal::create(session_id) {
+        Ok(journal) => orchestrator.enable_journal(Arc::new(journal)),
+        Err(e) => log::warn!("⚠️ No se pudo abrir el journal de la ejecución: {}", e),
+    }
+    initialize_adapters(&mut orchestrator).await?;
+
+    let result = orchestrator.resume_plan(checkpoint).await;
+    finish_plan_run(&orchestrator, result)
+}
+
+/// `KEY=VALUE` de `--label`
+fn parse_label(spec: &str) -> Result<(String, String), String> {
+    match spec.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() && !value.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("etiqueta inválida '{}': se espera KEY=VALUE", spec)),
+    }
+}
+
+/// Caché de respuestas de `~/.enjambre/cache`, salvo que esté desactivada en config.toml
+fn install_response_cache(orchestrator: &mut SwarmOrchestrator, config: &CacheConfig) {
+    if config.enabled {
+        orchestrator.set_response_cache(Arc::new(ResponseCache::load_or_default(config.clone())));
+    }
+}
+
+/// Journal de la ejecución y adaptadores; no se preparan en `--dry-run`
+async fn prepare_execution(orchestrator: &mut SwarmOrchestrator) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match RunJournal::create(orchestrator.session_id()) {
+        Ok(journal) => orchestrator.enable_journal(Arc::new(journal)),
+        Err(e) => log::warn!("⚠️ No se pudo crear el journal de la ejecución: {}", e),
+    }
+    initialize_adapters(orchestrator).await
+}
+
+fn print_estimate(estimate: &RunEstimate) {
+    println!(
+        "  {:<5} {:<40} {:<10} {:<16} {:>9} {:>9} {:>10} {:>10}",
+        "Paso", "Descripción", "Compl.", "Modelo", "Tok. in", "Tok. out", "Esperado", "Peor caso"
+    );
+    for step in &estimate.steps {
+        let mut description: String = step.description.chars().take(38).collect();
+        if step.description.chars().count() > 38 {
+            description.push('…');
+        }
+        let model = match &step.downgraded_from {
+            Some(original) => format!("{:?}↓", step.model).yellow().to_string() + &format!(" (de {:?})", original),
+            None => format!("{:?}", step.model),
+        };
+        let line = format!(
+            "  {:<5} {:<40} {:<10} {:<16} {:>9} {:>9} {:>10} {:>10}",
+            step.step_id.map(|id| id.to_string()).unwrap_or_else(|| "-".to_string()),
+            description,
+            format!("{:?}", step.complexity),
+            model,
+            step.input_tokens,
+            step.output_tokens,
+            format!("${:.4}", step.expected_cost_usd),
+            format!("${:.4}", step.worst_case_cost_usd),
+        );
+        if step.refused.is_some() { println!("{}", line.red()) } else { println!("{}", line) }
+    }
+
+    println!();
+    println!("  💰 Costo esperado: {}", format!("${:.4}", estimate.expected_total_usd).bright_green().bold());
+    println!("  🔁 Peor caso (con reintentos): {}", format!("${:.4}", estimate.worst_case_total_usd).bright_yellow().bold());
+    if let Some(budget) = estimate.daily_budget_usd {
+        println!("  📅 Presupuesto diario: ${:.4} gastados de ${:.2}", estimate.spent_today_usd, budget);
+    }
+    for warning in estimate.warnings() {
+        println!("  {} {}", "⚠️".yellow(), warning.yellow());
+    }
+    println!();
+    println!("{}", "ℹ️  Dry run: no se ha ejecutado nada".bright_blue());
+}
+
+fn print_recommendations(recommendations: &[OptimizationRecommendation]) {
+    println!();
+    println!("{}", "💡 Modelos recomendados (frente de Pareto costo/latencia/éxito):".bright_cyan());
+    println!("  {:<16} {:>10} {:>10} {:>8} {:>10}  Motivo", "Modelo", "Costo", "Latencia", "Éxito", "Confianza");
+    for rec in recommendations {
+        println!(
+            "  {:<16} {:>10} {:>10} {:>7.1}% {:>9.1}%  {}",
+            format!("{:?}", rec.model),
+            format!("${:.4}", rec.estimated_cost),
+            format!("{:.1}s", rec.estimated_latency_ms / 1000.0),
+            rec.expected_success * 100.0,
+            rec.confidence * 100.0,
+            rec.reason.bright_yellow(),
+        );
+    }
+}
+
+/// Deja elegir uno de los modelos recomendados; sin terminal se mantiene la
+/// selección automática
+fn pick_recommendation(
+    recommendations: &[OptimizationRecommendation],
+) -> Result<Option<ModelChoice>, Box<dyn std::error::Error + Send + Sync>> {
+    if recommendations.is_empty() || !std::io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let mut items: Vec<String> = recommendations.iter()
+        .map(|rec| format!("{:?} (${:.4}, {:.0}% éxito)", rec.model, rec.estimated_cost, rec.expected_success * 100.0))
+        .collect();
+    items.push("Selección automática".to_string());
+
+    let choice = dialoguer::Select::new()
+        .with_prompt("¿Qué modelo usar?")
+        .items(&items)
+        .default(items.len() - 1)
+        .interact()?;
+    Ok(recommendations.get(choice).map(|rec| rec.model.clone()))
+}
+
+/// Modelos fijados por clase de tarea en la configuración
+fn model_overrides(config: &CliConfig) -> HashMap<TaskType, ModelChoice> {
+    config.model_overrides.iter()
+        .filter_map(|(task_type, model)| {
+            match serde_json::from_value::<TaskType>(serde_json::Value::String(task_type.clone())) {
+                Ok(task_type) => Some((task_type, model.clone())),
+                Err(_) => {
+                    log::warn!("⚠️ Clase de tarea desconocida en model_overrides: '{}'", task_type);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn complexity_overrides(config: &CliConfig) -> HashMap<(TaskType, TaskComplexity), ModelChoice> {
+    let parse = |name: &str| serde_json::Value::String(name.to_string());
+    config.complexity_overrides.iter()
+        .flat_map(|(task_type, models)| models.iter().map(move |(complexity, model)| (task_type, complexity, model)))
+        .filter_map(|(task_type, complexity, model)| {
+            match (serde_json::from_value::<TaskType>(parse(task_type)), serde_json::from_value::<TaskComplexity>(parse(complexity))) {
+                (Ok(task_type), Ok(complexity)) => Some(((task_type, complexity), model.clone())),
+                _ => {
+                    log::warn!("⚠️ Entrada desconocida en complexity_overrides: '{}.{}'", task_type, complexity);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn load_plan(path: &Path) -> Result<ExecutionPlan, Box<dyn std::error::Error + Send + Sync>> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Muestra el resultado de un plan y cómo reanudarlo si quedó a medias
+fn finish_plan_run(
+    orchestrator: &SwarmOrchestrator,
+    result: Result<PlanExecutionResult, crate::FlowError>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
+            orchestrator.record_run_finished(false, 0);
+            println!("{} {}", "❌ El plan se detuvo:".bright_red().bold(), e);
+            println!("  ⏯️  Reanudar con: enjambre swarm resume {}", orchestrator.session_id());
+            return Err(e.into());
+        }
+    };
+    orchestrator.record_run_finished(result.success, result.execution_time_ms);
+
+    println!();
+    for step in &result.steps {
+        let icon = if result.resumed_steps.contains(&step.step_id) {
+            "⏭️"
+        } else if step.result.success {
+            "✅"
+        } else {
+            "❌"
+        };
+        println!("  {} Paso {} ({})", icon, step.step_id, step.agent_id);
+        if let Some(error) = &step.result.error {
+            println!("    📝 {}", error.red());
+        }
+    }
+    println!("  💰 Costo total: ${:.4}", result.spent_usd);
+    println!("  ⏱️  Tiempo: {:.2}s", result.execution_time_ms as f64 / 1000.0);
+
+    println!();
+    if result.success {
+        println!("{}", "🎯 Plan completado".bright_green().bold());
+    } else {
+        println!("{}", "⚠️ Plan incompleto".bright_yellow().bold());
+        println!("  ⏯️  Reanudar con: enjambre swarm resume {}", orchestrator.session_id());
+    }
+    Ok(())
+}
+
+/// `enjambre swarm replay <id>`: repite la orquestación con las respuestas grabadas
+async fn replay_swarm_run(session_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!("{}", format!("🔁 Reproduciendo ejecución {}", session_id).bright_green().bold());
+
+    let entries = RunJournal::load(session_id)?;
+    println!("  📜 {} eventos grabados", entries.len());
+
+    let report = journal::replay_run(&entries, true).await?;
+
+    println!();
+    for (i, replayed) in report.replayed.iter().enumerate() {
+        let recorded = report.recorded.get(i);
+        let same = recorded.is_some_and(|recorded| {
+            recorded.success == replayed.success
+                && recorded.result.as_ref().map(|r| &r.code) == replayed.result.as_ref().map(|r| &r.code)
+        });
+        let icon = if same { "✅" } else { "❌" };
+        println!(
+            "  {} Tarea {} - éxito: {} (grabado: {})",
+            icon,
+            replayed.task_id,
+            replayed.success,
+            recorded.map(|r| r.success.to_string()).unwrap_or_else(|| "-".to_string()),
+        );
+        if let Some(error) = &replayed.error {
+            println!("    📝 {}", error.red());
+        }
+    }
+
+    if !report.recorded_messages.is_empty() || !report.replayed_messages.is_empty() {
+        println!(
+            "  📨 {} mensajes del bus (grabados: {})",
+            report.replayed_messages.len(),
+            report.recorded_messages.len(),
+        );
+    }
+
+    if !report.divergences.is_empty() {
+        println!();
+        println!("{}", "🔀 Divergencias:".bright_yellow().bold());
+        for divergence in &report.divergences {
+            println!("  • {}", divergence);
+        }
+    }
+
+    println!();
+    if report.matches() {
+        println!("{}", "🎯 La reproducción coincide con la ejecución grabada".bright_green().bold());
+    } else {
+        println!("{}", "⚠️ La reproducción difiere de la ejecución grabada".bright_yellow().bold());
+    }
+    println!("  🗂️  Sesión de replay: {}", report.replay_session);
+
+    Ok(())
+}
+
+/// Inicializa los adaptadores del orquestador a partir de las variables de entorno
+async fn initialize_adapters(orchestrator: &mut SwarmOrchestrator) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(ProgressStyle::default_spinner()
+        .template("{spinner:.green} {msg}")
+        .unwrap());
+    spinner.set_message("Inicializando adaptadores optimizados...");
+    spinner.enable_steady_tick(Duration::from_millis(100));
+
+    let mut adapter_configs = HashMap::new();
+    
+    let api_key = std::env::var("GEMINI_API_KEY")
+        .or_else(|_| std::env::var("GOOGLE_API_KEY"))
+        .unwrap_or_else(|_| {
+            spinner.finish_with_message("⚠️ No se encontró API key");
+            eprintln!("{}", "⚠️  ADVERTENCIA: No se encontró GEMINI_API_KEY en variables de entorno".yellow());
+            eprintln!("{}", "   Configura tu API key con: export GEMINI_API_KEY=your_api_key".cyan());
+            String::new()
+        });
+
+    if !api_key.is_empty() {
+        let adapter_config = AdapterConfig {
+            api_key,
+            base_url: None,
+            timeout_seconds: 120,
+            max_attempts: 3,
+            enable_verification: true,
+            project_id: std::env::var("GOOGLE_PROJECT_ID").ok(),
+            location: std::env::var("GOOGLE_LOCATION").ok(),
+        };
+
+        adapter_configs.insert("gemini".to_string(), adapter_config);
+    }
+
+    match orchestrator.initialize(adapter_configs).await {
+        Ok(_) => {
+            spinner.finish_with_message("✅ Adaptadores inicializados correctamente");
+        }
+        Err(e) => {
+            spinner.finish_with_message("❌ Error en inicialización");
+            eprintln!("{} {}", "Error:".red().bold(), e);
+            return Err(e.into());
+        }
+    }
+
+    Ok(())
+}
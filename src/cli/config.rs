@@ -2,10 +2,14 @@
 // CLI CONFIGURATION - Configuration Management
 // ============================================================================
 
+use crate::adapters::CacheConfig;
+use crate::cost_optimizer::{LabelBudget, ModelChoice};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CliConfig {
     pub gemini_api_key: Option<String>,
     pub default_adapter: String,
@@ -13,6 +17,21 @@ pub struct CliConfig {
     pub enable_neural_selection: bool,
     pub enable_adaptive_learning: bool,
     pub log_level: String,
+    /// Presupuesto diario en USD, si no se pasa `--daily-budget`
+    pub daily_budget: Option<f64>,
+    /// Modelo de referencia para medir el ahorro, si no se pasa `--baseline-model`
+    pub baseline_model: Option<ModelChoice>,
+    /// Caché de respuestas (`[response_cache]`: enabled, ttl_secs, max_entries, max_bytes)
+    pub response_cache: CacheConfig,
+    /// Modelo fijo por clase de tarea (p. ej. `CodeGeneration = "Gemini15Flash"`)
+    pub model_overrides: BTreeMap<String, ModelChoice>,
+    /// Modelo fijo por clase de tarea y complejidad, por encima de
+    /// `model_overrides` (`[complexity_overrides.CodeGeneration]`: `Simple = "Gemini15Flash"`)
+    pub complexity_overrides: BTreeMap<String, BTreeMap<String, ModelChoice>>,
+    /// Etiquetas por defecto de las tareas (`[labels]`: project, user, team...)
+    pub labels: BTreeMap<String, String>,
+    /// Presupuestos diarios por etiqueta (`[[label_budgets]]`: label, value, daily_usd)
+    pub label_budgets: Vec<LabelBudget>,
 }
 
 impl Default for CliConfig {
@@ -24,6 +43,13 @@ impl Default for CliConfig {
             enable_neural_selection: true,
             enable_adaptive_learning: true,
             log_level: "info".to_string(),
+            daily_budget: None,
+            baseline_model: None,
+            response_cache: CacheConfig::default(),
+            model_overrides: BTreeMap::new(),
+            complexity_overrides: BTreeMap::new(),
+            labels: BTreeMap::new(),
+            label_budgets: Vec::new(),
         }
     }
 }
@@ -46,10 +72,83 @@ impl CliConfig {
                 .parse()
                 .unwrap_or(true),
             log_level: std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
+            daily_budget: None,
+            baseline_model: None,
+            response_cache: CacheConfig::default(),
+            model_overrides: BTreeMap::new(),
+            complexity_overrides: BTreeMap::new(),
+            labels: BTreeMap::new(),
+            label_budgets: Vec::new(),
         }
     }
 
     pub fn config_dir() -> Option<PathBuf> {
         dirs::home_dir().map(|home| home.join(".enjambre"))
     }
-} 
\ No newline at end of file
+
+    /// `~/.enjambre/config.toml`
+    pub fn config_file() -> Option<PathBuf> {
+        Self::config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// Carga `config.toml` si existe; si no, la configuración por defecto
+    pub fn load() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        match Self::config_file().filter(|path| path.exists()) {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Serializa la configuración sin la API key, que vive en `.env`
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        let mut config = self.clone();
+        config.gemini_api_key = None;
+        toml::to_string_pretty(&config)
+    }
+
+    /// Guarda la configuración sobre el fichero existente: se conservan la
+    /// API key y las claves que esta versión no conoce. Escritura atómica
+    /// (fichero temporal + rename).
+    pub fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut table: toml::Table = match std::fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let known: toml::Table = toml::from_str(&self.to_toml()?)?;
+        table.extend(known);
+
+        let tmp = path.with_extension("toml.tmp");
+        std::fs::write(&tmp, toml::to_string_pretty(&table)?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_preserves_api_key_and_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "gemini_api_key = \"secreta\"\nfuture_option = 3\nmax_concurrent_tasks = 2\n").unwrap();
+
+        let mut config = CliConfig::load_from(&path).unwrap();
+        config.model_overrides.insert("CodeGeneration".to_string(), ModelChoice::Gemini15Flash);
+        config.save_to(&path).unwrap();
+
+        let saved: toml::Table = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["gemini_api_key"].as_str(), Some("secreta"));
+        assert_eq!(saved["future_option"].as_integer(), Some(3));
+        assert_eq!(CliConfig::load_from(&path).unwrap().model_overrides.len(), 1);
+    }
+}
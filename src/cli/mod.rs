@@ -56,6 +56,145 @@ pub struct Cli {
     /// Skip safety checks (dangerous)
     #[arg(long, global = true)]
     pub dangerously_skip_permissions: bool,
+
+    /// Non-interactive approval policy file (TOML), for CI
+    #[arg(long, global = true, env = "ENJAMBRE_APPROVAL_POLICY")]
+    pub approval_policy: Option<PathBuf>,
+
+    /// Minimum tool risk level that requires approval
+    #[arg(long, global = true, value_enum, default_value = "high")]
+    pub approval_threshold: CliRiskLevel,
+
+    /// Do not ask for approval before applying plan steps
+    #[arg(long, global = true)]
+    pub no_plan_approval: bool,
+
+    /// Only offer tools of these categories (repeatable or comma-separated)
+    #[arg(long, global = true, value_enum, value_delimiter = ',', env = "ENJAMBRE_TOOLS_INCLUDE")]
+    pub tools_include: Vec<CliToolCategory>,
+
+    /// Never offer tools of these categories (wins over --tools-include)
+    #[arg(long, global = true, value_enum, value_delimiter = ',', env = "ENJAMBRE_TOOLS_EXCLUDE")]
+    pub tools_exclude: Vec<CliToolCategory>,
+
+    /// Serve Prometheus metrics on this address (e.g. 0.0.0.0:9464)
+    #[arg(long, global = true, env = "ENJAMBRE_METRICS_ADDR")]
+    pub metrics_addr: Option<std::net::SocketAddr>,
+
+    /// Write Prometheus metrics to this file when the command finishes
+    #[arg(long, global = true, env = "ENJAMBRE_METRICS_FILE")]
+    pub metrics_file: Option<PathBuf>,
+
+    /// Write tracing spans as OTLP/JSON to this file when the command finishes
+    #[arg(long, global = true, env = "ENJAMBRE_TRACE_FILE")]
+    pub trace_file: Option<PathBuf>,
+
+    /// Send tracing spans to an OTLP/HTTP collector (e.g. http://localhost:4318)
+    #[arg(long, global = true, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+}
+
+/// Nivel de riesgo seleccionable desde la CLI
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum CliRiskLevel {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl From<CliRiskLevel> for crate::tools::RiskLevel {
+    fn from(level: CliRiskLevel) -> Self {
+        match level {
+            CliRiskLevel::Low => Self::Low,
+            CliRiskLevel::Medium => Self::Medium,
+            CliRiskLevel::High => Self::High,
+            CliRiskLevel::Critical => Self::Critical,
+        }
+    }
+}
+
+/// Categoría de herramientas seleccionable desde la CLI
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum CliToolCategory {
+    FileSystem,
+    System,
+    Network,
+    Text,
+    Data,
+    Memory,
+    Utils,
+    Security,
+    Development,
+    Ai,
+}
+
+impl From<CliToolCategory> for crate::tools::ToolCategory {
+    fn from(category: CliToolCategory) -> Self {
+        match category {
+            CliToolCategory::FileSystem => Self::FileSystem,
+            CliToolCategory::System => Self::System,
+            CliToolCategory::Network => Self::Network,
+            CliToolCategory::Text => Self::Text,
+            CliToolCategory::Data => Self::Data,
+            CliToolCategory::Memory => Self::Memory,
+            CliToolCategory::Utils => Self::Utils,
+            CliToolCategory::Security => Self::Security,
+            CliToolCategory::Development => Self::Development,
+            CliToolCategory::Ai => Self::AI,
+        }
+    }
+}
+
+impl Cli {
+    /// Registro de herramientas limitado por `--tools-include`/`--tools-exclude`;
+    /// sin esos flags se usa el registro completo por defecto
+    pub fn tool_registry(&self) -> Option<crate::tools::ToolRegistry> {
+        if self.tools_include.is_empty() && self.tools_exclude.is_empty() {
+            return None;
+        }
+        let builder = self.tools_include.iter()
+            .fold(crate::tools::ToolRegistry::builder(), |builder, category| builder.include((*category).into()));
+        let builder = self.tools_exclude.iter()
+            .fold(builder, |builder, category| builder.exclude((*category).into()));
+        Some(builder.build())
+    }
+
+    /// Construye el gate de aprobación a partir de los flags globales
+    pub fn approval_gate(&self) -> Result<crate::tools::approval::ApprovalGate, crate::tools::ToolError> {
+        use crate::tools::approval::{ApprovalGate, ApprovalMode, ApprovalPolicy};
+
+        let mode = if self.dangerously_skip_permissions {
+            ApprovalMode::Bypass
+        } else if let Some(path) = &self.approval_policy {
+            ApprovalMode::Policy(ApprovalPolicy::load(path)?)
+        } else {
+            ApprovalMode::Interactive
+        };
+
+        Ok(ApprovalGate::new(mode, self.approval_threshold.into())
+            .with_plan_step_confirmation(!self.no_plan_approval))
+    }
+
+    /// Instala el tracer global si se pidió exportar trazas
+    pub fn trace_export(&self) -> Option<crate::performance::trace::TraceExport> {
+        if self.trace_file.is_none() && self.otlp_endpoint.is_none() {
+            return None;
+        }
+        Some(crate::performance::trace::TraceExport {
+            tracer: crate::performance::trace::install_tracer(crate::performance::Tracer::new()),
+            file: self.trace_file.clone(),
+            endpoint: self.otlp_endpoint.clone(),
+        })
+    }
+
+    /// Instala el exportador de Prometheus si se pidió por flags
+    pub fn metrics_exporter(&self) -> Result<Option<crate::performance::MetricsExporter>, crate::performance::ExporterError> {
+        if self.metrics_addr.is_none() && self.metrics_file.is_none() {
+            return Ok(None);
+        }
+        crate::performance::MetricsExporter::install(self.metrics_addr, self.metrics_file.clone()).map(Some)
+    }
 }
 
 #[derive(Subcommand)]
@@ -332,19 +471,63 @@ pub enum PerformanceCommands {
         /// Output file
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// OTLP/JSON trace file to render as a flame-style breakdown
+        #[arg(long)]
+        trace: Option<PathBuf>,
+
+        /// Only include runs from this date on (YYYY-MM-DD, RFC 3339 or an age like 7d)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only include runs up to this date (YYYY-MM-DD, RFC 3339 or an age like 24h)
+        #[arg(long)]
+        until: Option<String>,
     },
     
     /// Analyze system bottlenecks
     #[command(about = "🔍 Analyze and identify system bottlenecks")]
     Bottleneck {
-        /// Auto-optimize found bottlenecks
+        /// Write safe config changes (concurrency, model per task class) to ~/.enjambre/config.toml
         #[arg(long)]
         auto_optimize: bool,
+
+        /// OTLP/JSON trace file with the spans to analyse
+        #[arg(long)]
+        trace: Option<PathBuf>,
+
+        /// Only analyse runs from this date on (YYYY-MM-DD, RFC 3339 or an age like 7d)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Apply the changes without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
     },
     
     /// Show token usage statistics
     #[command(about = "🪙 Display token usage and cost analysis")]
-    Tokens,
+    Tokens {
+        /// Number of days to show
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+
+        /// Daily budget in USD (defaults to daily_budget in ~/.enjambre/config.toml)
+        #[arg(long, value_name = "USD")]
+        budget: Option<f64>,
+
+        /// Chargeback report for all users, grouped by a task label (project, user, ticket...)
+        #[arg(long, value_name = "LABEL")]
+        group_by: Option<String>,
+
+        /// Chargeback report format (csv, json)
+        #[arg(long, default_value = "csv", requires = "group_by")]
+        format: String,
+
+        /// Write the chargeback report to a file instead of stdout
+        #[arg(short, long, requires = "group_by")]
+        output: Option<PathBuf>,
+    },
     
     /// Run system benchmark
     #[command(about = "⚡ Run comprehensive system benchmark")]
@@ -0,0 +1,129 @@
+// ============================================================================
+// MODEL CATALOG - Costo, latencia y éxito esperados por modelo
+// ============================================================================
+// Perfiles a priori de cada modelo (latencia base, ms por token de salida y
+// probabilidad de éxito por complejidad). Las observaciones del aprendizaje
+// adaptativo, cuando las hay, corrigen esos priors. Sobre las estimaciones
+// se calcula el frente de Pareto: menor costo, menor latencia y mayor éxito.
+// ============================================================================
+
+use super::{ModelChoice, TaskComplexity, MIN_EXPECTED_OUTPUT_TOKENS};
+use serde::{Deserialize, Serialize};
+
+/// Peso, en tareas ficticias, del prior de éxito frente a lo observado
+const PRIOR_WEIGHT: f64 = 5.0;
+
+/// Tareas observadas a partir de las que se usa la latencia medida
+const MIN_LATENCY_TRIALS: u64 = 3;
+
+/// Perfil a priori de un modelo
+#[derive(Debug, Clone)]
+pub struct ModelProfile {
+    pub model: ModelChoice,
+    pub base_latency_ms: f64,
+    pub ms_per_output_token: f64,
+    /// Probabilidad de éxito para Simple, Medium, Complex y Critical
+    pub success_by_complexity: [f64; 4],
+}
+
+impl ModelProfile {
+    pub fn expected_success(&self, complexity: &TaskComplexity) -> f64 {
+        let index = match complexity {
+            TaskComplexity::Simple => 0,
+            TaskComplexity::Medium => 1,
+            TaskComplexity::Complex => 2,
+            TaskComplexity::Critical => 3,
+        };
+        self.success_by_complexity[index]
+    }
+}
+
+/// Modelos catalogados
+pub fn model_catalog() -> Vec<ModelProfile> {
+    vec![
+        ModelProfile {
+            model: ModelChoice::Gemini15Flash,
+            base_latency_ms: 1500.0,
+            ms_per_output_token: 4.0,
+            success_by_complexity: [0.95, 0.85, 0.65, 0.5],
+        },
+        ModelProfile {
+            model: ModelChoice::Gemini15Pro,
+            base_latency_ms: 4000.0,
+            ms_per_output_token: 12.0,
+            success_by_complexity: [0.97, 0.92, 0.85, 0.75],
+        },
+        ModelProfile {
+            model: ModelChoice::Gemini15ProExp,
+            base_latency_ms: 5000.0,
+            ms_per_output_token: 15.0,
+            success_by_complexity: [0.97, 0.93, 0.88, 0.8],
+        },
+    ]
+}
+
+/// Resultados observados de un modelo para tareas similares
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelObservation {
+    pub trials: u64,
+    pub successes: u64,
+    pub mean_latency_ms: f64,
+}
+
+/// Estimación de un modelo para una tarea concreta
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelEstimate {
+    pub model: ModelChoice,
+    pub estimated_cost: f64,
+    pub estimated_latency_ms: f64,
+    pub expected_success: f64,
+    /// Tareas observadas que respaldan la estimación
+    pub observed_trials: u64,
+}
+
+impl ModelEstimate {
+    /// `self` es al menos igual de bueno en todo y mejor en algo
+    pub fn dominates(&self, other: &ModelEstimate) -> bool {
+        let no_worse = self.estimated_cost <= other.estimated_cost
+            && self.estimated_latency_ms <= other.estimated_latency_ms
+            && self.expected_success >= other.expected_success;
+        let better = self.estimated_cost < other.estimated_cost
+            || self.estimated_latency_ms < other.estimated_latency_ms
+            || self.expected_success > other.expected_success;
+        no_worse && better
+    }
+}
+
+/// Estima costo, latencia y éxito de un modelo del catálogo
+pub fn estimate(
+    profile: &ModelProfile,
+    complexity: &TaskComplexity,
+    input_tokens: u32,
+    observation: Option<&ModelObservation>,
+) -> ModelEstimate {
+    let output_tokens = input_tokens.max(MIN_EXPECTED_OUTPUT_TOKENS);
+    let prior_success = profile.expected_success(complexity);
+    let prior_latency = profile.base_latency_ms + profile.ms_per_output_token * output_tokens as f64;
+    let observation = observation.copied().unwrap_or_default();
+
+    ModelEstimate {
+        model: profile.model.clone(),
+        estimated_cost: profile.model.projected_cost(input_tokens),
+        estimated_latency_ms: if observation.trials >= MIN_LATENCY_TRIALS {
+            observation.mean_latency_ms
+        } else {
+            prior_latency
+        },
+        expected_success: (prior_success * PRIOR_WEIGHT + observation.successes as f64)
+            / (PRIOR_WEIGHT + observation.trials as f64),
+        observed_trials: observation.trials,
+    }
+}
+
+/// Estimaciones no dominadas por ninguna otra
+pub fn pareto_front(estimates: &[ModelEstimate]) -> Vec<ModelEstimate> {
+    estimates.iter()
+        .filter(|candidate| !estimates.iter().any(|other| other.dominates(candidate)))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,300 @@
+// ============================================================================
+// COMPLEXITY ANALYSIS - Puntuación de la complejidad de una tarea
+// ============================================================================
+// Cada señal (longitud del prompt, contexto adjunto, lenguajes, pasos del
+// plan, herramientas, palabras clave en inglés y español e historial) suma o
+// resta a una puntuación en [0, 1] que se corta en cuatro tramos. El
+// resultado incluye la confianza (distancia al corte más cercano) y la
+// explicación factor a factor. `ComplexityScorer` permite sustituir la
+// heurística por un clasificador aprendido.
+// ============================================================================
+
+use super::{estimate_tokens, TaskComplexity};
+use serde::{Deserialize, Serialize};
+
+/// Cortes de la puntuación: Simple < 0.25 <= Medium < 0.5 <= Complex < 0.75 <= Critical
+const BOUNDARIES: [f64; 3] = [0.25, 0.5, 0.75];
+
+// Palabras completas, con sus formas habituales en inglés y español
+const SIMPLE_KEYWORDS: [&str; 14] = [
+    "simple", "basic", "trivial", "quick", "small", "hello world",
+    "básico", "básica", "sencillo", "sencilla", "pequeño", "pequeña", "rápido", "hola mundo",
+];
+const COMPLEX_KEYWORDS: [&str; 36] = [
+    "complex", "advanced", "architecture", "distributed", "concurrent", "concurrency", "optimize",
+    "optimization", "refactor", "refactoring", "security", "scalable", "scalability", "migrate", "migration",
+    "parallel", "complejo", "compleja", "avanzado", "avanzada", "arquitectura", "distribuido", "distribuida",
+    "concurrencia", "optimizar", "optimización", "refactorizar", "seguridad", "escalable", "escalabilidad",
+    "migrar", "migración", "paralelo", "paralela", "parallelism", "paralelismo",
+];
+/// Señales de criticidad con su peso: ninguna fija el tramo por sí sola,
+/// suman a la puntuación como el resto (hasta `CRITICAL_KEYWORDS_CAP`)
+const CRITICAL_KEYWORDS: [(&str, f64); 14] = [
+    ("mission-critical", 0.35), ("critical", 0.3), ("crítico", 0.3), ("crítica", 0.3),
+    ("críticos", 0.3), ("críticas", 0.3), ("critico", 0.3), ("critica", 0.3),
+    ("production", 0.15), ("producción", 0.15), ("produccion", 0.15),
+    ("urgent", 0.1), ("urgente", 0.1), ("urgently", 0.1),
+];
+const CRITICAL_KEYWORDS_CAP: f64 = 0.45;
+// "go" suelto es un verbo: solo cuenta "golang"
+const LANGUAGES: [&str; 14] = [
+    "rust", "python", "javascript", "typescript", "golang", "java", "kotlin",
+    "swift", "c++", "c#", "ruby", "php", "sql", "bash",
+];
+
+/// Resultados previos de tareas del mismo tipo
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HistoricalOutcome {
+    pub trials: u64,
+    pub success_rate: f64,
+}
+
+/// Señales disponibles para estimar la complejidad de una tarea
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComplexityInput {
+    pub description: String,
+    /// Caracteres de contexto adjunto (salidas de pasos previos, ficheros)
+    pub context_chars: usize,
+    /// Lenguajes pedidos explícitamente, además de los mencionados
+    pub languages: Vec<String>,
+    pub plan_steps: usize,
+    pub tool_count: usize,
+    pub history: Option<HistoricalOutcome>,
+}
+
+impl ComplexityInput {
+    pub fn new(description: &str) -> Self {
+        Self { description: description.to_string(), ..Default::default() }
+    }
+
+    pub fn with_context_chars(mut self, context_chars: usize) -> Self {
+        self.context_chars = context_chars;
+        self
+    }
+
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.languages.push(language.to_lowercase());
+        self
+    }
+
+    pub fn with_plan_steps(mut self, plan_steps: usize) -> Self {
+        self.plan_steps = plan_steps;
+        self
+    }
+
+    pub fn with_tool_count(mut self, tool_count: usize) -> Self {
+        self.tool_count = tool_count;
+        self
+    }
+
+    pub fn with_history(mut self, history: Option<HistoricalOutcome>) -> Self {
+        self.history = history;
+        self
+    }
+}
+
+/// Aporte de una señal a la puntuación
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplexityFactor {
+    pub signal: String,
+    pub contribution: f64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplexityAssessment {
+    pub complexity: TaskComplexity,
+    /// Confianza en [0, 1]
+    pub confidence: f64,
+    pub score: f64,
+    pub factors: Vec<ComplexityFactor>,
+}
+
+impl ComplexityAssessment {
+    /// Explicación legible de los factores que movieron la puntuación
+    pub fn explanation(&self) -> String {
+        if self.factors.is_empty() {
+            return "sin señales; complejidad media por defecto".to_string();
+        }
+        self.factors.iter()
+            .map(|factor| format!("{} {} ({:+.2})", factor.signal, factor.detail, factor.contribution))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Estrategia de estimación de complejidad
+pub trait ComplexityScorer: Send + Sync {
+    fn name(&self) -> &str;
+    fn assess(&self, input: &ComplexityInput) -> ComplexityAssessment;
+}
+
+/// Pesos de la heurística por defecto
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeuristicScorer {
+    /// Puntuación de partida (una petición corta sin más señales es media)
+    pub base: f64,
+    pub length_weight: f64,
+    pub context_weight: f64,
+    pub language_weight: f64,
+    pub plan_weight: f64,
+    pub tool_weight: f64,
+    /// Tareas previas mínimas para tener en cuenta el historial
+    pub min_history: u64,
+}
+
+impl Default for HeuristicScorer {
+    fn default() -> Self {
+        Self {
+            base: 0.3,
+            length_weight: 0.25,
+            context_weight: 0.15,
+            language_weight: 0.05,
+            plan_weight: 0.1,
+            tool_weight: 0.1,
+            min_history: 5,
+        }
+    }
+}
+
+impl ComplexityScorer for HeuristicScorer {
+    fn name(&self) -> &str {
+        "heuristic"
+    }
+
+    fn assess(&self, input: &ComplexityInput) -> ComplexityAssessment {
+        let text = input.description.to_lowercase();
+        let words: Vec<&str> = text
+            .split(|c: char| !(c.is_alphanumeric() || c == '+' || c == '#' || c == '-'))
+            .filter(|word| !word.is_empty())
+            .collect();
+        let mentions = |keyword: &str| {
+            let phrase: Vec<&str> = keyword.split(' ').collect();
+            words.windows(phrase.len()).any(|window| window == phrase.as_slice())
+        };
+
+        let mut factors = Vec::new();
+        let mut push = |signal: &str, contribution: f64, detail: String| {
+            if contribution.abs() >= 0.005 {
+                factors.push(ComplexityFactor { signal: signal.to_string(), contribution, detail });
+            }
+        };
+
+        // Longitud: escala logarítmica de 50 a 2000 tokens
+        let tokens = estimate_tokens(&input.description);
+        let length = ((tokens.max(50) as f64 / 50.0).ln() / 40f64.ln()).min(1.0);
+        push("longitud", length * self.length_weight, format!("{} tokens", tokens));
+
+        let context_tokens = input.context_chars.div_ceil(4);
+        let context = (context_tokens as f64 / 20_000.0).min(1.0);
+        push("contexto", context * self.context_weight, format!("{} tokens adjuntos", context_tokens));
+
+        let mut languages: Vec<String> = LANGUAGES.iter()
+            .filter(|language| words.contains(language))
+            .map(|language| language.to_string())
+            .collect();
+        for language in &input.languages {
+            if !languages.contains(language) {
+                languages.push(language.clone());
+            }
+        }
+        push(
+            "lenguajes",
+            languages.len().saturating_sub(1).min(2) as f64 * self.language_weight,
+            languages.join(", "),
+        );
+
+        if input.plan_steps > 1 {
+            push("plan", (input.plan_steps as f64 / 10.0).min(1.0) * self.plan_weight, format!("{} pasos", input.plan_steps));
+        }
+        push("herramientas", (input.tool_count as f64 / 5.0).min(1.0) * self.tool_weight, format!("{} herramientas", input.tool_count));
+
+        let simple: Vec<&str> = SIMPLE_KEYWORDS.iter().copied().filter(|k| mentions(k)).collect();
+        if !simple.is_empty() {
+            push("palabras clave", -0.2, format!("simples: {}", simple.join(", ")));
+        }
+        let complex: Vec<&str> = COMPLEX_KEYWORDS.iter().copied().filter(|k| mentions(k)).collect();
+        if !complex.is_empty() {
+            push("palabras clave", (0.15 + 0.1 * complex.len() as f64).min(0.4), format!("complejas: {}", complex.join(", ")));
+        }
+        let critical: Vec<(&str, f64)> = CRITICAL_KEYWORDS.iter().copied().filter(|(k, _)| mentions(k)).collect();
+        if !critical.is_empty() {
+            let names: Vec<&str> = critical.iter().map(|(k, _)| *k).collect();
+            let weight = critical.iter().map(|(_, w)| w).sum::<f64>().min(CRITICAL_KEYWORDS_CAP);
+            push("palabras clave", weight, format!("críticas: {}", names.join(", ")));
+        }
+
+        if let Some(history) = input.history.filter(|history| history.trials >= self.min_history) {
+            let detail = format!("{:.0}% de éxito en {} tareas", history.success_rate * 100.0, history.trials);
+            if history.success_rate < 0.6 {
+                push("historial", 0.15, detail);
+            } else if history.success_rate > 0.95 {
+                push("historial", -0.1, detail);
+            }
+        }
+
+        let score = (self.base + factors.iter().map(|f| f.contribution).sum::<f64>()).clamp(0.0, 1.0);
+
+        let complexity = match score {
+            s if s < BOUNDARIES[0] => TaskComplexity::Simple,
+            s if s < BOUNDARIES[1] => TaskComplexity::Medium,
+            s if s < BOUNDARIES[2] => TaskComplexity::Complex,
+            _ => TaskComplexity::Critical,
+        };
+
+        // Confianza: distancia al corte más cercano y cantidad de evidencia
+        let margin = BOUNDARIES.iter().map(|b| (score - b).abs()).fold(f64::MAX, f64::min);
+        let evidence = (factors.len() as f64 / 4.0).min(1.0);
+        let confidence = (0.4 + margin * 2.0 + evidence * 0.2).min(0.95);
+
+        ComplexityAssessment { complexity, confidence, score, factors }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assess(input: ComplexityInput) -> ComplexityAssessment {
+        HeuristicScorer::default().assess(&input)
+    }
+
+    #[test]
+    fn test_scores_signals_in_both_languages() {
+        assert_eq!(assess(ComplexityInput::new("write a simple hello world")).complexity, TaskComplexity::Simple);
+        assert_eq!(assess(ComplexityInput::new("una función sencilla")).complexity, TaskComplexity::Simple);
+        assert_eq!(assess(ComplexityInput::new("parse a CSV file")).complexity, TaskComplexity::Medium);
+        assert_eq!(
+            assess(ComplexityInput::new("design a distributed, scalable architecture")).complexity,
+            TaskComplexity::Complex
+        );
+        let critical = assess(ComplexityInput::new("fallo crítico en producción de un sistema distribuido complejo"));
+        assert_eq!(critical.complexity, TaskComplexity::Critical);
+        assert!(critical.explanation().contains("crítico"));
+
+        let plain = assess(ComplexityInput::new("parse a CSV file"));
+        let loaded = assess(
+            ComplexityInput::new("parse a CSV file")
+                .with_context_chars(80_000)
+                .with_plan_steps(8)
+                .with_tool_count(4),
+        );
+        assert!(loaded.score > plain.score);
+        assert_eq!(loaded.complexity, TaskComplexity::Complex);
+    }
+
+    #[test]
+    fn test_keywords_match_whole_words_and_only_weigh() {
+        // Una sola señal de criticidad no basta para el tramo crítico
+        for prompt in ["deploy the parser to production", "urgent: parse a CSV file", "fix a critical typo"] {
+            assert_ne!(assess(ComplexityInput::new(prompt)).complexity, TaskComplexity::Critical, "{}", prompt);
+        }
+        // Ni prefijos ni el verbo "go"
+        let criticism = assess(ComplexityInput::new("write a criticism of this essay"));
+        assert!(!criticism.explanation().contains("críticas"));
+        let go = assess(ComplexityInput::new("let's go write a python script"));
+        assert!(!go.factors.iter().any(|factor| factor.signal == "lenguajes"));
+        let golang = assess(ComplexityInput::new("port this python script to golang"));
+        assert!(golang.explanation().contains("python, golang"));
+    }
+}
@@ -0,0 +1,433 @@
+// ============================================================================
+// SPEND LEDGER - Gasto diario persistente
+// ============================================================================
+// Acumula el gasto por día, proyecto y usuario en
+// `~/.enjambre/budget/ledger.json`. El orquestador consulta el gasto del día
+// antes de cada llamada para aplicar `CostConstraints.daily_budget` y anota
+// el costo real al terminar cada tarea. Al guardar se toma un bloqueo
+// consultivo sobre `ledger.lock`, se vuelve a leer el fichero y se suman
+// solo los movimientos nuevos, de modo que varias ejecuciones simultáneas no
+// se pisan el gasto. Junto al gasto se acumula el ahorro frente al modelo de
+// referencia, desglosado por fuente.
+//
+// Las etiquetas de la tarea (`project`, `user`, `ticket`...) deciden a quién
+// se imputa cada movimiento: `project` y `user` sustituyen al ámbito del
+// ledger y el resto se guarda en la entrada, de modo que los informes de
+// refacturación pueden agrupar por cualquiera de ellas.
+// ============================================================================
+
+use super::CostSavings;
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Etiqueta con el proyecto al que se imputa el gasto
+pub const PROJECT_LABEL: &str = "project";
+/// Etiqueta con el usuario al que se imputa el gasto
+pub const USER_LABEL: &str = "user";
+/// Grupo de los informes para las entradas sin la etiqueta pedida
+pub const UNLABELED: &str = "(sin etiqueta)";
+
+/// Gasto acumulado de un día, proyecto y usuario
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpendEntry {
+    pub day: NaiveDate,
+    pub project: String,
+    pub user: String,
+    pub requests: u64,
+    /// Llamadas rechazadas por presupuesto
+    #[serde(default)]
+    pub refused: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    /// Ahorro total frente al modelo de referencia
+    #[serde(default)]
+    pub cost_saved_usd: f64,
+    /// Parte del ahorro debida a aciertos de caché
+    #[serde(default)]
+    pub saved_by_cache_usd: f64,
+    /// Parte del ahorro debida a la compresión de prompts
+    #[serde(default)]
+    pub saved_by_compression_usd: f64,
+    /// Resto de etiquetas de las tareas (p. ej. `ticket`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl SpendEntry {
+    fn empty(key: &EntryKey) -> Self {
+        let (day, project, user, labels) = key;
+        Self {
+            day: *day,
+            project: project.clone(),
+            user: user.clone(),
+            requests: 0,
+            refused: 0,
+            input_tokens: 0,
+            output_tokens: 0,
+            cost_usd: 0.0,
+            cost_saved_usd: 0.0,
+            saved_by_cache_usd: 0.0,
+            saved_by_compression_usd: 0.0,
+            labels: labels.clone(),
+        }
+    }
+
+    fn key(&self) -> EntryKey {
+        (self.day, self.project.clone(), self.user.clone(), self.labels.clone())
+    }
+
+    /// Valor de una etiqueta; `project` y `user` siempre están
+    pub fn label(&self, name: &str) -> Option<&str> {
+        match name {
+            PROJECT_LABEL => Some(&self.project),
+            USER_LABEL => Some(&self.user),
+            _ => self.labels.get(name).map(String::as_str),
+        }
+    }
+
+    fn add(&mut self, other: &SpendEntry) {
+        self.requests += other.requests;
+        self.refused += other.refused;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost_usd += other.cost_usd;
+        self.cost_saved_usd += other.cost_saved_usd;
+        self.saved_by_cache_usd += other.saved_by_cache_usd;
+        self.saved_by_compression_usd += other.saved_by_compression_usd;
+    }
+}
+
+type EntryKey = (NaiveDate, String, String, BTreeMap<String, String>);
+
+pub struct SpendLedger {
+    entries: BTreeMap<EntryKey, SpendEntry>,
+    /// Movimientos aún no guardados
+    pending: BTreeMap<EntryKey, SpendEntry>,
+    project: String,
+    user: String,
+    path: Option<PathBuf>,
+}
+
+impl Default for SpendLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpendLedger {
+    /// Ledger en memoria, sin persistencia
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            project: default_project(),
+            user: default_user(),
+            path: None,
+        }
+    }
+
+    /// Ruta por defecto del ledger
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".enjambre").join("budget").join("ledger.json"))
+    }
+
+    /// Carga el ledger de la ruta por defecto (vacío si no existe)
+    pub fn load_or_default() -> Self {
+        match Self::default_path() {
+            Some(path) => Self::load_from(&path),
+            None => Self::new(),
+        }
+    }
+
+    /// Carga el ledger de un fichero; se guardará en el mismo sitio
+    pub fn load_from(path: &Path) -> Self {
+        let mut ledger = Self::new();
+        ledger.entries = read_entries(path);
+        ledger.path = Some(path.to_path_buf());
+        ledger
+    }
+
+    /// Proyecto y usuario a los que se imputa el gasto
+    pub fn with_scope(mut self, project: &str, user: &str) -> Self {
+        self.project = project.to_string();
+        self.user = user.to_string();
+        self
+    }
+
+    pub fn project(&self) -> &str {
+        &self.project
+    }
+
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    /// Etiquetas efectivas de una tarea: el ámbito del ledger completado
+    /// con las etiquetas propias, que tienen prioridad
+    pub fn resolve_labels(&self, labels: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+        let mut resolved = BTreeMap::from([
+            (PROJECT_LABEL.to_string(), self.project.clone()),
+            (USER_LABEL.to_string(), self.user.clone()),
+        ]);
+        resolved.extend(labels.iter().map(|(key, value)| (key.clone(), value.clone())));
+        resolved
+    }
+
+    /// Anota el costo real de una tarea y su ahorro frente a la referencia
+    pub fn record(
+        &mut self,
+        labels: &BTreeMap<String, String>,
+        cost_usd: f64,
+        savings: Option<&CostSavings>,
+        input_tokens: u32,
+        output_tokens: u32,
+    ) {
+        self.apply(labels, |entry| {
+            entry.requests += 1;
+            entry.input_tokens += input_tokens as u64;
+            entry.output_tokens += output_tokens as u64;
+            entry.cost_usd += cost_usd;
+            if let Some(savings) = savings {
+                entry.cost_saved_usd += savings.total_usd();
+                entry.saved_by_cache_usd += savings.cache_usd;
+                entry.saved_by_compression_usd += savings.compression_usd;
+            }
+        });
+    }
+
+    /// Anota una llamada rechazada por presupuesto
+    pub fn record_refusal(&mut self, labels: &BTreeMap<String, String>) {
+        self.apply(labels, |entry| entry.refused += 1);
+    }
+
+    fn apply(&mut self, labels: &BTreeMap<String, String>, update: impl Fn(&mut SpendEntry)) {
+        let mut labels = self.resolve_labels(labels);
+        let project = labels.remove(PROJECT_LABEL).unwrap_or_default();
+        let user = labels.remove(USER_LABEL).unwrap_or_default();
+        let key = (Local::now().date_naive(), project, user, labels);
+        for entries in [&mut self.entries, &mut self.pending] {
+            update(entries.entry(key.clone()).or_insert_with(|| SpendEntry::empty(&key)));
+        }
+    }
+
+    /// Gasto total de un día, de todos los proyectos y usuarios: las
+    /// etiquetas solo deciden a quién se imputa, no eximen del presupuesto
+    pub fn spent_on(&self, day: NaiveDate) -> f64 {
+        self.entries.values()
+            .filter(|entry| entry.day == day)
+            .fold(0.0, |total, entry| total + entry.cost_usd)
+    }
+
+    pub fn spent_today(&self) -> f64 {
+        self.spent_on(Local::now().date_naive())
+    }
+
+    /// Gasto de hoy de todas las entradas con `label = value`, de cualquier usuario
+    pub fn spent_today_with_label(&self, label: &str, value: &str) -> f64 {
+        let today = Local::now().date_naive();
+        self.entries.values()
+            .filter(|entry| entry.day == today && entry.label(label) == Some(value))
+            .fold(0.0, |total, entry| total + entry.cost_usd)
+    }
+
+    /// Ahorro acumulado del usuario en todos sus proyectos y días
+    pub fn total_saved(&self) -> f64 {
+        self.entries.values()
+            .filter(|entry| entry.user == self.user)
+            .fold(0.0, |total, entry| total + entry.cost_saved_usd)
+    }
+
+    /// Todas las entradas, por día, proyecto y usuario
+    pub fn entries(&self) -> impl Iterator<Item = &SpendEntry> {
+        self.entries.values()
+    }
+
+    /// Guarda los movimientos pendientes si el ledger es persistente
+    pub fn save(&mut self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Sumar lo nuevo a lo que haya escrito cualquier otra ejecución, sin
+        // que otra pueda guardar entre la lectura y el rename
+        let _lock = lock_sibling(path)?;
+        let mut entries = read_entries(path);
+        for (key, delta) in std::mem::take(&mut self.pending) {
+            entries.entry(key).or_insert_with_key(SpendEntry::empty).add(&delta);
+        }
+        let content = serde_json::to_string_pretty(&entries.values().collect::<Vec<_>>())
+            .map_err(std::io::Error::other)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(tmp, path)?;
+        self.entries = entries;
+        Ok(())
+    }
+}
+
+/// Bloqueo consultivo exclusivo sobre `<fichero>.lock`, junto a `path`.
+/// Espera a que lo suelte otro proceso y se libera al soltar el `File`.
+pub(crate) fn lock_sibling(path: &Path) -> std::io::Result<std::fs::File> {
+    let lock = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("lock"))?;
+    lock.lock()?;
+    Ok(lock)
+}
+
+// ============================================================================
+// REFACTURACIÓN POR ETIQUETA
+// ============================================================================
+
+/// Gasto agregado de un valor de etiqueta
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChargebackRow {
+    pub group: String,
+    pub requests: u64,
+    pub refused: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    pub cost_saved_usd: f64,
+}
+
+/// Agrupa el gasto desde `since` por el valor de la etiqueta `group_by`,
+/// de todos los usuarios, del grupo más caro al más barato
+pub fn chargeback<'a>(
+    entries: impl Iterator<Item = &'a SpendEntry>,
+    group_by: &str,
+    since: NaiveDate,
+) -> Vec<ChargebackRow> {
+    let mut groups: BTreeMap<String, ChargebackRow> = BTreeMap::new();
+    for entry in entries.filter(|entry| entry.day >= since) {
+        let group = entry.label(group_by).unwrap_or(UNLABELED);
+        let row = groups.entry(group.to_string())
+            .or_insert_with(|| ChargebackRow { group: group.to_string(), ..Default::default() });
+        row.requests += entry.requests;
+        row.refused += entry.refused;
+        row.input_tokens += entry.input_tokens;
+        row.output_tokens += entry.output_tokens;
+        row.cost_usd += entry.cost_usd;
+        row.cost_saved_usd += entry.cost_saved_usd;
+    }
+    let mut rows: Vec<ChargebackRow> = groups.into_values().collect();
+    rows.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd));
+    rows
+}
+
+fn read_entries(path: &Path) -> BTreeMap<EntryKey, SpendEntry> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return BTreeMap::new();
+    };
+    match serde_json::from_str::<Vec<SpendEntry>>(&content) {
+        Ok(entries) => entries.into_iter().map(|entry| (entry.key(), entry)).collect(),
+        Err(e) => {
+            log::warn!("⚠️ Ledger de gasto inválido en {}: {}", path.display(), e);
+            BTreeMap::new()
+        }
+    }
+}
+
+/// Proyecto por defecto: `ENJAMBRE_PROJECT` o el nombre del directorio actual
+fn default_project() -> String {
+    std::env::var("ENJAMBRE_PROJECT").ok()
+        .or_else(|| {
+            std::env::current_dir().ok()
+                .and_then(|dir| dir.file_name().map(|name| name.to_string_lossy().into_owned()))
+        })
+        .unwrap_or_else(|| "default".to_string())
+}
+
+fn default_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost_optimizer::ModelChoice;
+
+    #[test]
+    fn test_save_merges_concurrent_spend() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.json");
+
+        let mut first = SpendLedger::load_from(&path).with_scope("api", "ana");
+        let mut second = SpendLedger::load_from(&path).with_scope("api", "ana");
+        let none = BTreeMap::new();
+        first.record(&none, 0.25, None, 1000, 500);
+        let savings = CostSavings::compute(&ModelChoice::Gemini15Pro, ModelChoice::Gemini15Flash.price_per_million(), 2000, 800, 0, false, 0.5);
+        second.record(&none, 0.5, Some(&savings), 2000, 800);
+        second.record_refusal(&none);
+        first.save().unwrap();
+        second.save().unwrap();
+
+        let ledger = SpendLedger::load_from(&path).with_scope("web", "ana");
+        assert!((ledger.spent_today() - 0.75).abs() < 1e-9);
+        let entry = ledger.entries().next().unwrap();
+        assert_eq!((entry.requests, entry.refused, entry.input_tokens), (2, 1, 3000));
+        assert!((ledger.total_saved() - savings.total_usd()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_concurrent_saves_keep_every_movement() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.json");
+
+        let savers: Vec<_> = (0..8).map(|_| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                for _ in 0..5 {
+                    let mut ledger = SpendLedger::load_from(&path).with_scope("api", "ana");
+                    ledger.record(&BTreeMap::new(), 0.5, None, 10, 10);
+                    ledger.save().unwrap();
+                }
+            })
+        }).collect();
+        for saver in savers {
+            saver.join().unwrap();
+        }
+
+        let ledger = SpendLedger::load_from(&path);
+        assert!((ledger.spent_today() - 20.0).abs() < 1e-9);
+        assert_eq!(ledger.entries().map(|entry| entry.requests).sum::<u64>(), 40);
+    }
+
+    #[test]
+    fn test_labels_attribute_spend_and_group_chargeback() {
+        let mut ledger = SpendLedger::new().with_scope("api", "ana");
+        let labels = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+        };
+        ledger.record(&labels(&[("ticket", "OPS-1")]), 0.25, None, 100, 50);
+        ledger.record(&labels(&[("project", "web"), ("user", "luis"), ("ticket", "OPS-1")]), 0.5, None, 200, 80);
+        ledger.record(&labels(&[("project", "web")]), 1.0, None, 400, 90);
+
+        assert!((ledger.spent_today_with_label("project", "web") - 1.5).abs() < 1e-9);
+        assert!((ledger.spent_today_with_label("ticket", "OPS-1") - 0.75).abs() < 1e-9);
+        // `user` de la etiqueta decide la imputación pero no escapa del presupuesto global
+        assert_eq!(ledger.entries().filter(|entry| entry.user == "luis").count(), 1);
+        assert!((ledger.spent_today() - 1.75).abs() < 1e-9);
+
+        let today = Local::now().date_naive();
+        let by_project = chargeback(ledger.entries(), "project", today);
+        assert_eq!(by_project.iter().map(|row| row.group.as_str()).collect::<Vec<_>>(), ["web", "api"]);
+        assert_eq!((by_project[0].requests, by_project[0].input_tokens), (2, 600));
+        let by_ticket = chargeback(ledger.entries(), "ticket", today);
+        assert_eq!(by_ticket[0].group, UNLABELED);
+        assert!((by_ticket[1].cost_usd - 0.75).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,323 @@
+// ============================================================================
+// COST OPTIMIZER - Optimizador de Costos para Modelos de IA
+// ============================================================================
+
+pub mod catalog;
+pub mod complexity;
+pub mod ledger;
+pub mod savings;
+
+pub use catalog::{model_catalog, ModelEstimate, ModelObservation, ModelProfile};
+pub use complexity::{ComplexityAssessment, ComplexityInput, ComplexityScorer, HeuristicScorer, HistoricalOutcome};
+pub use ledger::{ChargebackRow, SpendEntry, SpendLedger};
+pub use savings::CostSavings;
+
+use crate::FlowError;
+use serde::{Deserialize, Serialize};
+
+/// Tokens de salida mínimos que se suponen al proyectar el costo de una llamada
+pub const MIN_EXPECTED_OUTPUT_TOKENS: u32 = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ModelChoice {
+    Gemini15Flash,
+    Gemini15Pro,
+    Gemini15ProExp,
+    Auto,
+}
+
+impl ModelChoice {
+    /// Precio en USD por millón de tokens (entrada, salida). `Auto` se
+    /// proyecta con el precio de Pro, el modelo por defecto del adaptador.
+    pub fn price_per_million(&self) -> (f64, f64) {
+        match self {
+            ModelChoice::Gemini15Flash => (0.075, 0.30),
+            ModelChoice::Gemini15Pro | ModelChoice::Gemini15ProExp | ModelChoice::Auto => (1.25, 10.00),
+        }
+    }
+
+    /// Costo de una llamada con estos tokens
+    pub fn cost(&self, input_tokens: u32, output_tokens: u32) -> f64 {
+        cost_at(self.price_per_million(), input_tokens, output_tokens)
+    }
+
+    /// Costo proyectado a partir del prompt: la salida se estima como mínimo
+    /// del tamaño del prompt
+    pub fn projected_cost(&self, input_tokens: u32) -> f64 {
+        projected_cost_at(self.price_per_million(), input_tokens)
+    }
+
+    /// Nombre del modelo tal como lo informan los adaptadores en `model_used`
+    pub fn from_label(label: &str) -> Option<ModelChoice> {
+        serde_json::from_value(serde_json::Value::String(label.to_string())).ok()
+    }
+
+    /// Siguiente modelo más barato al que degradar, si lo hay
+    pub fn cheaper(&self) -> Option<ModelChoice> {
+        match self {
+            ModelChoice::Gemini15ProExp => Some(ModelChoice::Gemini15Pro),
+            ModelChoice::Gemini15Pro | ModelChoice::Auto => Some(ModelChoice::Gemini15Flash),
+            ModelChoice::Gemini15Flash => None,
+        }
+    }
+}
+
+/// Costo de una llamada con un precio (entrada, salida) por millón de tokens
+pub fn cost_at(price: (f64, f64), input_tokens: u32, output_tokens: u32) -> f64 {
+    price.0 * input_tokens as f64 / 1_000_000.0 + price.1 * output_tokens as f64 / 1_000_000.0
+}
+
+/// Costo proyectado de un prompt con un precio por millón de tokens
+pub fn projected_cost_at(price: (f64, f64), input_tokens: u32) -> f64 {
+    cost_at(price, input_tokens, input_tokens.max(MIN_EXPECTED_OUTPUT_TOKENS))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TaskComplexity {
+    Simple,
+    Medium,
+    Complex,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PriorityLevel {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostConstraints {
+    pub max_cost_per_request: Option<f64>,
+    pub daily_budget: Option<f64>,
+    pub priority: PriorityLevel,
+    /// Modelo de referencia contra el que se mide el ahorro
+    #[serde(default = "default_baseline_model")]
+    pub baseline_model: ModelChoice,
+    /// Presupuestos diarios por etiqueta (p. ej. `project = web`)
+    #[serde(default)]
+    pub label_budgets: Vec<LabelBudget>,
+}
+
+/// Presupuesto diario del gasto de todas las tareas con `label = value`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelBudget {
+    pub label: String,
+    pub value: String,
+    pub daily_usd: f64,
+}
+
+impl LabelBudget {
+    pub fn applies_to(&self, labels: &std::collections::BTreeMap<String, String>) -> bool {
+        labels.get(&self.label) == Some(&self.value)
+    }
+}
+
+fn default_baseline_model() -> ModelChoice {
+    ModelChoice::Gemini15Pro
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationRecommendation {
+    pub model: ModelChoice,
+    pub reason: String,
+    pub estimated_cost: f64,
+    pub confidence: f64,
+    #[serde(default)]
+    pub estimated_latency_ms: f64,
+    #[serde(default)]
+    pub expected_success: f64,
+}
+
+/// Resultado de comprobar el presupuesto antes de una llamada
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetDecision {
+    pub model: ModelChoice,
+    pub projected_cost_usd: f64,
+    /// Modelo elegido originalmente, si hubo que degradarlo
+    pub downgraded_from: Option<ModelChoice>,
+}
+
+pub struct CostOptimizer {
+    scorer: Box<dyn ComplexityScorer>,
+}
+
+impl CostOptimizer {
+    pub fn new() -> Self {
+        Self { scorer: Box::new(HeuristicScorer::default()) }
+    }
+
+    /// Sustituye la heurística de complejidad (p. ej. por un clasificador aprendido)
+    pub fn with_scorer(mut self, scorer: Box<dyn ComplexityScorer>) -> Self {
+        self.scorer = scorer;
+        self
+    }
+
+    pub fn assess_complexity(&self, input: &ComplexityInput) -> ComplexityAssessment {
+        self.scorer.assess(input)
+    }
+    
+    pub fn optimize_model_selection(
+        &self,
+        complexity: TaskComplexity,
+        constraints: &CostConstraints,
+    ) -> ModelChoice {
+        match (complexity, &constraints.priority) {
+            (TaskComplexity::Simple, _) => ModelChoice::Gemini15Flash,
+            (TaskComplexity::Medium, PriorityLevel::Low) => ModelChoice::Gemini15Flash,
+            (TaskComplexity::Medium, _) => ModelChoice::Gemini15Pro,
+            (TaskComplexity::Complex, PriorityLevel::Critical) => ModelChoice::Gemini15ProExp,
+            (TaskComplexity::Complex, _) => ModelChoice::Gemini15Pro,
+            (TaskComplexity::Critical, _) => ModelChoice::Gemini15ProExp,
+        }
+    }
+    
+    /// Comprueba el límite por petición y el presupuesto diario restante
+    /// antes de una llamada. Si el modelo elegido no cabe se degrada al
+    /// siguiente más barato; si ninguno cabe, la llamada se rechaza. `price`
+    /// da el precio por millón de tokens del adaptador que serviría cada modelo.
+    pub fn enforce_budget(
+        &self,
+        model: ModelChoice,
+        price: impl Fn(&ModelChoice) -> (f64, f64),
+        input_tokens: u32,
+        max_cost_per_request: Option<f64>,
+        daily_budget: Option<f64>,
+        spent_today: f64,
+    ) -> Result<BudgetDecision, FlowError> {
+        if let Some(budget) = daily_budget {
+            if spent_today >= budget {
+                return Err(FlowError::DailyBudgetExceeded(budget));
+            }
+        }
+
+        let mut candidate = model.clone();
+        loop {
+            let projected = projected_cost_at(price(&candidate), input_tokens);
+            let within_request = max_cost_per_request.is_none_or(|limit| projected <= limit);
+            let within_daily = daily_budget.is_none_or(|budget| spent_today + projected <= budget);
+            if within_request && within_daily {
+                return Ok(BudgetDecision {
+                    downgraded_from: (candidate != model).then_some(model),
+                    model: candidate,
+                    projected_cost_usd: projected,
+                });
+            }
+            candidate = match candidate.cheaper() {
+                Some(cheaper) => cheaper,
+                None if !within_daily => return Err(FlowError::DailyBudgetExceeded(daily_budget.unwrap_or_default())),
+                None => return Err(FlowError::CostLimitExceeded(max_cost_per_request.unwrap_or_default())),
+            };
+        }
+    }
+
+    /// Estima costo, latencia y éxito de cada modelo del catálogo para la
+    /// tarea y devuelve el frente de Pareto, del más barato al más caro.
+    /// `observe` aporta lo aprendido de cada modelo para esa complejidad.
+    pub fn get_recommendations(
+        &self,
+        input: &ComplexityInput,
+        observe: impl Fn(&TaskComplexity, &ModelChoice) -> Option<ModelObservation>,
+    ) -> Vec<OptimizationRecommendation> {
+        let assessment = self.assess_complexity(input);
+        let input_tokens = estimate_tokens(&input.description) + input.context_chars.div_ceil(4) as u32;
+        let estimates: Vec<ModelEstimate> = model_catalog().iter()
+            .map(|profile| {
+                let observation = observe(&assessment.complexity, &profile.model);
+                catalog::estimate(profile, &assessment.complexity, input_tokens, observation.as_ref())
+            })
+            .collect();
+
+        let mut front = catalog::pareto_front(&estimates);
+        front.sort_by(|a, b| a.estimated_cost.total_cmp(&b.estimated_cost));
+
+        let best = |value: fn(&ModelEstimate) -> f64| {
+            front.iter().map(value).fold(f64::NAN, f64::min)
+        };
+        let cheapest = best(|e| e.estimated_cost);
+        let fastest = best(|e| e.estimated_latency_ms);
+        let safest = -best(|e| -e.expected_success);
+
+        front.iter().map(|estimate| {
+            let mut reasons = Vec::new();
+            if estimate.estimated_cost <= cheapest {
+                reasons.push("el más barato");
+            }
+            if estimate.estimated_latency_ms <= fastest {
+                reasons.push("el más rápido");
+            }
+            if estimate.expected_success >= safest {
+                reasons.push("la mayor probabilidad de éxito");
+            }
+            if reasons.is_empty() {
+                reasons.push("equilibrio entre costo y calidad");
+            }
+            let evidence = (estimate.observed_trials as f64 / 20.0).min(1.0);
+            OptimizationRecommendation {
+                model: estimate.model.clone(),
+                reason: format!(
+                    "{} para una tarea {:?} ({} tareas observadas)",
+                    reasons.join(", "), assessment.complexity, estimate.observed_trials
+                ),
+                estimated_cost: estimate.estimated_cost,
+                confidence: assessment.confidence * (0.6 + 0.4 * evidence),
+                estimated_latency_ms: estimate.estimated_latency_ms,
+                expected_success: estimate.expected_success,
+            }
+        }).collect()
+    }
+}
+
+/// Estimación aproximada de tokens (~4 caracteres por token)
+pub fn estimate_tokens(text: &str) -> u32 {
+    text.chars().count().div_ceil(4) as u32
+}
+
+/// Complejidad de una descripción suelta, con la heurística por defecto
+pub fn analyze_task_complexity(task: &str) -> TaskComplexity {
+    HeuristicScorer::default().assess(&ComplexityInput::new(task)).complexity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_downgrades_then_refuses() {
+        let optimizer = CostOptimizer::new();
+        // 10k tokens: Pro ≈ $0.1125, Flash ≈ $0.00375
+        let decision = optimizer.enforce_budget(ModelChoice::Gemini15Pro, ModelChoice::price_per_million, 10_000, Some(0.05), Some(1.0), 0.0).unwrap();
+        assert_eq!(decision.model, ModelChoice::Gemini15Flash);
+        assert_eq!(decision.downgraded_from, Some(ModelChoice::Gemini15Pro));
+
+        let decision = optimizer.enforce_budget(ModelChoice::Gemini15Pro, ModelChoice::price_per_million, 10_000, None, Some(1.0), 0.5).unwrap();
+        assert_eq!(decision.downgraded_from, None);
+
+        let refused = optimizer.enforce_budget(ModelChoice::Gemini15Pro, ModelChoice::price_per_million, 10_000, None, Some(1.0), 0.999);
+        assert!(matches!(refused, Err(FlowError::DailyBudgetExceeded(_))));
+        let refused = optimizer.enforce_budget(ModelChoice::Gemini15Flash, ModelChoice::price_per_million, 10_000, Some(0.0001), None, 0.0);
+        assert!(matches!(refused, Err(FlowError::CostLimitExceeded(_))));
+    }
+
+    #[test]
+    fn test_recommendations_are_pareto_optimal() {
+        let optimizer = CostOptimizer::new();
+        let input = ComplexityInput::new("design a distributed, scalable architecture");
+
+        let recommendations = optimizer.get_recommendations(&input, |_, _| None);
+        let models: Vec<ModelChoice> = recommendations.iter().map(|r| r.model.clone()).collect();
+        assert_eq!(models, vec![ModelChoice::Gemini15Flash, ModelChoice::Gemini15Pro, ModelChoice::Gemini15ProExp]);
+        assert!(recommendations[0].reason.contains("el más barato"));
+        assert!(recommendations[2].reason.contains("la mayor probabilidad de éxito"));
+
+        // Si Flash acierta siempre en esta clase, domina a Pro: Pro sale del frente
+        let learned = optimizer.get_recommendations(&input, |_, model| {
+            (*model == ModelChoice::Gemini15Flash)
+                .then_some(ModelObservation { trials: 40, successes: 40, mean_latency_ms: 1200.0 })
+        });
+        assert_eq!(learned.len(), 1);
+        assert_eq!(learned[0].model, ModelChoice::Gemini15Flash);
+    }
+}
@@ -0,0 +1,99 @@
+// ============================================================================
+// COST SAVINGS - Ahorro frente a un modelo de referencia
+// ============================================================================
+// Para cada tarea se calcula lo que habría costado el modelo de referencia
+// (`CostConstraints.baseline_model`) con los mismos tokens, y se reparte la
+// diferencia con el costo real en tres fuentes:
+//   - selección de modelo: referencia vs lo que facturó el adaptador que
+//     sirvió la llamada, sobre el prompt original (con signo: un modelo más
+//     caro resta)
+//   - compresión: tokens de prompt eliminados, al precio de ese adaptador
+//   - caché: la llamada que no se hizo al servir la respuesta desde la caché
+// ============================================================================
+
+use super::{cost_at, ModelChoice};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostSavings {
+    pub baseline_model: ModelChoice,
+    pub baseline_cost_usd: f64,
+    pub actual_cost_usd: f64,
+    pub model_selection_usd: f64,
+    pub cache_usd: f64,
+    pub compression_usd: f64,
+}
+
+impl CostSavings {
+    /// Ahorro de una llamada con `input_tokens`/`output_tokens` reales.
+    /// `serving_price` es el precio por millón de tokens (entrada, salida) del
+    /// adaptador que sirvió la llamada y `actual_cost_usd` lo que facturó;
+    /// `compressed_tokens` son los tokens de prompt que eliminó la compresión.
+    pub fn compute(
+        baseline: &ModelChoice,
+        serving_price: (f64, f64),
+        input_tokens: u32,
+        output_tokens: u32,
+        compressed_tokens: u32,
+        cache_hit: bool,
+        actual_cost_usd: f64,
+    ) -> Self {
+        let original_input = input_tokens + compressed_tokens;
+        let baseline_cost = baseline.cost(original_input, output_tokens);
+        // Un acierto de caché no factura: se valora la llamada que se evitó
+        let call_cost = if cache_hit { cost_at(serving_price, input_tokens, output_tokens) } else { actual_cost_usd };
+        let compression = cost_at(serving_price, original_input, output_tokens) - cost_at(serving_price, input_tokens, output_tokens);
+
+        Self {
+            baseline_model: baseline.clone(),
+            baseline_cost_usd: baseline_cost,
+            actual_cost_usd,
+            model_selection_usd: baseline_cost - call_cost - compression,
+            compression_usd: compression,
+            cache_usd: if cache_hit { (call_cost - actual_cost_usd).max(0.0) } else { 0.0 },
+        }
+    }
+
+    pub fn total_usd(&self) -> f64 {
+        self.model_selection_usd + self.cache_usd + self.compression_usd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_savings_split_by_source() {
+        let pro = ModelChoice::Gemini15Pro;
+        let flash = ModelChoice::Gemini15Flash;
+
+        // Flash frente a Pro, 1000 tokens de prompt de los que 200 se comprimieron
+        let savings = CostSavings::compute(&pro, flash.price_per_million(), 800, 500, 200, false, flash.cost(800, 500));
+        assert!((savings.model_selection_usd - (pro.cost(1000, 500) - flash.cost(1000, 500))).abs() < 1e-12);
+        assert!((savings.compression_usd - (flash.cost(1000, 500) - flash.cost(800, 500))).abs() < 1e-12);
+        assert_eq!(savings.cache_usd, 0.0);
+        assert!((savings.total_usd() - (savings.baseline_cost_usd - savings.actual_cost_usd)).abs() < 1e-12);
+
+        // Acierto de caché con el propio modelo de referencia: todo es caché
+        let cached = CostSavings::compute(&pro, pro.price_per_million(), 1000, 500, 0, true, 0.0);
+        assert_eq!(cached.model_selection_usd, 0.0);
+        assert!((cached.cache_usd - pro.cost(1000, 500)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_model_selection_uses_the_billed_cost_not_the_label() {
+        let pro = ModelChoice::Gemini15Pro;
+        let flash = ModelChoice::Gemini15Flash;
+
+        // Etiquetado como Pro pero servido (y facturado) por un adaptador de Flash
+        let billed = flash.cost(1000, 500);
+        let savings = CostSavings::compute(&pro, flash.price_per_million(), 1000, 500, 0, false, billed);
+        assert!((savings.model_selection_usd - (pro.cost(1000, 500) - billed)).abs() < 1e-12);
+        assert!(savings.model_selection_usd > 0.0);
+
+        // Los reintentos facturados restan al ahorro por selección
+        let retried = CostSavings::compute(&pro, flash.price_per_million(), 1000, 500, 0, false, billed * 3.0);
+        assert!((retried.total_usd() - (retried.baseline_cost_usd - retried.actual_cost_usd)).abs() < 1e-12);
+    }
+}
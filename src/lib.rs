@@ -18,12 +18,29 @@ use std::fmt;
 pub trait CodeGenerationFlow: Send + Sync {
     /// Ejecuta el flujo completo: Generar -> Verificar -> Refinar
     async fn execute(&self, problem_description: &str) -> Result<CodeGenerationResult, FlowError>;
+
+    /// Igual que `execute`, ofreciendo al modelo las herramientas indicadas
+    /// (esquemas de función de `ToolRegistry`). Los adaptadores sin function
+    /// calling las ignoran.
+    async fn execute_with_tools(
+        &self,
+        problem_description: &str,
+        _tools: &[serde_json::Value],
+    ) -> Result<CodeGenerationResult, FlowError> {
+        self.execute(problem_description).await
+    }
     
     /// Verifica si el código generado cumple con los criterios de calidad
     fn verify_code(&self, code: &str) -> VerificationResult;
     
     /// Obtiene información sobre las capacidades del adaptador
     fn get_capabilities(&self) -> AdapterCapabilities;
+
+    /// Huella del modelo y la configuración de generación, parte de la clave
+    /// de la caché de respuestas: si cambia, las respuestas guardadas no valen
+    fn cache_fingerprint(&self) -> String {
+        serde_json::to_string(&self.get_capabilities()).unwrap_or_default()
+    }
 }
 
 // ============================================================================
@@ -57,6 +74,25 @@ pub struct CodeGenerationResult {
     pub cost_estimate: Option<CostEstimate>,
     pub model_used: Option<String>,
     pub metrics: CodeGenerationMetrics,
+    /// Historial de intentos del bucle Generar -> Verificar -> Refinar
+    #[serde(default)]
+    pub attempt_history: Vec<AttemptRecord>,
+    /// La respuesta salió de la caché de respuestas
+    #[serde(default)]
+    pub cache_hit: bool,
+}
+
+/// Un intento del bucle de refinamiento con su verificación
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttemptRecord {
+    pub attempt: u32,
+    pub code: String,
+    pub quality_score: f64,
+    pub verification_passed: bool,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    /// Diff unificado respecto al intento anterior
+    pub diff_from_previous: Option<String>,
 }
 
 // ============================================================================
@@ -100,6 +136,12 @@ pub struct CostEstimate {
     pub output_tokens: u32,
     pub estimated_cost_usd: f64,
     pub model_used: String,
+    /// La respuesta salió de la caché y no se llamó al modelo
+    #[serde(default)]
+    pub cache_hit: bool,
+    /// Tokens de prompt eliminados por la compresión de contexto
+    #[serde(default)]
+    pub compressed_tokens: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -138,9 +180,31 @@ pub enum FlowError {
     NetworkError(String),
     MaxAttemptsReached(u32),
     CostLimitExceeded(f64),
+    DailyBudgetExceeded(f64),
     ThinkingModeNotSupported,
     AdapterNotFound(String),
     InvalidResponse(String),
+    ApprovalDenied(String),
+}
+
+impl FlowError {
+    /// Nombre estable de la variante, para métricas y logs
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FlowError::ApiError(_) => "api_error",
+            FlowError::CompilationError(_) => "compilation_error",
+            FlowError::TimeoutError => "timeout",
+            FlowError::InvalidPrompt(_) => "invalid_prompt",
+            FlowError::NetworkError(_) => "network_error",
+            FlowError::MaxAttemptsReached(_) => "max_attempts_reached",
+            FlowError::CostLimitExceeded(_) => "cost_limit_exceeded",
+            FlowError::DailyBudgetExceeded(_) => "daily_budget_exceeded",
+            FlowError::ThinkingModeNotSupported => "thinking_mode_not_supported",
+            FlowError::AdapterNotFound(_) => "adapter_not_found",
+            FlowError::InvalidResponse(_) => "invalid_response",
+            FlowError::ApprovalDenied(_) => "approval_denied",
+        }
+    }
 }
 
 impl fmt::Display for FlowError {
@@ -157,6 +221,9 @@ impl fmt::Display for FlowError {
             FlowError::CostLimitExceeded(limit) => {
                 write!(f, "Límite de costo excedido: ${:.4}", limit)
             }
+            FlowError::DailyBudgetExceeded(budget) => {
+                write!(f, "Presupuesto diario agotado: ${:.2}", budget)
+            }
             FlowError::ThinkingModeNotSupported => {
                 write!(f, "Modo thinking no soportado por este modelo")
             }
@@ -166,6 +233,7 @@ impl fmt::Display for FlowError {
             FlowError::InvalidResponse(msg) => {
                 write!(f, "Respuesta inválida de la IA: {}", msg)
             }
+            FlowError::ApprovalDenied(msg) => write!(f, "Aprobación denegada: {}", msg),
         }
     }
 }
@@ -9,6 +9,40 @@ async fn main() {
     
     // Parsear argumentos de línea de comandos
     let cli = Cli::parse();
+
+    // Configurar el gate de aprobación de herramientas y pasos de plan
+    match cli.approval_gate() {
+        Ok(gate) => {
+            if cli.dangerously_skip_permissions {
+                eprintln!("⚠️  --dangerously-skip-permissions: se omiten todas las aprobaciones");
+            }
+            enjambre::tools::approval::install_gate(gate);
+        }
+        Err(e) => {
+            eprintln!("❌ Error: {}", e);
+            process::exit(1);
+        }
+    }
+    
+    // Categorías de herramientas disponibles, antes del primer uso del registro
+    if let Some(registry) = cli.tool_registry() {
+        if let Err(e) = enjambre::tools::install_registry(registry) {
+            eprintln!("❌ Error: {}", e);
+            process::exit(1);
+        }
+    }
+    
+    // Exportador de métricas de Prometheus (opcional)
+    let metrics_exporter = match cli.metrics_exporter() {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            eprintln!("❌ Error: {}", e);
+            process::exit(1);
+        }
+    };
+    
+    // Trazas (opcional)
+    let trace_export = cli.trace_export();
     
     // Ejecutar el comando correspondiente
     let result = match cli.command {
@@ -44,6 +78,15 @@ async fn main() {
         }
     };
     
+    if let Some(trace_export) = &trace_export {
+        trace_export.export().await;
+    }
+    if let Some(exporter) = &metrics_exporter {
+        if let Err(e) = exporter.flush() {
+            eprintln!("⚠️  {}", e);
+        }
+    }
+    
     // Manejar errores
     if let Err(e) = result {
         eprintln!("❌ Error: {}", e);
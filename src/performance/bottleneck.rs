@@ -0,0 +1,552 @@
+// ============================================================================
+// BOTTLENECK ANALYZER - Cuellos de botella en ejecuciones grabadas
+// ============================================================================
+// Analiza los journals de `~/.enjambre/runs` y, si se dan, los spans de una
+// traza para encontrar dónde se va el tiempo: herramientas lentas, tormentas
+// de regeneraciones, pasos de plan independientes ejecutados en serie,
+// prompts sobredimensionados y spans que dominan el tiempo total. Cada
+// hallazgo trae una recomendación y, cuando es seguro, un `ConfigChange`.
+// ============================================================================
+
+use super::trace::{SpanKind, SpanRecord};
+use super::AlertSeverity;
+use crate::cost_optimizer::{estimate_tokens, ModelChoice, TaskComplexity};
+use crate::swarm::journal::{JournalEntry, JournalEvent, RunInput};
+use crate::swarm::{ExecutionPlan, TaskType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BottleneckThresholds {
+    /// p95 a partir del cual una herramienta se considera lenta
+    pub slow_tool_p95_ms: u64,
+    /// Llamadas al adaptador por tarea a partir de las cuales hay tormenta
+    pub retry_storm_calls: usize,
+    /// Tokens estimados a partir de los cuales un prompt es excesivo
+    pub oversized_prompt_tokens: u32,
+    /// Tareas mínimas de una clase para proponer un modelo más barato
+    pub downgrade_min_samples: usize,
+    /// Fracción del tiempo total a partir de la que un span domina
+    pub time_sink_share: f64,
+}
+
+impl Default for BottleneckThresholds {
+    fn default() -> Self {
+        Self {
+            slow_tool_p95_ms: 2000,
+            retry_storm_calls: 3,
+            oversized_prompt_tokens: 8000,
+            downgrade_min_samples: 5,
+            time_sink_share: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    TimeSink,
+    SlowTool,
+    RetryStorm,
+    SerialSteps,
+    OversizedPrompt,
+    OverprovisionedModel,
+}
+
+/// Cambio de configuración seguro que resuelve un hallazgo
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ConfigChange {
+    /// Modelo fijo para una clase de tarea con una complejidad concreta
+    ModelOverride { task_type: String, complexity: TaskComplexity, model: ModelChoice },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub severity: AlertSeverity,
+    pub title: String,
+    pub detail: String,
+    pub recommendation: String,
+    /// Tiempo que se ahorraría resolviéndolo, si se puede estimar
+    pub estimated_savings_ms: Option<u64>,
+    pub change: Option<ConfigChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeSink {
+    pub kind: SpanKind,
+    pub name: String,
+    pub calls: u64,
+    /// Tiempo propio (sin hijos) acumulado
+    pub self_ms: f64,
+    pub share: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BottleneckAnalysis {
+    pub runs: usize,
+    pub spans: usize,
+    pub time_sinks: Vec<TimeSink>,
+    pub findings: Vec<Finding>,
+}
+
+impl BottleneckAnalysis {
+    /// Cambios de configuración propuestos por los hallazgos
+    pub fn config_changes(&self) -> Vec<ConfigChange> {
+        self.findings.iter().filter_map(|finding| finding.change.clone()).collect()
+    }
+}
+
+/// Analiza los journals y spans grabados
+pub fn analyze(
+    journals: &[Vec<JournalEntry>],
+    spans: &[SpanRecord],
+    thresholds: &BottleneckThresholds,
+) -> BottleneckAnalysis {
+    let time_sinks = time_sinks(spans);
+    let mut findings = Vec::new();
+
+    for sink in time_sinks.iter().filter(|sink| sink.share >= thresholds.time_sink_share) {
+        findings.push(Finding {
+            kind: FindingKind::TimeSink,
+            severity: AlertSeverity::Medium,
+            title: format!("'{}' concentra el {:.0}% del tiempo", sink.name, sink.share * 100.0),
+            detail: format!("{} llamadas, {:.0}ms de tiempo propio", sink.calls, sink.self_ms),
+            recommendation: match sink.kind {
+                SpanKind::Adapter => "Usa un modelo más rápido para las tareas simples o reduce el tamaño de los prompts".to_string(),
+                SpanKind::Tool => "Cachea o paraleliza las llamadas a esta herramienta".to_string(),
+                _ => "Divide el trabajo en pasos independientes que puedan ejecutarse en paralelo".to_string(),
+            },
+            estimated_savings_ms: None,
+            change: None,
+        });
+    }
+
+    findings.extend(slow_tools(journals, spans, thresholds));
+    findings.extend(retry_storms(journals, thresholds));
+    findings.extend(serial_steps(journals));
+    findings.extend(oversized_prompts(journals, thresholds));
+    findings.extend(overprovisioned_models(journals, thresholds));
+
+    findings.sort_by_key(|finding| std::cmp::Reverse(finding.estimated_savings_ms.unwrap_or(0)));
+
+    BottleneckAnalysis {
+        runs: journals.len(),
+        spans: spans.len(),
+        time_sinks,
+        findings,
+    }
+}
+
+/// Tiempo propio acumulado por span, ordenado de mayor a menor
+fn time_sinks(spans: &[SpanRecord]) -> Vec<TimeSink> {
+    let mut children_ms: HashMap<&str, f64> = HashMap::new();
+    for span in spans {
+        if let Some(parent) = &span.parent_span_id {
+            *children_ms.entry(parent.as_str()).or_default() += span.duration_ms();
+        }
+    }
+    let total_ms: f64 = spans.iter()
+        .filter(|span| span.parent_span_id.as_ref().is_none_or(|p| !spans.iter().any(|s| &s.span_id == p)))
+        .map(SpanRecord::duration_ms)
+        .sum();
+
+    let mut sinks: BTreeMap<(String, String), TimeSink> = BTreeMap::new();
+    for span in spans {
+        let self_ms = (span.duration_ms() - children_ms.get(span.span_id.as_str()).copied().unwrap_or(0.0)).max(0.0);
+        let sink = sinks.entry((format!("{:?}", span.kind), span.name.clone())).or_insert_with(|| TimeSink {
+            kind: span.kind,
+            name: span.name.clone(),
+            calls: 0,
+            self_ms: 0.0,
+            share: 0.0,
+        });
+        sink.calls += 1;
+        sink.self_ms += self_ms;
+    }
+
+    let mut sinks: Vec<TimeSink> = sinks.into_values()
+        .map(|mut sink| {
+            sink.share = if total_ms > 0.0 { sink.self_ms / total_ms } else { 0.0 };
+            sink
+        })
+        .collect();
+    sinks.sort_by(|a, b| b.self_ms.total_cmp(&a.self_ms));
+    sinks
+}
+
+fn percentile_95(samples: &mut [u64]) -> u64 {
+    samples.sort_unstable();
+    let index = ((samples.len() as f64 * 0.95).ceil() as usize).clamp(1, samples.len()) - 1;
+    samples[index]
+}
+
+fn slow_tools(journals: &[Vec<JournalEntry>], spans: &[SpanRecord], thresholds: &BottleneckThresholds) -> Vec<Finding> {
+    let mut durations: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+    for entry in journals.iter().flatten() {
+        if let JournalEvent::ToolCall { tool, duration_ms, .. } = &entry.event {
+            durations.entry(tool.clone()).or_default().push(*duration_ms);
+        }
+    }
+    // Las trazas solo aportan herramientas que no estén ya en los journals
+    let mut from_spans: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+    for span in spans.iter().filter(|span| span.kind == SpanKind::Tool) {
+        let tool = span.name.trim_start_matches("tool ").to_string();
+        if !durations.contains_key(&tool) {
+            from_spans.entry(tool).or_default().push(span.duration_ms() as u64);
+        }
+    }
+    durations.extend(from_spans);
+
+    durations.into_iter().filter_map(|(tool, mut samples)| {
+        let total: u64 = samples.iter().sum();
+        let p95 = percentile_95(&mut samples);
+        (p95 >= thresholds.slow_tool_p95_ms).then(|| Finding {
+            kind: FindingKind::SlowTool,
+            severity: AlertSeverity::Medium,
+            title: format!("Herramienta lenta: {}", tool),
+            detail: format!("{} llamadas, p95 {}ms, {}ms en total", samples.len(), p95, total),
+            recommendation: format!(
+                "Cachea los resultados de '{}', acota sus parámetros o lánzala en paralelo con otros pasos",
+                tool
+            ),
+            // Bajar el p95 al umbral en cada llamada
+            estimated_savings_ms: Some(total.saturating_sub(thresholds.slow_tool_p95_ms * samples.len() as u64)),
+            change: None,
+        })
+    }).collect()
+}
+
+fn retry_storms(journals: &[Vec<JournalEntry>], thresholds: &BottleneckThresholds) -> Vec<Finding> {
+    // Por clase de tarea: (tareas con tormenta, llamadas, ms desperdiciados)
+    let mut storms: BTreeMap<String, (usize, usize, u64)> = BTreeMap::new();
+
+    for journal in journals {
+        let mut current: Option<String> = None;
+        let mut calls: Vec<u64> = Vec::new();
+        for entry in journal {
+            match &entry.event {
+                JournalEvent::TaskStarted { task } => {
+                    current = Some(format!("{:?}", task.task_type));
+                    calls.clear();
+                }
+                JournalEvent::Response { duration_ms, .. } => calls.push(*duration_ms),
+                JournalEvent::TaskFinished { .. } => {
+                    if let Some(task_type) = current.take() {
+                        if calls.len() >= thresholds.retry_storm_calls {
+                            let storm = storms.entry(task_type).or_default();
+                            storm.0 += 1;
+                            storm.1 += calls.len();
+                            // Todas las llamadas salvo la última se repitieron
+                            storm.2 += calls[..calls.len() - 1].iter().sum::<u64>();
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    storms.into_iter().map(|(task_type, (tasks, calls, wasted_ms))| Finding {
+        kind: FindingKind::RetryStorm,
+        severity: AlertSeverity::High,
+        title: format!("Tormenta de regeneraciones en tareas {}", task_type),
+        detail: format!("{} tareas necesitaron {} llamadas al adaptador en total", tasks, calls),
+        recommendation: "Revisa el umbral de calidad de estas tareas, mejora el prompt o usa un modelo más capaz para esta clase".to_string(),
+        estimated_savings_ms: Some(wasted_ms),
+        change: None,
+    }).collect()
+}
+
+fn serial_steps(journals: &[Vec<JournalEntry>]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for journal in journals {
+        let Some(plan) = journal.iter().find_map(|entry| match &entry.event {
+            JournalEvent::RunStarted { input, .. } => match input.as_ref() {
+                RunInput::Plan(plan) => Some(plan.clone()),
+                RunInput::Task(_) => None,
+            },
+            _ => None,
+        }) else {
+            continue;
+        };
+
+        let durations = step_durations(journal);
+        let mut levels: BTreeMap<usize, Vec<u32>> = BTreeMap::new();
+        for (step, level) in step_levels(&plan) {
+            levels.entry(level).or_default().push(step);
+        }
+
+        let mut savings = 0;
+        let mut widest = 1;
+        let mut parallel_groups = Vec::new();
+        for steps in levels.values().filter(|steps| steps.len() > 1) {
+            widest = widest.max(steps.len());
+            let times: Vec<u64> = steps.iter().filter_map(|id| durations.get(id).copied()).collect();
+            savings += times.iter().sum::<u64>() - times.iter().max().copied().unwrap_or(0);
+            parallel_groups.push(format!("{:?}", steps));
+        }
+        if parallel_groups.is_empty() {
+            continue;
+        }
+
+        findings.push(Finding {
+            kind: FindingKind::SerialSteps,
+            severity: AlertSeverity::Medium,
+            title: format!("Pasos independientes ejecutados en serie en '{}'", plan.original_objective),
+            detail: format!("Los grupos {} no dependen entre sí", parallel_groups.join(", ")),
+            recommendation: format!("Permite hasta {} tareas concurrentes para ejecutar estos pasos en paralelo", widest),
+            estimated_savings_ms: Some(savings),
+            // El planificador aún ejecuta los pasos de uno en uno: subir
+            // `max_concurrent_tasks` no cambiaría nada
+            change: None,
+        });
+    }
+    findings
+}
+
+/// Nivel de cada paso en el grafo de dependencias (0 = sin dependencias)
+fn step_levels(plan: &ExecutionPlan) -> Vec<(u32, usize)> {
+    let mut levels: HashMap<u32, usize> = HashMap::new();
+    let Ok(order) = plan.execution_order() else {
+        return Vec::new();
+    };
+    for step in order {
+        let level = step.depends_on.iter()
+            .filter_map(|dep| levels.get(dep))
+            .map(|level| level + 1)
+            .max()
+            .unwrap_or(0);
+        levels.insert(step.id, level);
+    }
+    let mut levels: Vec<(u32, usize)> = levels.into_iter().collect();
+    levels.sort();
+    levels
+}
+
+/// Duración de cada paso: la tarea que sigue a su decisión `plan_step`
+fn step_durations(journal: &[JournalEntry]) -> HashMap<u32, u64> {
+    let mut durations = HashMap::new();
+    let mut pending: Option<u32> = None;
+    for entry in journal {
+        match &entry.event {
+            // Detalle con formato "paso <id> asignado a <agente>"
+            JournalEvent::Decision { kind, detail, .. } if kind == "plan_step" => {
+                pending = detail.split_whitespace().nth(1).and_then(|id| id.parse().ok());
+            }
+            JournalEvent::TaskFinished { result } => {
+                if let Some(step) = pending.take() {
+                    durations.insert(step, result.execution_time_ms);
+                }
+            }
+            _ => {}
+        }
+    }
+    durations
+}
+
+fn oversized_prompts(journals: &[Vec<JournalEntry>], thresholds: &BottleneckThresholds) -> Vec<Finding> {
+    let sizes: Vec<u32> = journals.iter().flatten()
+        .filter_map(|entry| match &entry.event {
+            JournalEvent::Prompt { prompt, .. } => Some(estimate_tokens(prompt)),
+            _ => None,
+        })
+        .filter(|tokens| *tokens >= thresholds.oversized_prompt_tokens)
+        .collect();
+    if sizes.is_empty() {
+        return Vec::new();
+    }
+
+    vec![Finding {
+        kind: FindingKind::OversizedPrompt,
+        severity: AlertSeverity::Low,
+        title: "Prompts sobredimensionados".to_string(),
+        detail: format!(
+            "{} prompts superan los {} tokens (máximo {})",
+            sizes.len(),
+            thresholds.oversized_prompt_tokens,
+            sizes.iter().max().copied().unwrap_or(0),
+        ),
+        recommendation: "Recorta el contexto de los pasos previos y envía solo los ficheros relevantes".to_string(),
+        estimated_savings_ms: None,
+        change: None,
+    }]
+}
+
+/// Clases de tarea que, con una complejidad dada, siempre salen bien a la
+/// primera con un modelo caro. Se agrupan por complejidad para no fijar Flash
+/// en las tareas complejas de una clase que solo es fácil en sus casos simples.
+fn overprovisioned_models(journals: &[Vec<JournalEntry>], thresholds: &BottleneckThresholds) -> Vec<Finding> {
+    struct ClassStats {
+        complexity: TaskComplexity,
+        model: ModelChoice,
+        tasks: usize,
+        successes: usize,
+        retried: usize,
+        cost: f64,
+    }
+    // (clase, complejidad, modelo) -> estadísticas
+    let mut stats: BTreeMap<(String, String, String), ClassStats> = BTreeMap::new();
+
+    for journal in journals {
+        let mut task_types: HashMap<String, TaskType> = HashMap::new();
+        let mut complexities: HashMap<String, TaskComplexity> = HashMap::new();
+        let mut calls = 0;
+        for entry in journal {
+            match &entry.event {
+                JournalEvent::TaskStarted { task } => {
+                    task_types.insert(task.id.clone(), task.task_type.clone());
+                    calls = 0;
+                }
+                // Detalle con formato "<complejidad> (puntuación ...)"
+                JournalEvent::Decision { task_id: Some(task_id), kind, detail } if kind == "complexity" => {
+                    let complexity = detail.split_whitespace().next()
+                        .and_then(|name| serde_json::from_value(serde_json::Value::String(name.to_string())).ok());
+                    if let Some(complexity) = complexity {
+                        complexities.insert(task_id.clone(), complexity);
+                    }
+                }
+                JournalEvent::Response { .. } => calls += 1,
+                JournalEvent::TaskFinished { result } => {
+                    // Las tareas personalizadas no se pueden fijar por nombre
+                    let Some(task_type) = task_types.get(&result.task_id).filter(|t| !matches!(t, TaskType::CustomTask(_))) else {
+                        continue;
+                    };
+                    let Some(complexity) = complexities.get(&result.task_id) else {
+                        continue;
+                    };
+                    let key = (format!("{:?}", task_type), format!("{:?}", complexity), format!("{:?}", result.selected_model));
+                    let stat = stats.entry(key).or_insert_with(|| ClassStats {
+                        complexity: complexity.clone(),
+                        model: result.selected_model.clone(),
+                        tasks: 0,
+                        successes: 0,
+                        retried: 0,
+                        cost: 0.0,
+                    });
+                    stat.tasks += 1;
+                    stat.successes += result.success as usize;
+                    stat.retried += (calls > 1) as usize;
+                    stat.cost += result.cost_actual;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    stats.into_iter().filter_map(|((task_type, _, _), ClassStats { complexity, model, tasks, successes, retried, cost })| {
+        let expensive = matches!(model, ModelChoice::Gemini15Pro | ModelChoice::Gemini15ProExp);
+        (expensive && tasks >= thresholds.downgrade_min_samples && successes == tasks && retried == 0).then(|| Finding {
+            kind: FindingKind::OverprovisionedModel,
+            severity: AlertSeverity::Low,
+            title: format!("{:?} sobra para las tareas {} de complejidad {:?}", model, task_type, complexity),
+            detail: format!("{} tareas, todas correctas al primer intento, ${:.4} en total", tasks, cost),
+            recommendation: format!("Usa {:?} para las tareas {} de complejidad {:?}", ModelChoice::Gemini15Flash, task_type, complexity),
+            estimated_savings_ms: None,
+            change: Some(ConfigChange::ModelOverride { task_type, complexity, model: ModelChoice::Gemini15Flash }),
+        })
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swarm::{SwarmConfig, SwarmExecutionResult, TaskStep};
+
+    fn entry(event: JournalEvent) -> JournalEntry {
+        JournalEntry { sequence: 0, timestamp: chrono::Utc::now(), elapsed_ms: 0, event }
+    }
+
+    fn step(id: u32, depends_on: Vec<u32>) -> TaskStep {
+        TaskStep { id, task: format!("paso {}", id), tools: Vec::new(), tool_params: Default::default(), depends_on, details: None }
+    }
+
+    fn finished(ms: u64) -> JournalEvent {
+        JournalEvent::TaskFinished {
+            result: Box::new(SwarmExecutionResult {
+                task_id: "t".to_string(),
+                success: true,
+                result: None,
+                thinking_result: None,
+                error: None,
+                error_kind: None,
+                selected_adapter: "gemini".to_string(),
+                selected_model: ModelChoice::Gemini15Flash,
+                execution_time_ms: ms,
+                performance_score: 0.0,
+                cost_actual: 0.0,
+                cost_saved: 0.0,
+                optimization_applied: true,
+                output: None,
+                savings: None,
+                context_report: None,
+                labels: Default::default(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_finds_serial_steps_and_slow_tools() {
+        // 1 -> {2, 3}: los pasos 2 y 3 podrían ir en paralelo
+        let plan = ExecutionPlan {
+            original_objective: "demo".to_string(),
+            steps: vec![step(1, vec![]), step(2, vec![1]), step(3, vec![1])],
+        };
+        let mut journal = vec![entry(JournalEvent::RunStarted {
+            session_id: "s".to_string(),
+            config: Box::new(SwarmConfig::default()),
+            input: Box::new(RunInput::Plan(plan)),
+            replay_of: None,
+        })];
+        for (id, ms) in [(1, 1000), (2, 4000), (3, 3000)] {
+            journal.push(entry(JournalEvent::Decision {
+                task_id: None,
+                kind: "plan_step".to_string(),
+                detail: format!("paso {} asignado a worker-{}", id, id),
+            }));
+            journal.push(entry(finished(ms)));
+        }
+        journal.push(entry(JournalEvent::ToolCall {
+            tool: "web_fetch".to_string(),
+            params: serde_json::Value::Null,
+            result: Err("timeout".to_string()),
+            duration_ms: 9000,
+        }));
+
+        let analysis = analyze(&[journal], &[], &BottleneckThresholds::default());
+        let serial = analysis.findings.iter().find(|f| f.kind == FindingKind::SerialSteps).unwrap();
+        assert_eq!(serial.estimated_savings_ms, Some(3000));
+        assert_eq!(serial.change, None);
+        assert!(analysis.findings.iter().any(|f| f.kind == FindingKind::SlowTool && f.title.contains("web_fetch")));
+        assert!(analysis.config_changes().is_empty());
+    }
+
+    #[test]
+    fn test_overprovisioned_models_are_keyed_by_complexity() {
+        let mut journal = Vec::new();
+        for (index, complexity, success) in [(0, "Simple", true), (1, "Simple", true), (2, "Simple", true), (3, "Complex", false)] {
+            let task = crate::swarm::TaskBuilder::code_generation("tarea");
+            let id = task.id.clone();
+            journal.push(entry(JournalEvent::TaskStarted { task }));
+            journal.push(entry(JournalEvent::Decision {
+                task_id: Some(id.clone()),
+                kind: "complexity".to_string(),
+                detail: format!("{} (puntuación 0.{})", complexity, index),
+            }));
+            let JournalEvent::TaskFinished { mut result } = finished(1000) else { unreachable!() };
+            result.task_id = id;
+            result.selected_model = ModelChoice::Gemini15Pro;
+            result.success = success;
+            journal.push(entry(JournalEvent::TaskFinished { result }));
+        }
+
+        let thresholds = BottleneckThresholds { downgrade_min_samples: 3, ..BottleneckThresholds::default() };
+        let changes = analyze(&[journal], &[], &thresholds).config_changes();
+        assert_eq!(changes, vec![ConfigChange::ModelOverride {
+            task_type: "CodeGeneration".to_string(),
+            complexity: TaskComplexity::Simple,
+            model: ModelChoice::Gemini15Flash,
+        }]);
+    }
+}
@@ -0,0 +1,156 @@
+// ============================================================================
+// CIRCUIT BREAKER - Corte de llamadas a un adaptador que no responde
+// ============================================================================
+// Tras `failure_threshold` fallos seguidos del adaptador (errores de API, de
+// red, timeouts o respuestas inválidas; no los de presupuesto o aprobación)
+// el circuito se abre y las llamadas fallan al instante. Pasado `cooldown`
+// queda medio abierto: se deja pasar una llamada de prueba que lo cierra si
+// sale bien o lo vuelve a abrir si falla. Cada cambio se exporta como gauge.
+// ============================================================================
+
+use super::exporter;
+use crate::FlowError;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CircuitState {
+    Closed,
+    HalfOpen,
+    Open,
+}
+
+impl CircuitState {
+    /// Valor del gauge: 0 cerrado, 1 medio abierto, 2 abierto
+    pub fn as_gauge(&self) -> f64 {
+        match self {
+            CircuitState::Closed => 0.0,
+            CircuitState::HalfOpen => 1.0,
+            CircuitState::Open => 2.0,
+        }
+    }
+}
+
+struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// Hay una llamada de prueba en curso en estado medio abierto
+    probing: bool,
+}
+
+pub struct CircuitBreaker {
+    name: String,
+    failure_threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &str) -> Self {
+        Self::with_limits(name, DEFAULT_FAILURE_THRESHOLD, DEFAULT_COOLDOWN)
+    }
+
+    pub fn with_limits(name: &str, failure_threshold: u32, cooldown: Duration) -> Self {
+        exporter::circuit_breaker_state(name, CircuitState::Closed);
+        Self {
+            name: name.to_string(),
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            inner: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+                probing: false,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.inner.lock().map(|inner| inner.state).unwrap_or(CircuitState::Closed)
+    }
+
+    /// Decide si una llamada puede pasar; con el circuito abierto devuelve el error
+    pub fn allow(&self) -> Result<(), FlowError> {
+        let Ok(mut inner) = self.inner.lock() else { return Ok(()) };
+        match inner.state {
+            CircuitState::Closed => Ok(()),
+            CircuitState::Open if inner.opened_at.is_some_and(|at| at.elapsed() >= self.cooldown) => {
+                inner.state = CircuitState::HalfOpen;
+                inner.probing = true;
+                exporter::circuit_breaker_state(&self.name, CircuitState::HalfOpen);
+                Ok(())
+            }
+            CircuitState::HalfOpen if !inner.probing => {
+                inner.probing = true;
+                Ok(())
+            }
+            _ => Err(FlowError::ApiError(format!("Circuito abierto para '{}'", self.name))),
+        }
+    }
+
+    /// Registra el resultado de una llamada que pasó por `allow`
+    pub fn record<T>(&self, result: &Result<T, FlowError>) {
+        let Ok(mut inner) = self.inner.lock() else { return };
+        inner.probing = false;
+        let previous = inner.state;
+        match result {
+            Err(e) if counts_as_failure(e) => {
+                inner.consecutive_failures += 1;
+                if inner.state == CircuitState::HalfOpen || inner.consecutive_failures >= self.failure_threshold {
+                    inner.state = CircuitState::Open;
+                    inner.opened_at = Some(Instant::now());
+                }
+            }
+            _ => {
+                inner.consecutive_failures = 0;
+                inner.state = CircuitState::Closed;
+                inner.opened_at = None;
+            }
+        }
+        if inner.state != previous {
+            if inner.state == CircuitState::Open {
+                log::warn!("🔌 Circuito abierto para '{}' tras {} fallos seguidos", self.name, inner.consecutive_failures);
+            }
+            exporter::circuit_breaker_state(&self.name, inner.state);
+        }
+    }
+}
+
+/// Solo cuentan los fallos del propio adaptador
+fn counts_as_failure(error: &FlowError) -> bool {
+    matches!(
+        error,
+        FlowError::ApiError(_) | FlowError::NetworkError(_) | FlowError::TimeoutError | FlowError::InvalidResponse(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_failures_and_recovers_after_cooldown() {
+        let breaker = CircuitBreaker::with_limits("gemini", 2, Duration::from_millis(20));
+        let failure: Result<(), FlowError> = Err(FlowError::NetworkError("caído".to_string()));
+
+        breaker.record(&Err::<(), _>(FlowError::DailyBudgetExceeded(1.0)));
+        breaker.record(&failure);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record(&failure);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.allow().is_err());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(breaker.allow().is_ok());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        // Solo una llamada de prueba a la vez
+        assert!(breaker.allow().is_err());
+        breaker.record(&Ok::<(), FlowError>(()));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,234 @@
+// ============================================================================
+// PROMETHEUS EXPORTER - Métricas para Prometheus / Grafana
+// ============================================================================
+// Las métricas se emiten con las macros del crate `metrics`; sin exportador
+// instalado no hacen nada. `--metrics-addr` sirve `/metrics` por HTTP para
+// sesiones largas (`hive-mind`) y `--metrics-file` vuelca el último estado en
+// formato de texto de Prometheus al terminar (ejecuciones puntuales, p. ej.
+// para el textfile collector de node_exporter).
+// ============================================================================
+
+use super::{CircuitState, ResourceUsage};
+use crate::FlowError;
+use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Unit};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const LATENCY_BUCKETS: [f64; 12] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0];
+const COST_BUCKETS: [f64; 8] = [0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5];
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExporterError {
+    #[error("No se pudo iniciar el exportador de métricas: {0}")]
+    Build(String),
+
+    #[error("No se pudieron escribir las métricas: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Exportador instalado como recorder global de `metrics`
+pub struct MetricsExporter {
+    handle: PrometheusHandle,
+    file: Option<PathBuf>,
+}
+
+impl MetricsExporter {
+    /// Instala el recorder global. Con `addr` sirve las métricas por HTTP
+    /// (requiere un runtime de tokio); con `file` se vuelcan en `flush`.
+    pub fn install(addr: Option<SocketAddr>, file: Option<PathBuf>) -> Result<Self, ExporterError> {
+        let builder = builder()?;
+        let handle = match addr {
+            Some(addr) => {
+                let (recorder, exporter) = builder
+                    .with_http_listener(addr)
+                    .build()
+                    .map_err(|e| ExporterError::Build(e.to_string()))?;
+                let handle = recorder.handle();
+                tokio::spawn(async move {
+                    if let Err(e) = exporter.await {
+                        log::error!("❌ El endpoint de métricas terminó: {}", e);
+                    }
+                });
+                metrics::set_global_recorder(recorder).map_err(|e| ExporterError::Build(e.to_string()))?;
+                log::info!("📡 Métricas de Prometheus en http://{}/metrics", addr);
+                handle
+            }
+            None => builder.install_recorder().map_err(|e| ExporterError::Build(e.to_string()))?,
+        };
+
+        describe_metrics();
+        Ok(Self { handle, file })
+    }
+
+    /// Métricas actuales en formato de texto de Prometheus
+    pub fn render(&self) -> String {
+        self.handle.render()
+    }
+
+    /// Vuelca las métricas al fichero configurado, si lo hay
+    pub fn flush(&self) -> Result<(), ExporterError> {
+        match &self.file {
+            Some(path) => write_atomically(path, &self.render()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn builder() -> Result<PrometheusBuilder, ExporterError> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), &LATENCY_BUCKETS)
+        .and_then(|builder| builder.set_buckets_for_metric(Matcher::Suffix("_usd".to_string()), &COST_BUCKETS))
+        .map_err(|e| ExporterError::Build(e.to_string()))
+}
+
+fn write_atomically(path: &Path, content: &str) -> Result<(), ExporterError> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("prom.tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+fn describe_metrics() {
+    describe_counter!("enjambre_tasks_total", "Tareas ejecutadas por tipo, modelo y estado");
+    describe_gauge!("enjambre_tasks_in_flight", "Tareas en ejecución");
+    describe_histogram!("enjambre_task_duration_seconds", Unit::Seconds, "Duración de las tareas");
+    describe_histogram!("enjambre_task_cost_usd", "Costo de cada tarea en USD (la suma es el gasto total)");
+    describe_counter!("enjambre_adapter_calls_total", "Llamadas a adaptadores por estado");
+    describe_histogram!("enjambre_adapter_duration_seconds", Unit::Seconds, "Duración de las llamadas a adaptadores");
+    describe_counter!("enjambre_tokens_total", "Tokens consumidos por adaptador, modelo y dirección");
+    describe_histogram!("enjambre_adapter_cost_usd", "Costo de cada llamada a un adaptador en USD");
+    describe_counter!("enjambre_response_cache_total", "Consultas a la caché de respuestas por adaptador y resultado");
+    describe_counter!("enjambre_tool_calls_total", "Llamadas a herramientas por estado");
+    describe_histogram!("enjambre_tool_duration_seconds", Unit::Seconds, "Duración de las llamadas a herramientas");
+    describe_counter!("enjambre_errors_total", "Errores por variante de FlowError");
+    describe_gauge!("enjambre_circuit_breaker_state", "Estado del circuit breaker por adaptador (0 cerrado, 1 medio abierto, 2 abierto)");
+    describe_gauge!("enjambre_process_memory_bytes", Unit::Bytes, "RSS del proceso y sus descendientes");
+    describe_gauge!("enjambre_process_cpu_percent", Unit::Percent, "CPU del proceso y sus descendientes");
+    describe_gauge!("enjambre_process_open_fds", "Descriptores de fichero abiertos");
+    describe_gauge!("enjambre_process_children", "Procesos descendientes vivos");
+}
+
+// ============================================================================
+// PUNTOS DE EMISIÓN
+// ============================================================================
+
+fn status(success: bool) -> &'static str {
+    if success { "success" } else { "failure" }
+}
+
+pub fn task_started() {
+    gauge!("enjambre_tasks_in_flight").increment(1.0);
+}
+
+/// Etiquetas de tarea que se exportan; el resto (p. ej. `ticket`) tiene
+/// demasiados valores distintos para Prometheus y solo va al ledger
+const EXPORTED_LABELS: [&str; 2] = ["project", "user"];
+
+pub fn task_finished(
+    task_type: &str,
+    model: &str,
+    success: bool,
+    duration: Duration,
+    cost_usd: f64,
+    labels: &BTreeMap<String, String>,
+) {
+    let owner: Vec<(&'static str, String)> = EXPORTED_LABELS.iter()
+        .map(|&name| (name, labels.get(name).cloned().unwrap_or_default()))
+        .collect();
+    let with_owner = |extra: &[(&'static str, String)]| -> Vec<(&'static str, String)> {
+        extra.iter().cloned().chain(owner.iter().cloned()).collect()
+    };
+    gauge!("enjambre_tasks_in_flight").decrement(1.0);
+    counter!("enjambre_tasks_total", &with_owner(&[
+        ("task_type", task_type.to_string()), ("model", model.to_string()), ("status", status(success).to_string()),
+    ])).increment(1);
+    histogram!("enjambre_task_duration_seconds", "task_type" => task_type.to_string())
+        .record(duration.as_secs_f64());
+    histogram!("enjambre_task_cost_usd", &with_owner(&[("model", model.to_string())])).record(cost_usd);
+}
+
+pub fn adapter_call(adapter: &str, success: bool, duration: Duration) {
+    counter!("enjambre_adapter_calls_total", "adapter" => adapter.to_string(), "status" => status(success))
+        .increment(1);
+    histogram!("enjambre_adapter_duration_seconds", "adapter" => adapter.to_string())
+        .record(duration.as_secs_f64());
+}
+
+pub fn adapter_usage(adapter: &str, model: &str, input_tokens: u32, output_tokens: u32, cost_usd: f64) {
+    let labels = |direction: &'static str| {
+        [("adapter", adapter.to_string()), ("model", model.to_string()), ("direction", direction.to_string())]
+    };
+    counter!("enjambre_tokens_total", &labels("input")).increment(input_tokens as u64);
+    counter!("enjambre_tokens_total", &labels("output")).increment(output_tokens as u64);
+    histogram!("enjambre_adapter_cost_usd", "adapter" => adapter.to_string(), "model" => model.to_string())
+        .record(cost_usd);
+}
+
+pub fn cache_lookup(adapter: &str, hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    counter!("enjambre_response_cache_total", "adapter" => adapter.to_string(), "result" => result).increment(1);
+}
+
+pub fn tool_call(tool: &str, success: bool, duration: Duration) {
+    counter!("enjambre_tool_calls_total", "tool" => tool.to_string(), "status" => status(success)).increment(1);
+    histogram!("enjambre_tool_duration_seconds", "tool" => tool.to_string()).record(duration.as_secs_f64());
+}
+
+pub fn error(error: &FlowError) {
+    counter!("enjambre_errors_total", "kind" => error.kind()).increment(1);
+}
+
+pub fn circuit_breaker_state(adapter: &str, state: CircuitState) {
+    gauge!("enjambre_circuit_breaker_state", "adapter" => adapter.to_string()).set(state.as_gauge());
+}
+
+pub fn resource_usage(usage: &ResourceUsage) {
+    gauge!("enjambre_process_memory_bytes").set((usage.memory_usage_mb * 1024 * 1024) as f64);
+    gauge!("enjambre_process_cpu_percent").set(usage.cpu_usage_percent);
+    gauge!("enjambre_process_children").set(usage.child_processes as f64);
+    if let Some(fds) = usage.open_file_descriptors {
+        gauge!("enjambre_process_open_fds").set(fds as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flush_writes_prometheus_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("enjambre.prom");
+        // Recorder local: no toca el global que pudiera instalar otro test
+        let recorder = builder().unwrap().build_recorder();
+        let exporter = MetricsExporter { handle: recorder.handle(), file: Some(path.clone()) };
+
+        metrics::with_local_recorder(&recorder, || {
+            describe_metrics();
+            task_started();
+            let labels = BTreeMap::from([
+                ("project".to_string(), "api".to_string()),
+                ("user".to_string(), "ana".to_string()),
+                ("ticket".to_string(), "OPS-1".to_string()),
+            ]);
+            task_finished("CodeGeneration", "Gemini15Flash", true, Duration::from_millis(1500), 0.002, &labels);
+            tool_call("file_read", false, Duration::from_millis(3));
+            error(&FlowError::TimeoutError);
+            circuit_breaker_state("gemini", CircuitState::Open);
+        });
+        exporter.flush().unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains(r#"enjambre_tasks_total{task_type="CodeGeneration",model="Gemini15Flash",status="success",project="api",user="ana"} 1"#));
+        assert!(content.contains(r#"enjambre_tool_calls_total{tool="file_read",status="failure"} 1"#));
+        assert!(content.contains(r#"enjambre_errors_total{kind="timeout"} 1"#));
+        assert!(content.contains("enjambre_task_duration_seconds_bucket"));
+        assert!(content.contains(r#"enjambre_circuit_breaker_state{adapter="gemini"} 2"#));
+    }
+}
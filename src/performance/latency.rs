@@ -0,0 +1,279 @@
+// ============================================================================
+// LATENCY - Percentiles de latencia por ventanas de tiempo
+// ============================================================================
+// Cada `LatencyTracker` guarda las muestras recientes con su instante en un
+// buffer circular (`VecDeque`) podado a la ventana más larga, y un histograma
+// de toda la sesión con cubetas de 3 cifras significativas (al estilo HDR),
+// de modo que la memoria no crece con la duración de la sesión.
+// ============================================================================
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Ventanas por defecto: 1m, 5m y 1h (además de la sesión completa)
+pub const DEFAULT_WINDOWS: [Duration; 3] = [
+    Duration::from_secs(60),
+    Duration::from_secs(300),
+    Duration::from_secs(3600),
+];
+
+/// Percentiles de latencia de una ventana
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyPercentiles {
+    /// "1m", "5m", "1h"... o "session"
+    pub window: String,
+    pub count: u64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct LatencyTracker {
+    recent: VecDeque<(Instant, u64)>,
+    retention: Duration,
+    session: BTreeMap<u64, u64>,
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl LatencyTracker {
+    /// Tracker que conserva muestras individuales durante `retention`
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            recent: VecDeque::new(),
+            retention,
+            session: BTreeMap::new(),
+            count: 0,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+        }
+    }
+
+    pub fn record(&mut self, duration: Duration) {
+        self.record_at(Instant::now(), duration);
+    }
+
+    fn record_at(&mut self, now: Instant, duration: Duration) {
+        let ms = duration.as_millis() as u64;
+        self.recent.push_back((now, ms));
+        while self.recent.front().is_some_and(|(at, _)| now.duration_since(*at) > self.retention) {
+            self.recent.pop_front();
+        }
+
+        *self.session.entry(bucket(ms)).or_default() += 1;
+        self.count += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Latencia media de la sesión en ms
+    pub fn mean_ms(&self) -> u64 {
+        (self.total.as_millis() as u64).checked_div(self.count).unwrap_or(0)
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Percentiles de una ventana (None = sesión completa)
+    pub fn percentiles(&self, window: Option<Duration>) -> LatencyPercentiles {
+        self.percentiles_at(Instant::now(), window)
+    }
+
+    fn percentiles_at(&self, now: Instant, window: Option<Duration>) -> LatencyPercentiles {
+        let label = window_label(window);
+        match window {
+            Some(window) => {
+                let samples = self.window_samples(now, window);
+                let at = |p: f64| samples.get(rank(p, samples.len() as u64).saturating_sub(1) as usize)
+                    .copied()
+                    .unwrap_or(0);
+                LatencyPercentiles {
+                    window: label,
+                    count: samples.len() as u64,
+                    p50_ms: at(50.0),
+                    p90_ms: at(90.0),
+                    p95_ms: at(95.0),
+                    p99_ms: at(99.0),
+                    max_ms: samples.last().copied().unwrap_or(0),
+                }
+            }
+            None => LatencyPercentiles {
+                window: label,
+                count: self.count,
+                p50_ms: self.session_percentile(50.0),
+                p90_ms: self.session_percentile(90.0),
+                p95_ms: self.session_percentile(95.0),
+                p99_ms: self.session_percentile(99.0),
+                max_ms: self.max.as_millis() as u64,
+            },
+        }
+    }
+
+    /// Percentil arbitrario de una ventana (None = sesión completa)
+    pub fn percentile(&self, percentile: f64, window: Option<Duration>) -> Option<u64> {
+        match window {
+            Some(window) => {
+                let samples = self.window_samples(Instant::now(), window);
+                let index = rank(percentile, samples.len() as u64).checked_sub(1)?;
+                samples.get(index as usize).copied()
+            }
+            None if self.count == 0 => None,
+            None => Some(self.session_percentile(percentile)),
+        }
+    }
+
+    /// Latencias (ms, ordenadas) registradas dentro de la ventana
+    fn window_samples(&self, now: Instant, window: Duration) -> Vec<u64> {
+        let mut samples: Vec<u64> = self.recent.iter()
+            .filter(|(at, _)| now.duration_since(*at) <= window)
+            .map(|(_, ms)| *ms)
+            .collect();
+        samples.sort_unstable();
+        samples
+    }
+
+    fn session_percentile(&self, percentile: f64) -> u64 {
+        let target = rank(percentile, self.count);
+        let mut seen = 0;
+        for (bucket, count) in &self.session {
+            seen += count;
+            if seen >= target {
+                return *bucket;
+            }
+        }
+        0
+    }
+}
+
+/// Rango (1-based) del percentil por el método nearest-rank
+fn rank(percentile: f64, count: u64) -> u64 {
+    if count == 0 {
+        return 0;
+    }
+    ((percentile.clamp(0.0, 100.0) / 100.0 * count as f64).ceil() as u64).clamp(1, count)
+}
+
+/// Redondea a 3 cifras significativas
+fn bucket(ms: u64) -> u64 {
+    let mut magnitude = 1;
+    while ms / magnitude >= 1000 {
+        magnitude *= 10;
+    }
+    ms / magnitude * magnitude
+}
+
+/// Etiqueta legible de una ventana
+pub fn window_label(window: Option<Duration>) -> String {
+    match window.map(|w| w.as_secs()) {
+        None => "session".to_string(),
+        Some(secs) if secs > 0 && secs % 3600 == 0 => format!("{}h", secs / 3600),
+        Some(secs) if secs > 0 && secs % 60 == 0 => format!("{}m", secs / 60),
+        Some(secs) => format!("{}s", secs),
+    }
+}
+
+/// Parsea duraciones como "500ms", "8s", "5m", "1h" o "7d"
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let value: f64 = number.parse().map_err(|_| format!("Duración inválida: '{}'", text))?;
+    let secs = match unit {
+        "ms" => value / 1000.0,
+        "s" | "" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        "d" => value * 86400.0,
+        _ => return Err(format!("Unidad de duración desconocida en '{}' (usa ms, s, m, h o d)", text)),
+    };
+    Ok(Duration::from_secs_f64(secs))
+}
+
+/// Umbral de alerta sobre un percentil de latencia, p. ej. "p95>8s@5m"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PercentileThreshold {
+    pub percentile: f64,
+    pub threshold_ms: u64,
+    /// Ventana en segundos (None = sesión completa)
+    #[serde(default)]
+    pub window_secs: Option<u64>,
+}
+
+impl PercentileThreshold {
+    pub fn window(&self) -> Option<Duration> {
+        self.window_secs.map(Duration::from_secs)
+    }
+
+    /// Nombre de la métrica para las alertas, p. ej. "latency_p95_5m"
+    pub fn metric_name(&self) -> String {
+        format!("latency_p{}_{}", self.percentile, window_label(self.window()))
+    }
+}
+
+impl FromStr for PercentileThreshold {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Umbral de percentil inválido: '{}' (formato: p95>8s[@5m])", spec);
+
+        let (condition, window) = match spec.split_once('@') {
+            Some((condition, window)) => (condition, Some(parse_duration(window)?)),
+            None => (spec, None),
+        };
+        let (percentile, threshold) = condition.split_once('>').ok_or_else(invalid)?;
+        let percentile: f64 = percentile.trim()
+            .strip_prefix('p')
+            .ok_or_else(invalid)?
+            .parse()
+            .map_err(|_| invalid())?;
+        if !(0.0..=100.0).contains(&percentile) {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            percentile,
+            threshold_ms: parse_duration(threshold)?.as_millis() as u64,
+            window_secs: window.map(|w| w.as_secs()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_by_window() {
+        let mut tracker = LatencyTracker::new(Duration::from_secs(3600));
+        let start = Instant::now();
+        // Muestras antiguas y lentas, fuera de la ventana de 1 minuto
+        for _ in 0..10 {
+            tracker.record_at(start, Duration::from_millis(9000));
+        }
+        let now = start + Duration::from_secs(120);
+        for ms in 1..=100 {
+            tracker.record_at(now, Duration::from_millis(ms));
+        }
+
+        let minute = tracker.percentiles_at(now, Some(Duration::from_secs(60)));
+        assert_eq!((minute.count, minute.p50_ms, minute.p99_ms, minute.max_ms), (100, 50, 99, 100));
+
+        let session = tracker.percentiles_at(now, None);
+        assert_eq!((session.count, session.p95_ms, session.max_ms), (110, 9000, 9000));
+
+        let threshold: PercentileThreshold = "p95>8s@5m".parse().unwrap();
+        assert_eq!((threshold.threshold_ms, threshold.window_secs), (8000, Some(300)));
+        assert_eq!(threshold.metric_name(), "latency_p95_5m");
+    }
+}
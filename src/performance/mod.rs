@@ -0,0 +1,622 @@
+// ============================================================================
+// PERFORMANCE MONITOR - Monitor de Rendimiento del Sistema
+// ============================================================================
+
+pub mod bottleneck;
+pub mod breaker;
+pub mod exporter;
+pub mod latency;
+pub mod report;
+pub mod sampler;
+pub mod trace;
+
+pub use bottleneck::{BottleneckAnalysis, BottleneckThresholds, ConfigChange, Finding, FindingKind};
+pub use breaker::{CircuitBreaker, CircuitState};
+pub use exporter::{ExporterError, MetricsExporter};
+pub use latency::{LatencyPercentiles, LatencyTracker, PercentileThreshold, DEFAULT_WINDOWS};
+pub use sampler::{ResourceSampler, ResourceUsage, SampleListener, DEFAULT_SAMPLE_INTERVAL};
+pub use trace::{Span, SpanKind, Tracer};
+
+use crate::{AdapterCapabilities, CodeGenerationFlow, CodeGenerationResult, FlowError, VerificationResult};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AlertSeverity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertThresholds {
+    pub response_time_ms: u64,
+    pub error_rate: f64,
+    pub memory_usage_mb: u64,
+    pub cpu_usage_percent: f64,
+    pub open_file_descriptors: u64,
+    pub child_processes: u64,
+    /// Fracción bajo el umbral a la que debe bajar una métrica para que su
+    /// alerta activa se apague (evita alertas intermitentes)
+    pub hysteresis: f64,
+    /// Umbrales sobre percentiles de latencia, p. ej. p95 > 8s en 5m
+    pub latency_percentiles: Vec<PercentileThreshold>,
+}
+
+impl Default for AlertThresholds {
+    fn default() -> Self {
+        Self {
+            response_time_ms: 5000,
+            error_rate: 0.05,
+            memory_usage_mb: 1024,
+            cpu_usage_percent: 80.0,
+            open_file_descriptors: 1024,
+            child_processes: 16,
+            hysteresis: 0.1,
+            latency_percentiles: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceMetrics {
+    pub success_rate: f64,
+    pub average_response_time_ms: u64,
+    pub total_requests: u64,
+    pub failed_requests: u64,
+    pub memory_usage_mb: u64,
+    pub cpu_usage_percent: f64,
+    #[serde(default)]
+    pub open_file_descriptors: Option<u64>,
+    #[serde(default)]
+    pub child_processes: u64,
+    pub uptime_seconds: u64,
+    /// Percentiles de latencia por ventana
+    #[serde(default)]
+    pub latency: Vec<LatencyPercentiles>,
+}
+
+impl Default for PerformanceMetrics {
+    fn default() -> Self {
+        Self {
+            success_rate: 1.0,
+            average_response_time_ms: 100,
+            total_requests: 0,
+            failed_requests: 0,
+            memory_usage_mb: 0,
+            cpu_usage_percent: 0.0,
+            open_file_descriptors: None,
+            child_processes: 0,
+            uptime_seconds: 0,
+            latency: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    pub timestamp: String,
+    pub metrics: PerformanceMetrics,
+    pub alerts: Vec<PerformanceAlert>,
+    pub recommendations: Vec<String>,
+    /// Métricas por adaptador y por herramienta
+    #[serde(default)]
+    pub series: Vec<SeriesMetrics>,
+}
+
+/// Origen de una serie de métricas
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SeriesKind {
+    Adapter,
+    Model,
+    Tool,
+}
+
+/// Métricas acumuladas de un adaptador o una herramienta
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeriesMetrics {
+    pub kind: SeriesKind,
+    pub name: String,
+    pub total_requests: u64,
+    pub failed_requests: u64,
+    pub success_rate: f64,
+    pub average_response_time_ms: u64,
+    pub max_response_time_ms: u64,
+    #[serde(default)]
+    pub latency: Vec<LatencyPercentiles>,
+}
+
+#[derive(Debug, Clone)]
+struct SeriesStats {
+    total_requests: u64,
+    failed_requests: u64,
+    latency: LatencyTracker,
+}
+
+/// Muestra de una llamada medida fuera del monitor (p. ej. por `MeteredAdapter`)
+#[derive(Debug, Clone)]
+pub struct CallSample {
+    pub kind: SeriesKind,
+    pub name: String,
+    pub duration: Duration,
+    pub success: bool,
+}
+
+/// Buzón compartido de muestras pendientes de volcar en el monitor
+pub type SampleInbox = Arc<Mutex<Vec<CallSample>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceAlert {
+    pub severity: AlertSeverity,
+    pub message: String,
+    pub metric_name: String,
+    pub current_value: f64,
+    pub threshold: f64,
+}
+
+pub struct PerformanceMonitor {
+    start_time: Instant,
+    metrics: PerformanceMetrics,
+    thresholds: AlertThresholds,
+    /// Ventanas de tiempo para los percentiles (además de la sesión)
+    windows: Vec<Duration>,
+    latency: LatencyTracker,
+    series: BTreeMap<(SeriesKind, String), SeriesStats>,
+    sampler: Option<Arc<ResourceSampler>>,
+    /// Evalúa las alertas de recursos con cada muestra; vive con el monitor
+    resource_listener: Option<Arc<SampleListener>>,
+    /// Alertas activas, para aplicar histéresis
+    active_alerts: Arc<Mutex<HashSet<String>>>,
+}
+
+/// Alerta posible: severidad, mensaje, métrica, valor (si se conoce) y umbral
+type AlertCandidate = (AlertSeverity, String, String, Option<f64>, f64);
+
+impl PerformanceMonitor {
+    pub fn new() -> Self {
+        Self {
+            start_time: Instant::now(),
+            metrics: PerformanceMetrics::default(),
+            thresholds: AlertThresholds::default(),
+            windows: DEFAULT_WINDOWS.to_vec(),
+            latency: LatencyTracker::new(retention(&DEFAULT_WINDOWS)),
+            series: BTreeMap::new(),
+            sampler: None,
+            resource_listener: None,
+            active_alerts: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+    
+    pub fn with_thresholds(thresholds: AlertThresholds) -> Self {
+        Self {
+            start_time: Instant::now(),
+            metrics: PerformanceMetrics::default(),
+            thresholds,
+            windows: DEFAULT_WINDOWS.to_vec(),
+            latency: LatencyTracker::new(retention(&DEFAULT_WINDOWS)),
+            series: BTreeMap::new(),
+            sampler: None,
+            resource_listener: None,
+            active_alerts: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Cambia las ventanas de los percentiles (las series ya registradas
+    /// conservan su retención anterior)
+    pub fn with_windows(mut self, windows: Vec<Duration>) -> Self {
+        self.latency = LatencyTracker::new(retention(&windows));
+        self.windows = windows;
+        self
+    }
+
+    /// Empieza a muestrear CPU, memoria, descriptores y procesos hijos con
+    /// el muestreador compartido; las alertas de recursos se evalúan con
+    /// cada muestra y se avisa en el log de las que se disparan
+    pub fn start_sampling(&mut self, interval: Duration) {
+        let sampler = match ResourceSampler::shared(interval) {
+            Ok(sampler) => sampler,
+            Err(e) => {
+                log::warn!("⚠️ No se pudo iniciar el muestreo de recursos: {}", e);
+                return;
+            }
+        };
+
+        let thresholds = self.thresholds.clone();
+        let active = Arc::clone(&self.active_alerts);
+        let listener: Arc<SampleListener> = Arc::new(move |usage: &ResourceUsage| {
+            let was_active = active.lock().map(|active| active.clone()).unwrap_or_default();
+            for alert in evaluate_alerts(resource_candidates(usage, &thresholds), &thresholds, &active) {
+                if !was_active.contains(&alert.metric_name) {
+                    log::warn!("🚨 {}: {:.1} (umbral {:.1})", alert.message, alert.current_value, alert.threshold);
+                }
+            }
+        });
+        sampler.subscribe(&listener);
+        self.resource_listener = Some(listener);
+        self.sampler = Some(sampler);
+    }
+
+    /// Vuelca en las métricas la última muestra de recursos
+    pub fn refresh_resources(&mut self) {
+        if let Some(usage) = self.sampler.as_ref().and_then(|sampler| sampler.latest()) {
+            apply_resource_usage(&mut self.metrics, &usage);
+        }
+        self.metrics.uptime_seconds = self.start_time.elapsed().as_secs();
+    }
+    
+    pub fn record_request(&mut self, duration: Duration, success: bool) {
+        self.metrics.total_requests += 1;
+        if !success {
+            self.metrics.failed_requests += 1;
+        }
+        
+        self.latency.record(duration);
+        
+        // Actualizar métricas
+        self.update_metrics();
+    }
+    
+    /// Registra una llamada en la serie de un adaptador, modelo o herramienta
+    pub fn record_series(&mut self, kind: SeriesKind, name: &str, duration: Duration, success: bool) {
+        let retention = retention(&self.windows);
+        let stats = self.series.entry((kind, name.to_string())).or_insert_with(|| SeriesStats {
+            total_requests: 0,
+            failed_requests: 0,
+            latency: LatencyTracker::new(retention),
+        });
+        stats.total_requests += 1;
+        if !success {
+            stats.failed_requests += 1;
+        }
+        stats.latency.record(duration);
+    }
+
+    /// Vuelca las muestras acumuladas en un buzón
+    pub fn drain_samples(&mut self, inbox: &SampleInbox) {
+        let samples: Vec<CallSample> = match inbox.lock() {
+            Ok(mut samples) => samples.drain(..).collect(),
+            Err(_) => return,
+        };
+        for sample in samples {
+            self.record_series(sample.kind, &sample.name, sample.duration, sample.success);
+        }
+    }
+
+    /// Métricas de todas las series, ordenadas por tipo y nombre
+    pub fn get_series(&self) -> Vec<SeriesMetrics> {
+        self.series.iter().map(|((kind, name), stats)| SeriesMetrics {
+            kind: kind.clone(),
+            name: name.clone(),
+            total_requests: stats.total_requests,
+            failed_requests: stats.failed_requests,
+            success_rate: if stats.total_requests > 0 {
+                1.0 - stats.failed_requests as f64 / stats.total_requests as f64
+            } else {
+                1.0
+            },
+            average_response_time_ms: stats.latency.mean_ms(),
+            max_response_time_ms: stats.latency.max().as_millis() as u64,
+            latency: self.latency_percentiles(&stats.latency),
+        }).collect()
+    }
+
+    /// Percentiles de cada ventana configurada y de la sesión
+    fn latency_percentiles(&self, tracker: &LatencyTracker) -> Vec<LatencyPercentiles> {
+        self.windows.iter()
+            .map(|window| Some(*window))
+            .chain(std::iter::once(None))
+            .map(|window| tracker.percentiles(window))
+            .collect()
+    }
+
+    pub fn thresholds(&self) -> &AlertThresholds {
+        &self.thresholds
+    }
+
+    pub fn get_metrics(&self) -> &PerformanceMetrics {
+        &self.metrics
+    }
+    
+    pub fn get_report(&self) -> PerformanceReport {
+        let alerts = self.check_alerts();
+        let recommendations = self.generate_recommendations(&alerts);
+        
+        PerformanceReport {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            metrics: self.current_metrics(),
+            alerts,
+            recommendations,
+            series: self.get_series(),
+        }
+    }
+    
+    fn update_metrics(&mut self) {
+        // Calcular tasa de éxito
+        if self.metrics.total_requests > 0 {
+            self.metrics.success_rate = 1.0 - (self.metrics.failed_requests as f64 / self.metrics.total_requests as f64);
+        }
+        
+        // Calcular tiempo promedio de respuesta y percentiles
+        if self.latency.count() > 0 {
+            self.metrics.average_response_time_ms = self.latency.mean_ms();
+        }
+        self.metrics.latency = self.latency_percentiles(&self.latency);
+        
+        // Actualizar uptime y recursos
+        self.refresh_resources();
+    }
+
+    /// Métricas con la última muestra de recursos aplicada
+    fn current_metrics(&self) -> PerformanceMetrics {
+        let mut metrics = self.metrics.clone();
+        if let Some(usage) = self.sampler.as_ref().and_then(|sampler| sampler.latest()) {
+            apply_resource_usage(&mut metrics, &usage);
+        }
+        metrics.uptime_seconds = self.start_time.elapsed().as_secs();
+        metrics.latency = self.latency_percentiles(&self.latency);
+        metrics
+    }
+    
+    fn check_alerts(&self) -> Vec<PerformanceAlert> {
+        let metrics = self.current_metrics();
+        let thresholds = &self.thresholds;
+
+        let mut candidates: Vec<AlertCandidate> = vec![
+            (AlertSeverity::High, "Tiempo de respuesta elevado".to_string(), "response_time".to_string(),
+                Some(metrics.average_response_time_ms as f64), thresholds.response_time_ms as f64),
+            (AlertSeverity::Critical, "Tasa de error elevada".to_string(), "error_rate".to_string(),
+                Some(1.0 - metrics.success_rate), thresholds.error_rate),
+        ];
+        let usage = ResourceUsage {
+            memory_usage_mb: metrics.memory_usage_mb,
+            cpu_usage_percent: metrics.cpu_usage_percent,
+            open_file_descriptors: metrics.open_file_descriptors,
+            child_processes: metrics.child_processes,
+        };
+        candidates.extend(resource_candidates(&usage, thresholds));
+        for threshold in &thresholds.latency_percentiles {
+            candidates.push((
+                AlertSeverity::High,
+                format!("Latencia p{} elevada ({})", threshold.percentile, latency::window_label(threshold.window())),
+                threshold.metric_name(),
+                self.latency.percentile(threshold.percentile, threshold.window()).map(|ms| ms as f64),
+                threshold.threshold_ms as f64,
+            ));
+        }
+
+        evaluate_alerts(candidates, thresholds, &self.active_alerts)
+    }
+    
+    fn generate_recommendations(&self, alerts: &[PerformanceAlert]) -> Vec<String> {
+        let mut recommendations = Vec::new();
+        
+        for alert in alerts {
+            // Las alertas de percentiles comparten recomendaciones
+            let metric = if alert.metric_name.starts_with("latency_p") { "latency" } else { alert.metric_name.as_str() };
+            match metric {
+                "response_time" | "latency" => {
+                    recommendations.push("Considera optimizar las consultas a la base de datos".to_string());
+                    recommendations.push("Implementa caché para respuestas frecuentes".to_string());
+                }
+                "error_rate" => {
+                    recommendations.push("Revisa los logs para identificar errores comunes".to_string());
+                    recommendations.push("Implementa reintentos automáticos para fallos transitorios".to_string());
+                }
+                "memory_usage" => {
+                    recommendations.push("Reduce el contexto enviado a los adaptadores o el número de tareas en paralelo".to_string());
+                }
+                "cpu_usage" => {
+                    recommendations.push("Limita el número de agentes concurrentes".to_string());
+                }
+                "open_file_descriptors" => {
+                    recommendations.push("Revisa si hay ficheros o sockets que no se cierran".to_string());
+                }
+                "child_processes" => {
+                    recommendations.push("Comprueba que los procesos gemini-cli terminan correctamente".to_string());
+                }
+                _ => {}
+            }
+        }
+        
+        recommendations
+    }
+}
+
+/// Retención necesaria para la ventana más larga
+fn retention(windows: &[Duration]) -> Duration {
+    windows.iter().max().copied().unwrap_or(Duration::ZERO)
+}
+
+/// Alertas posibles sobre una muestra de recursos
+fn resource_candidates(usage: &ResourceUsage, thresholds: &AlertThresholds) -> Vec<AlertCandidate> {
+    vec![
+        (AlertSeverity::High, "Uso de memoria elevado".to_string(), "memory_usage".to_string(),
+            Some(usage.memory_usage_mb as f64), thresholds.memory_usage_mb as f64),
+        (AlertSeverity::Medium, "Uso de CPU elevado".to_string(), "cpu_usage".to_string(),
+            Some(usage.cpu_usage_percent), thresholds.cpu_usage_percent),
+        (AlertSeverity::Medium, "Demasiados descriptores de fichero abiertos".to_string(), "open_file_descriptors".to_string(),
+            usage.open_file_descriptors.map(|fds| fds as f64), thresholds.open_file_descriptors as f64),
+        (AlertSeverity::Low, "Demasiados procesos hijos".to_string(), "child_processes".to_string(),
+            Some(usage.child_processes as f64), thresholds.child_processes as f64),
+    ]
+}
+
+/// Alertas que se disparan, actualizando el conjunto de activas
+fn evaluate_alerts(
+    candidates: Vec<AlertCandidate>,
+    thresholds: &AlertThresholds,
+    active_alerts: &Mutex<HashSet<String>>,
+) -> Vec<PerformanceAlert> {
+    let Ok(mut active) = active_alerts.lock() else {
+        return Vec::new();
+    };
+    let mut alerts = Vec::new();
+    for (severity, message, metric_name, value, threshold) in candidates {
+        let Some(value) = value else { continue };
+
+        // Una alerta activa solo se apaga al bajar del umbral con margen
+        let firing = if active.contains(&metric_name) {
+            value > threshold * (1.0 - thresholds.hysteresis)
+        } else {
+            value > threshold
+        };
+        if !firing {
+            active.remove(&metric_name);
+            continue;
+        }
+        active.insert(metric_name.clone());
+        alerts.push(PerformanceAlert {
+            severity,
+            message,
+            metric_name,
+            current_value: value,
+            threshold,
+        });
+    }
+    alerts
+}
+
+fn apply_resource_usage(metrics: &mut PerformanceMetrics, usage: &ResourceUsage) {
+    metrics.memory_usage_mb = usage.memory_usage_mb;
+    metrics.cpu_usage_percent = usage.cpu_usage_percent;
+    metrics.open_file_descriptors = usage.open_file_descriptors;
+    metrics.child_processes = usage.child_processes;
+}
+
+// ============================================================================
+// ADAPTADOR MEDIDO
+// ============================================================================
+
+/// Decorador que mide cada llamada al adaptador, deja la muestra en un buzón
+/// y corta las llamadas si el adaptador falla de forma continuada
+pub struct MeteredAdapter {
+    name: String,
+    inner: Arc<dyn CodeGenerationFlow>,
+    inbox: SampleInbox,
+    breaker: CircuitBreaker,
+}
+
+impl MeteredAdapter {
+    pub fn new(name: &str, inner: Arc<dyn CodeGenerationFlow>, inbox: SampleInbox) -> Self {
+        Self { name: name.to_string(), inner, inbox, breaker: CircuitBreaker::new(name) }
+    }
+
+    pub fn circuit_state(&self) -> CircuitState {
+        self.breaker.state()
+    }
+}
+
+#[async_trait]
+impl CodeGenerationFlow for MeteredAdapter {
+    async fn execute(&self, problem_description: &str) -> Result<CodeGenerationResult, FlowError> {
+        self.execute_with_tools(problem_description, &[]).await
+    }
+
+    async fn execute_with_tools(
+        &self,
+        problem_description: &str,
+        tools: &[serde_json::Value],
+    ) -> Result<CodeGenerationResult, FlowError> {
+        let mut span = Span::start(&format!("adapter {}", self.name), SpanKind::Adapter);
+        span.set_attribute("adapter", self.name.as_str());
+
+        if let Err(e) = self.breaker.allow() {
+            span.set_error(e.to_string());
+            exporter::adapter_call(&self.name, false, Duration::ZERO);
+            return Err(e);
+        }
+        let start = Instant::now();
+        let result = span.scope(self.inner.execute_with_tools(problem_description, tools)).await;
+        let duration = start.elapsed();
+        self.breaker.record(&result);
+
+        exporter::adapter_call(&self.name, result.is_ok(), duration);
+        if let Err(e) = &result {
+            span.set_error(e.to_string());
+        }
+        if let Some(estimate) = result.as_ref().ok().and_then(|r| r.cost_estimate.as_ref()) {
+            span.set_attribute("model", estimate.model_used.as_str());
+            span.set_attribute("tokens.input", estimate.input_tokens);
+            span.set_attribute("tokens.output", estimate.output_tokens);
+            span.set_attribute("tokens.total", estimate.input_tokens + estimate.output_tokens);
+            span.set_attribute("cost_usd", estimate.estimated_cost_usd);
+            exporter::adapter_usage(
+                &self.name,
+                &estimate.model_used,
+                estimate.input_tokens,
+                estimate.output_tokens,
+                estimate.estimated_cost_usd,
+            );
+        }
+        if let Ok(mut samples) = self.inbox.lock() {
+            samples.push(CallSample {
+                kind: SeriesKind::Adapter,
+                name: self.name.clone(),
+                duration,
+                success: result.is_ok(),
+            });
+        }
+        result
+    }
+
+    fn verify_code(&self, code: &str) -> VerificationResult {
+        self.inner.verify_code(code)
+    }
+
+    fn get_capabilities(&self) -> AdapterCapabilities {
+        self.inner.get_capabilities()
+    }
+
+    fn cache_fingerprint(&self) -> String {
+        self.inner.cache_fingerprint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alerts_use_hysteresis() {
+        let mut monitor = PerformanceMonitor::with_thresholds(AlertThresholds {
+            response_time_ms: 100,
+            hysteresis: 0.5,
+            ..Default::default()
+        });
+        let fires = |monitor: &PerformanceMonitor| monitor.get_report().alerts.iter()
+            .any(|alert| alert.metric_name == "response_time");
+
+        monitor.record_request(Duration::from_millis(200), true);
+        assert!(fires(&monitor));
+
+        // Media de 100ms: ya no supera el umbral, pero sigue sobre el margen
+        monitor.record_request(Duration::from_millis(0), true);
+        assert!(fires(&monitor));
+
+        // Media de 50ms: baja del margen y la alerta se apaga
+        monitor.record_request(Duration::from_millis(0), true);
+        monitor.record_request(Duration::from_millis(0), true);
+        assert!(!fires(&monitor));
+    }
+
+    #[test]
+    fn test_resource_alerts_fire_on_each_sample() {
+        let mut monitor = PerformanceMonitor::with_thresholds(AlertThresholds {
+            memory_usage_mb: 0,
+            ..Default::default()
+        });
+        monitor.start_sampling(Duration::from_millis(10));
+
+        // Sin pedir el informe: la alerta la activa el propio muestreo
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !monitor.active_alerts.lock().unwrap().contains("memory_usage") {
+            assert!(Instant::now() < deadline, "la alerta de memoria no se activó");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -0,0 +1,497 @@
+// ============================================================================
+// PERFORMANCE REPORT - Informe agregado de las ejecuciones persistidas
+// ============================================================================
+// Lee los journals de `~/.enjambre/runs/*/journal.jsonl`, filtra las entradas
+// por rango de fechas y las pasa por un `PerformanceMonitor` para obtener
+// tasa de éxito, percentiles de latencia, series por adaptador y herramienta,
+// alertas y recomendaciones. Se muestra como tablas de texto, JSON o un HTML
+// autocontenido con gráficos SVG.
+// ============================================================================
+
+use super::{AlertThresholds, LatencyPercentiles, PerformanceAlert, PerformanceMonitor, SeriesKind, SeriesMetrics};
+use crate::swarm::journal::{JournalEntry, JournalEvent, RunJournal, JOURNAL_FILE};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Rango de fechas del informe (ambos extremos incluidos)
+#[derive(Debug, Clone, Default)]
+pub struct ReportFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl ReportFilter {
+    pub fn contains(&self, timestamp: &DateTime<Utc>) -> bool {
+        self.since.is_none_or(|since| *timestamp >= since) && self.until.is_none_or(|until| *timestamp <= until)
+    }
+}
+
+/// Parsea un extremo del rango: RFC 3339, fecha `YYYY-MM-DD` (con `end_of_day`
+/// se toma el final del día) o una antigüedad relativa como `24h` o `7d`
+pub fn parse_time_bound(text: &str, end_of_day: bool) -> Result<DateTime<Utc>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(text) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        let time = if end_of_day { date.and_hms_opt(23, 59, 59) } else { date.and_hms_opt(0, 0, 0) };
+        return time.map(|time| time.and_utc()).ok_or_else(|| format!("Fecha inválida: '{}'", text));
+    }
+    let age = super::latency::parse_duration(text)
+        .map_err(|_| format!("Fecha inválida: '{}' (usa YYYY-MM-DD, RFC 3339 o una antigüedad como 7d)", text))?;
+    let age = chrono::Duration::from_std(age).map_err(|e| e.to_string())?;
+    Ok(Utc::now() - age)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub model: String,
+    pub tasks: u64,
+    pub failed_tasks: u64,
+    pub cost_usd: f64,
+    pub average_latency_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyUsage {
+    pub date: String,
+    pub tasks: u64,
+    pub failed_tasks: u64,
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregatedReport {
+    pub generated_at: DateTime<Utc>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub runs: u64,
+    pub tasks: u64,
+    pub failed_tasks: u64,
+    pub success_rate: f64,
+    pub total_cost_usd: f64,
+    pub latency: LatencyPercentiles,
+    pub models: Vec<ModelUsage>,
+    pub adapters: Vec<SeriesMetrics>,
+    pub tools: Vec<SeriesMetrics>,
+    pub daily: Vec<DailyUsage>,
+    pub alerts: Vec<PerformanceAlert>,
+    pub recommendations: Vec<String>,
+}
+
+/// Carga todos los journals de un directorio de ejecuciones
+pub fn load_journals(runs_dir: &Path) -> Vec<Vec<JournalEntry>> {
+    let Ok(entries) = std::fs::read_dir(runs_dir) else {
+        return Vec::new();
+    };
+    let mut journals = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path().join(JOURNAL_FILE);
+        if !path.is_file() {
+            continue;
+        }
+        match RunJournal::load_from(&path) {
+            Ok(journal) => journals.push(journal),
+            Err(e) => log::warn!("⚠️ Se omite {}: {}", path.display(), e),
+        }
+    }
+    journals
+}
+
+/// El journal es un replay de otra ejecución (`swarm replay`): no es
+/// trabajo real y no debe contar en informes ni análisis
+pub fn is_replay(journal: &[JournalEntry]) -> bool {
+    journal.iter().any(|entry| matches!(&entry.event, JournalEvent::RunStarted { replay_of: Some(_), .. }))
+}
+
+/// Agrega los journals dentro del rango, sin contar los replays
+pub fn aggregate(journals: &[Vec<JournalEntry>], filter: &ReportFilter, thresholds: AlertThresholds) -> AggregatedReport {
+    // Solo percentiles de la sesión: las ventanas móviles no tienen sentido
+    // para datos históricos
+    let mut monitor = PerformanceMonitor::with_thresholds(thresholds).with_windows(Vec::new());
+    let mut models: BTreeMap<String, ModelUsage> = BTreeMap::new();
+    let mut daily: BTreeMap<String, DailyUsage> = BTreeMap::new();
+    let mut runs = 0;
+
+    for journal in journals.iter().filter(|journal| !is_replay(journal)) {
+        let mut in_range = false;
+        for entry in journal.iter().filter(|entry| filter.contains(&entry.timestamp)) {
+            in_range = true;
+            match &entry.event {
+                JournalEvent::TaskFinished { result } => {
+                    let duration = Duration::from_millis(result.execution_time_ms);
+                    monitor.record_request(duration, result.success);
+
+                    let model = format!("{:?}", result.selected_model);
+                    monitor.record_series(SeriesKind::Model, &model, duration, result.success);
+                    let usage = models.entry(model.clone()).or_insert_with(|| ModelUsage {
+                        model,
+                        tasks: 0,
+                        failed_tasks: 0,
+                        cost_usd: 0.0,
+                        average_latency_ms: 0,
+                    });
+                    usage.average_latency_ms = (usage.average_latency_ms * usage.tasks + result.execution_time_ms)
+                        / (usage.tasks + 1);
+                    usage.tasks += 1;
+                    usage.cost_usd += result.cost_actual;
+
+                    let date = entry.timestamp.format("%Y-%m-%d").to_string();
+                    let day = daily.entry(date.clone()).or_insert_with(|| DailyUsage {
+                        date,
+                        tasks: 0,
+                        failed_tasks: 0,
+                        cost_usd: 0.0,
+                    });
+                    day.tasks += 1;
+                    day.cost_usd += result.cost_actual;
+                    if !result.success {
+                        usage.failed_tasks += 1;
+                        day.failed_tasks += 1;
+                    }
+                }
+                JournalEvent::Response { adapter, result, duration_ms, .. } => {
+                    monitor.record_series(SeriesKind::Adapter, adapter, Duration::from_millis(*duration_ms), result.is_ok());
+                }
+                JournalEvent::ToolCall { tool, result, duration_ms, .. } => {
+                    monitor.record_series(SeriesKind::Tool, tool, Duration::from_millis(*duration_ms), result.is_ok());
+                }
+                _ => {}
+            }
+        }
+        if in_range {
+            runs += 1;
+        }
+    }
+
+    let performance = monitor.get_report();
+    let (adapters, tools): (Vec<SeriesMetrics>, Vec<SeriesMetrics>) = performance.series.into_iter()
+        .filter(|series| series.kind != SeriesKind::Model)
+        .partition(|series| series.kind == SeriesKind::Adapter);
+    let models: Vec<ModelUsage> = models.into_values().collect();
+    let tasks = performance.metrics.total_requests;
+
+    AggregatedReport {
+        generated_at: Utc::now(),
+        since: filter.since,
+        until: filter.until,
+        runs,
+        tasks,
+        failed_tasks: performance.metrics.failed_requests,
+        success_rate: if tasks > 0 { performance.metrics.success_rate } else { 0.0 },
+        total_cost_usd: models.iter().fold(0.0, |total, m| total + m.cost_usd),
+        latency: performance.metrics.latency.last().cloned().unwrap_or_else(|| LatencyPercentiles {
+            window: "session".to_string(),
+            count: 0,
+            p50_ms: 0,
+            p90_ms: 0,
+            p95_ms: 0,
+            p99_ms: 0,
+            max_ms: 0,
+        }),
+        models,
+        adapters,
+        tools,
+        daily: daily.into_values().collect(),
+        alerts: performance.alerts,
+        recommendations: performance.recommendations,
+    }
+}
+
+// ============================================================================
+// TEXTO
+// ============================================================================
+
+fn format_range(report: &AggregatedReport) -> String {
+    let bound = |bound: Option<DateTime<Utc>>| bound
+        .map(|b| b.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "…".to_string());
+    format!("{} → {}", bound(report.since), bound(report.until))
+}
+
+pub fn render_text(report: &AggregatedReport) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("📊 Informe de rendimiento ({})\n\n", format_range(report)));
+    out.push_str(&format!("  Ejecuciones:      {}\n", report.runs));
+    out.push_str(&format!("  Tareas:           {} ({} fallidas)\n", report.tasks, report.failed_tasks));
+    out.push_str(&format!("  Tasa de éxito:    {:.1}%\n", report.success_rate * 100.0));
+    out.push_str(&format!("  Costo total:      ${:.4}\n", report.total_cost_usd));
+    out.push_str(&format!(
+        "  Latencia:         p50 {}ms · p90 {}ms · p95 {}ms · p99 {}ms · máx {}ms\n",
+        report.latency.p50_ms, report.latency.p90_ms, report.latency.p95_ms, report.latency.p99_ms, report.latency.max_ms,
+    ));
+
+    if !report.models.is_empty() {
+        out.push_str("\n💰 Costo por modelo\n");
+        out.push_str(&format!("  {:<20} {:>7} {:>8} {:>12} {:>12}\n", "MODELO", "TAREAS", "FALLOS", "COSTO", "LAT. MEDIA"));
+        for model in &report.models {
+            out.push_str(&format!(
+                "  {:<20} {:>7} {:>8} {:>12} {:>10}ms\n",
+                model.model, model.tasks, model.failed_tasks, format!("${:.4}", model.cost_usd), model.average_latency_ms,
+            ));
+        }
+    }
+
+    for (title, series) in [("🤖 Adaptadores", &report.adapters), ("🔧 Uso de herramientas", &report.tools)] {
+        if series.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n{}\n", title));
+        out.push_str(&format!("  {:<20} {:>8} {:>8} {:>10} {:>10} {:>10}\n", "NOMBRE", "LLAMADAS", "FALLOS", "MEDIA", "P95", "MÁX"));
+        for entry in series {
+            let p95 = entry.latency.last().map(|l| l.p95_ms).unwrap_or(0);
+            out.push_str(&format!(
+                "  {:<20} {:>8} {:>8} {:>8}ms {:>8}ms {:>8}ms\n",
+                entry.name, entry.total_requests, entry.failed_requests,
+                entry.average_response_time_ms, p95, entry.max_response_time_ms,
+            ));
+        }
+    }
+
+    if !report.alerts.is_empty() {
+        out.push_str("\n🚨 Alertas\n");
+        for alert in &report.alerts {
+            out.push_str(&format!(
+                "  [{:?}] {}: {} (valor {:.2}, umbral {:.2})\n",
+                alert.severity, alert.metric_name, alert.message, alert.current_value, alert.threshold,
+            ));
+        }
+    }
+    if !report.recommendations.is_empty() {
+        out.push_str("\n💡 Recomendaciones\n");
+        for recommendation in &report.recommendations {
+            out.push_str(&format!("  - {}\n", recommendation));
+        }
+    }
+    out
+}
+
+// ============================================================================
+// HTML
+// ============================================================================
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Gráfico de barras horizontal en SVG
+fn bar_chart(title: &str, rows: &[(String, f64, String)]) -> String {
+    if rows.is_empty() {
+        return String::new();
+    }
+    const ROW_HEIGHT: usize = 26;
+    const LABEL_WIDTH: usize = 170;
+    const BAR_WIDTH: f64 = 420.0;
+
+    let max = rows.iter().map(|(_, value, _)| *value).fold(0.0, f64::max);
+    let height = rows.len() * ROW_HEIGHT + 10;
+    let mut svg = format!(
+        "<h2>{}</h2>\n<svg width=\"{}\" height=\"{}\" role=\"img\">\n",
+        escape(title), LABEL_WIDTH + BAR_WIDTH as usize + 120, height,
+    );
+    for (i, (label, value, caption)) in rows.iter().enumerate() {
+        let y = i * ROW_HEIGHT + 5;
+        let width = if max > 0.0 { (value / max * BAR_WIDTH).max(1.0) } else { 1.0 };
+        svg.push_str(&format!(
+            "  <text x=\"0\" y=\"{}\" class=\"label\">{}</text>\n  <rect x=\"{}\" y=\"{}\" width=\"{:.1}\" height=\"18\" class=\"bar\"/>\n  <text x=\"{:.1}\" y=\"{}\" class=\"value\">{}</text>\n",
+            y + 14, escape(label), LABEL_WIDTH, y, width, LABEL_WIDTH as f64 + width + 6.0, y + 14, escape(caption),
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn html_table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut html = String::from("<table>\n<tr>");
+    for header in headers {
+        html.push_str(&format!("<th>{}</th>", escape(header)));
+    }
+    html.push_str("</tr>\n");
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!("<td>{}</td>", escape(&cell)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+pub fn render_html(report: &AggregatedReport) -> String {
+    let mut body = String::new();
+    body.push_str(&format!("<h1>Informe de rendimiento</h1>\n<p class=\"range\">{}</p>\n", escape(&format_range(report))));
+    body.push_str("<div class=\"cards\">\n");
+    for (label, value) in [
+        ("Ejecuciones", report.runs.to_string()),
+        ("Tareas", report.tasks.to_string()),
+        ("Tasa de éxito", format!("{:.1}%", report.success_rate * 100.0)),
+        ("Costo total", format!("${:.4}", report.total_cost_usd)),
+        ("Latencia p50", format!("{}ms", report.latency.p50_ms)),
+        ("Latencia p95", format!("{}ms", report.latency.p95_ms)),
+        ("Latencia p99", format!("{}ms", report.latency.p99_ms)),
+    ] {
+        body.push_str(&format!("<div class=\"card\"><span>{}</span><strong>{}</strong></div>\n", escape(label), escape(&value)));
+    }
+    body.push_str("</div>\n");
+
+    body.push_str(&bar_chart("Costo por modelo", &report.models.iter()
+        .map(|m| (m.model.clone(), m.cost_usd, format!("${:.4} · {} tareas", m.cost_usd, m.tasks)))
+        .collect::<Vec<_>>()));
+    body.push_str(&bar_chart("Tareas por día", &report.daily.iter()
+        .map(|d| (d.date.clone(), d.tasks as f64, format!("{} ({} fallidas) · ${:.4}", d.tasks, d.failed_tasks, d.cost_usd)))
+        .collect::<Vec<_>>()));
+    body.push_str(&bar_chart("Uso de herramientas", &report.tools.iter()
+        .map(|t| (t.name.clone(), t.total_requests as f64, format!("{} llamadas · {:.0}% éxito", t.total_requests, t.success_rate * 100.0)))
+        .collect::<Vec<_>>()));
+
+    let series_rows = |series: &[SeriesMetrics]| series.iter().map(|s| vec![
+        s.name.clone(),
+        s.total_requests.to_string(),
+        s.failed_requests.to_string(),
+        format!("{}ms", s.average_response_time_ms),
+        format!("{}ms", s.latency.last().map(|l| l.p95_ms).unwrap_or(0)),
+        format!("{}ms", s.max_response_time_ms),
+    ]).collect::<Vec<_>>();
+    let headers = ["Nombre", "Llamadas", "Fallos", "Media", "p95", "Máx"];
+    if !report.adapters.is_empty() {
+        body.push_str("<h2>Adaptadores</h2>\n");
+        body.push_str(&html_table(&headers, series_rows(&report.adapters)));
+    }
+    if !report.tools.is_empty() {
+        body.push_str("<h2>Herramientas</h2>\n");
+        body.push_str(&html_table(&headers, series_rows(&report.tools)));
+    }
+
+    if !report.alerts.is_empty() {
+        body.push_str("<h2>Alertas</h2>\n");
+        body.push_str(&html_table(&["Severidad", "Métrica", "Mensaje", "Valor", "Umbral"], report.alerts.iter().map(|a| vec![
+            format!("{:?}", a.severity),
+            a.metric_name.clone(),
+            a.message.clone(),
+            format!("{:.2}", a.current_value),
+            format!("{:.2}", a.threshold),
+        ]).collect()));
+    }
+    if !report.recommendations.is_empty() {
+        body.push_str("<h2>Recomendaciones</h2>\n<ul>\n");
+        for recommendation in &report.recommendations {
+            body.push_str(&format!("<li>{}</li>\n", escape(recommendation)));
+        }
+        body.push_str("</ul>\n");
+    }
+
+    format!(r#"<!DOCTYPE html>
+<html lang="es">
+<head>
+<meta charset="utf-8">
+<title>Enjambre · Informe de rendimiento</title>
+<style>
+body {{ font-family: system-ui, sans-serif; margin: 2rem auto; max-width: 960px; color: #1f2933; }}
+h1 {{ margin-bottom: 0; }}
+.range {{ color: #616e7c; margin-top: 0.25rem; }}
+.cards {{ display: flex; flex-wrap: wrap; gap: 0.75rem; margin: 1.5rem 0; }}
+.card {{ background: #f5f7fa; border-radius: 8px; padding: 0.75rem 1rem; min-width: 120px; }}
+.card span {{ display: block; font-size: 0.8rem; color: #616e7c; }}
+.card strong {{ font-size: 1.3rem; }}
+table {{ border-collapse: collapse; width: 100%; margin-bottom: 1.5rem; }}
+th, td {{ text-align: left; padding: 0.4rem 0.6rem; border-bottom: 1px solid #e4e7eb; }}
+svg .bar {{ fill: #3e7bfa; }}
+svg .label, svg .value {{ font-size: 12px; fill: #323f4b; }}
+</style>
+</head>
+<body>
+{}<p class="range">Generado {}</p>
+</body>
+</html>
+"#, body, report.generated_at.to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost_optimizer::ModelChoice;
+    use crate::swarm::SwarmExecutionResult;
+
+    fn finished(day: &str, model: ModelChoice, success: bool, cost: f64, ms: u64) -> JournalEntry {
+        JournalEntry {
+            sequence: 0,
+            timestamp: parse_time_bound(day, false).unwrap(),
+            elapsed_ms: 0,
+            event: JournalEvent::TaskFinished {
+                result: Box::new(SwarmExecutionResult {
+                    task_id: "t".to_string(),
+                    success,
+                    result: None,
+                    thinking_result: None,
+                    error: None,
+                    error_kind: None,
+                    selected_adapter: "gemini".to_string(),
+                    selected_model: model,
+                    execution_time_ms: ms,
+                    performance_score: 0.0,
+                    cost_actual: cost,
+                    cost_saved: 0.0,
+                    optimization_applied: true,
+                    output: None,
+                    savings: None,
+                    context_report: None,
+                    labels: Default::default(),
+                }),
+            },
+        }
+    }
+
+    #[test]
+    fn test_aggregate_filters_by_date_range() {
+        let journals = vec![
+            vec![finished("2026-01-01", ModelChoice::Gemini15Pro, true, 0.01, 4000)],
+            vec![
+                finished("2026-02-01", ModelChoice::Gemini15Flash, true, 0.001, 1000),
+                finished("2026-02-02", ModelChoice::Gemini15Flash, false, 0.002, 3000),
+            ],
+        ];
+        let filter = ReportFilter {
+            since: Some(parse_time_bound("2026-01-15", false).unwrap()),
+            until: Some(parse_time_bound("2026-02-02", true).unwrap()),
+        };
+
+        let report = aggregate(&journals, &filter, AlertThresholds::default());
+        assert_eq!((report.runs, report.tasks, report.failed_tasks), (1, 2, 1));
+        assert_eq!(report.models.len(), 1);
+        assert_eq!(report.models[0].average_latency_ms, 2000);
+        assert!((report.total_cost_usd - 0.003).abs() < 1e-9);
+        assert_eq!(report.daily.len(), 2);
+        assert!(report.alerts.iter().any(|alert| alert.metric_name == "error_rate"));
+
+        assert!(render_text(&report).contains("Gemini15Flash"));
+        assert!(render_html(&report).contains("<svg"));
+    }
+
+    #[test]
+    fn test_aggregate_skips_replay_journals() {
+        let replay_started = JournalEntry {
+            sequence: 0,
+            timestamp: parse_time_bound("2026-02-01", false).unwrap(),
+            elapsed_ms: 0,
+            event: JournalEvent::RunStarted {
+                session_id: "replay".to_string(),
+                config: Box::default(),
+                input: Box::new(crate::swarm::journal::RunInput::Plan(crate::swarm::ExecutionPlan {
+                    original_objective: "x".to_string(),
+                    steps: Vec::new(),
+                })),
+                replay_of: Some("original".to_string()),
+            },
+        };
+        let journals = vec![
+            vec![finished("2026-02-01", ModelChoice::Gemini15Flash, true, 0.001, 1000)],
+            vec![replay_started, finished("2026-02-01", ModelChoice::Gemini15Flash, true, 0.001, 1000)],
+        ];
+
+        let report = aggregate(&journals, &ReportFilter::default(), AlertThresholds::default());
+        assert_eq!((report.runs, report.tasks), (1, 1));
+    }
+}
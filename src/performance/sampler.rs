@@ -0,0 +1,212 @@
+// ============================================================================
+// RESOURCE SAMPLER - Muestreo de CPU y memoria del proceso
+// ============================================================================
+// Un hilo en segundo plano mide periódicamente, con `sysinfo`, el RSS y la
+// CPU del proceso y de sus descendientes (p. ej. los gemini-cli lanzados),
+// el número de procesos hijos y los descriptores de fichero abiertos. La
+// última muestra queda en un `Arc<Mutex<..>>` compartido con el monitor y
+// cada muestra se notifica a los suscriptores (los monitores evalúan ahí sus
+// alertas de recursos). Todos los monitores vivos comparten un único hilo
+// (`ResourceSampler::shared`), que termina al soltar el último.
+// ============================================================================
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, System};
+
+/// Intervalo de muestreo por defecto
+pub const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Uso de recursos del proceso y sus descendientes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// RSS del proceso y sus descendientes, en MB
+    pub memory_usage_mb: u64,
+    /// CPU del proceso y sus descendientes, en % de la capacidad total
+    pub cpu_usage_percent: f64,
+    /// Descriptores de fichero abiertos (None si la plataforma no lo expone)
+    pub open_file_descriptors: Option<u64>,
+    /// Procesos descendientes vivos
+    pub child_processes: u64,
+}
+
+/// Función a la que se avisa con cada muestra
+pub type SampleListener = dyn Fn(&ResourceUsage) + Send + Sync;
+
+type Listeners = Arc<Mutex<Vec<Weak<SampleListener>>>>;
+
+pub struct ResourceSampler {
+    latest: Arc<Mutex<Option<ResourceUsage>>>,
+    listeners: Listeners,
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ResourceSampler {
+    /// Muestreador compartido del proceso: lo lanza el primer monitor (con
+    /// su intervalo) y lo reutilizan los demás mientras siga vivo alguno
+    pub fn shared(interval: Duration) -> std::io::Result<Arc<Self>> {
+        static SHARED: Mutex<Weak<ResourceSampler>> = Mutex::new(Weak::new());
+        let mut shared = SHARED.lock().map_err(|e| std::io::Error::other(e.to_string()))?;
+        if let Some(sampler) = shared.upgrade() {
+            return Ok(sampler);
+        }
+        let sampler = Arc::new(Self::spawn(interval)?);
+        *shared = Arc::downgrade(&sampler);
+        Ok(sampler)
+    }
+
+    /// Lanza un hilo de muestreo propio
+    pub fn spawn(interval: Duration) -> std::io::Result<Self> {
+        let latest = Arc::new(Mutex::new(None));
+        let listeners: Listeners = Arc::new(Mutex::new(Vec::new()));
+        let (stop, stop_rx) = mpsc::channel::<()>();
+
+        let shared = Arc::clone(&latest);
+        let subscribers = Arc::clone(&listeners);
+        let handle = std::thread::Builder::new()
+            .name("enjambre-resource-sampler".to_string())
+            .spawn(move || {
+                let Ok(pid) = sysinfo::get_current_pid() else {
+                    log::warn!("⚠️ No se pudo obtener el PID actual; muestreo de recursos desactivado");
+                    return;
+                };
+                let mut system = System::new();
+                loop {
+                    let usage = sample(&mut system, pid);
+                    super::exporter::resource_usage(&usage);
+                    if let Ok(mut latest) = shared.lock() {
+                        *latest = Some(usage.clone());
+                    }
+                    notify(&subscribers, &usage);
+                    match stop_rx.recv_timeout(interval) {
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        _ => break,
+                    }
+                }
+            })?;
+
+        Ok(Self { latest, listeners, stop: Some(stop), handle: Some(handle) })
+    }
+
+    /// Última muestra tomada, si la hay
+    pub fn latest(&self) -> Option<ResourceUsage> {
+        self.latest.lock().ok().and_then(|latest| latest.clone())
+    }
+
+    /// Avisa a `listener` con la última muestra y con cada una de las
+    /// siguientes, mientras quien lo creó mantenga el `Arc`
+    pub fn subscribe(&self, listener: &Arc<SampleListener>) {
+        if let Ok(mut listeners) = self.listeners.lock() {
+            listeners.push(Arc::downgrade(listener));
+        }
+        if let Some(usage) = self.latest() {
+            listener(&usage);
+        }
+    }
+}
+
+/// Avisa a los suscriptores vivos y olvida los que ya se soltaron
+fn notify(listeners: &Listeners, usage: &ResourceUsage) {
+    let alive: Vec<Arc<SampleListener>> = match listeners.lock() {
+        Ok(mut listeners) => {
+            listeners.retain(|listener| listener.strong_count() > 0);
+            listeners.iter().filter_map(Weak::upgrade).collect()
+        }
+        Err(_) => return,
+    };
+    for listener in alive {
+        listener(usage);
+    }
+}
+
+impl Drop for ResourceSampler {
+    fn drop(&mut self) {
+        // Cerrar el canal despierta al hilo para que termine
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn sample(system: &mut System, pid: Pid) -> ResourceUsage {
+    system.refresh_processes_specifics(ProcessRefreshKind::new().with_cpu().with_memory());
+
+    // Descendientes: procesos cuya cadena de padres llega hasta nosotros
+    let mut family: HashSet<Pid> = HashSet::from([pid]);
+    loop {
+        let before = family.len();
+        for (child, process) in system.processes() {
+            if process.parent().is_some_and(|parent| family.contains(&parent)) {
+                family.insert(*child);
+            }
+        }
+        if family.len() == before {
+            break;
+        }
+    }
+
+    let (memory_bytes, cpu) = family.iter()
+        .filter_map(|member| system.process(*member))
+        .fold((0u64, 0.0f64), |(memory, cpu), process| {
+            (memory + process.memory(), cpu + process.cpu_usage() as f64)
+        });
+    let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1) as f64;
+
+    ResourceUsage {
+        memory_usage_mb: memory_bytes / (1024 * 1024),
+        cpu_usage_percent: cpu / cpus,
+        open_file_descriptors: open_file_descriptors(),
+        child_processes: family.len() as u64 - 1,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_file_descriptors() -> Option<u64> {
+    std::fs::read_dir("/proc/self/fd").ok().map(|entries| entries.count() as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_file_descriptors() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_counts_the_process_and_its_children() {
+        let mut child = std::process::Command::new("sleep").arg("5").spawn().unwrap();
+        let pid = sysinfo::get_current_pid().unwrap();
+        let usage = sample(&mut System::new(), pid);
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert!(usage.memory_usage_mb > 0);
+        assert!(usage.child_processes >= 1);
+        assert!(usage.cpu_usage_percent >= 0.0);
+        #[cfg(target_os = "linux")]
+        assert!(usage.open_file_descriptors.is_some_and(|fds| fds > 0));
+    }
+
+    #[test]
+    fn test_shared_sampler_starts_once_and_notifies_listeners() {
+        let first = ResourceSampler::shared(Duration::from_millis(10)).unwrap();
+        let second = ResourceSampler::shared(Duration::from_millis(10)).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let listener: Arc<SampleListener> = Arc::new(move |usage: &ResourceUsage| {
+            let _ = sender.lock().unwrap().send(usage.memory_usage_mb);
+        });
+        first.subscribe(&listener);
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).unwrap() > 0);
+    }
+}
@@ -0,0 +1,566 @@
+// ============================================================================
+// TRACING - Spans estructurados al estilo OpenTelemetry
+// ============================================================================
+// Cada plan, paso, tarea, llamada a adaptador y llamada a herramienta abre un
+// `Span` con atributos (modelo, tokens, costo, herramienta, riesgo...). El
+// span padre se propaga por el contexto de la tarea de tokio (`Span::scope`),
+// así que los decoradores de adaptadores no necesitan recibirlo. Sin tracer
+// instalado los spans no registran nada. Los spans terminados se exportan
+// como OTLP/JSON a un fichero o a un collector local (`/v1/traces`), y
+// `render_flame` dibuja un desglose tipo flame graph.
+// ============================================================================
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+tokio::task_local! {
+    static CURRENT_SPAN: SpanContext;
+}
+
+static TRACER: OnceLock<Arc<Tracer>> = OnceLock::new();
+
+/// Instala el tracer global; solo la primera llamada tiene efecto
+pub fn install_tracer(tracer: Tracer) -> Arc<Tracer> {
+    Arc::clone(TRACER.get_or_init(|| Arc::new(tracer)))
+}
+
+/// Tracer global, si se instaló
+pub fn tracer() -> Option<Arc<Tracer>> {
+    TRACER.get().cloned()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanContext {
+    pub trace_id: String,
+    pub span_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanKind {
+    Plan,
+    PlanStep,
+    Task,
+    Adapter,
+    Tool,
+}
+
+impl SpanKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SpanKind::Plan => "plan",
+            SpanKind::PlanStep => "plan_step",
+            SpanKind::Task => "task",
+            SpanKind::Adapter => "adapter",
+            SpanKind::Tool => "tool",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        [SpanKind::Plan, SpanKind::PlanStep, SpanKind::Task, SpanKind::Adapter, SpanKind::Tool]
+            .into_iter()
+            .find(|kind| kind.as_str() == value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AttributeValue {
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+    String(String),
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        AttributeValue::String(value.to_string())
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        AttributeValue::String(value)
+    }
+}
+
+impl From<bool> for AttributeValue {
+    fn from(value: bool) -> Self {
+        AttributeValue::Bool(value)
+    }
+}
+
+impl From<u32> for AttributeValue {
+    fn from(value: u32) -> Self {
+        AttributeValue::Int(value as i64)
+    }
+}
+
+impl From<u64> for AttributeValue {
+    fn from(value: u64) -> Self {
+        AttributeValue::Int(value as i64)
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        AttributeValue::Double(value)
+    }
+}
+
+/// Span terminado
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpanRecord {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+    pub name: String,
+    pub kind: SpanKind,
+    pub start_unix_nanos: u64,
+    pub end_unix_nanos: u64,
+    pub attributes: BTreeMap<String, AttributeValue>,
+    /// Mensaje de error si el span terminó mal
+    pub error: Option<String>,
+}
+
+impl SpanRecord {
+    pub fn duration_ms(&self) -> f64 {
+        self.end_unix_nanos.saturating_sub(self.start_unix_nanos) as f64 / 1_000_000.0
+    }
+}
+
+/// Colector de spans terminados
+#[derive(Default)]
+pub struct Tracer {
+    spans: Mutex<Vec<SpanRecord>>,
+}
+
+impl Tracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn finish(&self, span: SpanRecord) {
+        if let Ok(mut spans) = self.spans.lock() {
+            spans.push(span);
+        }
+    }
+
+    /// Spans terminados hasta ahora
+    pub fn spans(&self) -> Vec<SpanRecord> {
+        self.spans.lock().map(|spans| spans.clone()).unwrap_or_default()
+    }
+
+    /// Escribe los spans como OTLP/JSON
+    pub fn export_file(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(&to_otlp(&self.spans())).map_err(std::io::Error::other)?;
+        std::fs::write(path, content)
+    }
+
+    /// Envía los spans a un collector OTLP/HTTP (p. ej. http://localhost:4318)
+    pub async fn export_collector(&self, endpoint: &str) -> Result<(), String> {
+        let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+        let response = reqwest::Client::new()
+            .post(&url)
+            .json(&to_otlp(&self.spans()))
+            .send()
+            .await
+            .map_err(|e| format!("No se pudieron enviar las trazas a {}: {}", url, e))?;
+        if !response.status().is_success() {
+            return Err(format!("El collector {} respondió {}", url, response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// Destinos a los que exportar las trazas al terminar un comando
+pub struct TraceExport {
+    pub tracer: Arc<Tracer>,
+    pub file: Option<PathBuf>,
+    pub endpoint: Option<String>,
+}
+
+impl TraceExport {
+    pub async fn export(&self) {
+        if let Some(path) = &self.file {
+            match self.tracer.export_file(path) {
+                Ok(()) => log::info!("🧵 Trazas guardadas en {}", path.display()),
+                Err(e) => eprintln!("⚠️  No se pudieron guardar las trazas en {}: {}", path.display(), e),
+            }
+        }
+        if let Some(endpoint) = &self.endpoint {
+            if let Err(e) = self.tracer.export_collector(endpoint).await {
+                eprintln!("⚠️  {}", e);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// SPANS ACTIVOS
+// ============================================================================
+
+/// Span abierto; se registra al soltarlo
+pub struct Span {
+    inner: Option<(Arc<Tracer>, SpanRecord)>,
+}
+
+impl Span {
+    /// Abre un span hijo del span actual (o raíz si no lo hay)
+    pub fn start(name: &str, kind: SpanKind) -> Self {
+        let parent = CURRENT_SPAN.try_with(|current| current.clone()).ok();
+        Self::start_with_parent(name, kind, parent.as_ref())
+    }
+
+    /// Abre un span hijo de un contexto explícito
+    pub fn start_with_parent(name: &str, kind: SpanKind, parent: Option<&SpanContext>) -> Self {
+        let Some(tracer) = tracer() else {
+            return Self { inner: None };
+        };
+        let trace_id = parent
+            .map(|parent| parent.trace_id.clone())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+        let record = SpanRecord {
+            trace_id,
+            span_id: uuid::Uuid::new_v4().simple().to_string()[..16].to_string(),
+            parent_span_id: parent.map(|parent| parent.span_id.clone()),
+            name: name.to_string(),
+            kind,
+            start_unix_nanos: now_unix_nanos(),
+            end_unix_nanos: 0,
+            attributes: BTreeMap::new(),
+            error: None,
+        };
+        Self { inner: Some((tracer, record)) }
+    }
+
+    pub fn context(&self) -> Option<SpanContext> {
+        self.inner.as_ref().map(|(_, record)| SpanContext {
+            trace_id: record.trace_id.clone(),
+            span_id: record.span_id.clone(),
+        })
+    }
+
+    pub fn set_attribute(&mut self, key: &str, value: impl Into<AttributeValue>) {
+        if let Some((_, record)) = self.inner.as_mut() {
+            record.attributes.insert(key.to_string(), value.into());
+        }
+    }
+
+    pub fn set_error(&mut self, message: impl Into<String>) {
+        if let Some((_, record)) = self.inner.as_mut() {
+            record.error = Some(message.into());
+        }
+    }
+
+    /// Ejecuta un future con este span como span actual
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        match self.context() {
+            Some(context) => CURRENT_SPAN.scope(context, future).await,
+            None => future.await,
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some((tracer, mut record)) = self.inner.take() {
+            record.end_unix_nanos = now_unix_nanos();
+            tracer.finish(record);
+        }
+    }
+}
+
+fn now_unix_nanos() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
+
+// ============================================================================
+// OTLP/JSON
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtlpTraces {
+    pub resource_spans: Vec<OtlpResourceSpans>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtlpResourceSpans {
+    #[serde(default)]
+    pub resource: OtlpResource,
+    pub scope_spans: Vec<OtlpScopeSpans>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OtlpResource {
+    #[serde(default)]
+    pub attributes: Vec<OtlpKeyValue>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtlpScopeSpans {
+    #[serde(default)]
+    pub scope: serde_json::Value,
+    pub spans: Vec<OtlpSpan>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtlpSpan {
+    pub trace_id: String,
+    pub span_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub parent_span_id: String,
+    pub name: String,
+    /// 1 = INTERNAL, 3 = CLIENT
+    #[serde(default)]
+    pub kind: u8,
+    pub start_time_unix_nano: String,
+    pub end_time_unix_nano: String,
+    #[serde(default)]
+    pub attributes: Vec<OtlpKeyValue>,
+    #[serde(default)]
+    pub status: OtlpStatus,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OtlpStatus {
+    /// 0 = UNSET, 1 = OK, 2 = ERROR
+    #[serde(default)]
+    pub code: u8,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OtlpKeyValue {
+    pub key: String,
+    pub value: OtlpAnyValue,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OtlpAnyValue {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub string_value: Option<String>,
+    /// En OTLP/JSON los enteros de 64 bits van como texto
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub int_value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub double_value: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bool_value: Option<bool>,
+}
+
+const KIND_ATTRIBUTE: &str = "enjambre.span_kind";
+
+fn to_any_value(value: &AttributeValue) -> OtlpAnyValue {
+    match value {
+        AttributeValue::Bool(b) => OtlpAnyValue { bool_value: Some(*b), ..Default::default() },
+        AttributeValue::Int(i) => OtlpAnyValue { int_value: Some(i.to_string()), ..Default::default() },
+        AttributeValue::Double(d) => OtlpAnyValue { double_value: Some(*d), ..Default::default() },
+        AttributeValue::String(s) => OtlpAnyValue { string_value: Some(s.clone()), ..Default::default() },
+    }
+}
+
+fn from_any_value(value: &OtlpAnyValue) -> Option<AttributeValue> {
+    value.string_value.clone().map(AttributeValue::String)
+        .or_else(|| value.int_value.as_ref().and_then(|i| i.parse().ok()).map(AttributeValue::Int))
+        .or_else(|| value.double_value.map(AttributeValue::Double))
+        .or_else(|| value.bool_value.map(AttributeValue::Bool))
+}
+
+pub fn to_otlp(spans: &[SpanRecord]) -> OtlpTraces {
+    let spans = spans.iter().map(|span| {
+        let mut attributes: Vec<OtlpKeyValue> = span.attributes.iter()
+            .map(|(key, value)| OtlpKeyValue { key: key.clone(), value: to_any_value(value) })
+            .collect();
+        attributes.push(OtlpKeyValue {
+            key: KIND_ATTRIBUTE.to_string(),
+            value: to_any_value(&span.kind.as_str().into()),
+        });
+        OtlpSpan {
+            trace_id: span.trace_id.clone(),
+            span_id: span.span_id.clone(),
+            parent_span_id: span.parent_span_id.clone().unwrap_or_default(),
+            name: span.name.clone(),
+            kind: if span.kind == SpanKind::Adapter { 3 } else { 1 },
+            start_time_unix_nano: span.start_unix_nanos.to_string(),
+            end_time_unix_nano: span.end_unix_nanos.to_string(),
+            attributes,
+            status: match &span.error {
+                Some(message) => OtlpStatus { code: 2, message: message.clone() },
+                None => OtlpStatus { code: 1, message: String::new() },
+            },
+        }
+    }).collect();
+
+    OtlpTraces {
+        resource_spans: vec![OtlpResourceSpans {
+            resource: OtlpResource {
+                attributes: vec![OtlpKeyValue {
+                    key: "service.name".to_string(),
+                    value: to_any_value(&"enjambre".into()),
+                }],
+            },
+            scope_spans: vec![OtlpScopeSpans {
+                scope: serde_json::json!({ "name": "enjambre", "version": env!("CARGO_PKG_VERSION") }),
+                spans,
+            }],
+        }],
+    }
+}
+
+/// Lee spans de un fichero OTLP/JSON
+pub fn load_otlp(path: &Path) -> Result<Vec<SpanRecord>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("No se pudo leer {}: {}", path.display(), e))?;
+    let traces: OtlpTraces = serde_json::from_str(&content)
+        .map_err(|e| format!("Trazas OTLP/JSON inválidas en {}: {}", path.display(), e))?;
+
+    let mut records = Vec::new();
+    for span in traces.resource_spans.iter().flat_map(|r| &r.scope_spans).flat_map(|s| &s.spans) {
+        let mut attributes: BTreeMap<String, AttributeValue> = span.attributes.iter()
+            .filter_map(|kv| from_any_value(&kv.value).map(|value| (kv.key.clone(), value)))
+            .collect();
+        let kind = match attributes.remove(KIND_ATTRIBUTE) {
+            Some(AttributeValue::String(kind)) => SpanKind::parse(&kind),
+            _ => None,
+        };
+        records.push(SpanRecord {
+            trace_id: span.trace_id.clone(),
+            span_id: span.span_id.clone(),
+            parent_span_id: Some(span.parent_span_id.clone()).filter(|id| !id.is_empty()),
+            name: span.name.clone(),
+            kind: kind.unwrap_or(SpanKind::Task),
+            start_unix_nanos: span.start_time_unix_nano.parse().unwrap_or(0),
+            end_unix_nanos: span.end_time_unix_nano.parse().unwrap_or(0),
+            attributes,
+            error: (span.status.code == 2).then(|| span.status.message.clone()),
+        });
+    }
+    Ok(records)
+}
+
+// ============================================================================
+// FLAME
+// ============================================================================
+
+/// Desglose tipo flame graph: árbol de spans con duración, porcentaje del
+/// span raíz y tiempo propio (sin hijos)
+pub fn render_flame(spans: &[SpanRecord]) -> String {
+    const BAR_WIDTH: f64 = 30.0;
+
+    let mut children: BTreeMap<&str, Vec<&SpanRecord>> = BTreeMap::new();
+    let mut roots = Vec::new();
+    for span in spans {
+        match span.parent_span_id.as_deref() {
+            Some(parent) if spans.iter().any(|s| s.span_id == parent) => {
+                children.entry(parent).or_default().push(span);
+            }
+            _ => roots.push(span),
+        }
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by_key(|span| span.start_unix_nanos);
+    }
+    roots.sort_by_key(|span| span.start_unix_nanos);
+
+    fn label(span: &SpanRecord) -> String {
+        let details: Vec<String> = ["model", "adapter", "tool", "tokens.total", "cost_usd", "risk_level"]
+            .iter()
+            .filter_map(|key| span.attributes.get(*key).map(|value| match value {
+                AttributeValue::Double(d) => format!("{}={:.4}", key, d),
+                AttributeValue::String(s) => format!("{}={}", key, s),
+                AttributeValue::Int(i) => format!("{}={}", key, i),
+                AttributeValue::Bool(b) => format!("{}={}", key, b),
+            }))
+            .collect();
+        let status = if span.error.is_some() { " ❌" } else { "" };
+        if details.is_empty() {
+            format!("{}{}", span.name, status)
+        } else {
+            format!("{} [{}]{}", span.name, details.join(", "), status)
+        }
+    }
+
+    fn walk(
+        span: &SpanRecord,
+        depth: usize,
+        root_ms: f64,
+        children: &BTreeMap<&str, Vec<&SpanRecord>>,
+        out: &mut String,
+    ) {
+        let duration = span.duration_ms();
+        let kids = children.get(span.span_id.as_str()).map(Vec::as_slice).unwrap_or(&[]);
+        let self_ms = (duration - kids.iter().map(|kid| kid.duration_ms()).sum::<f64>()).max(0.0);
+        let share = if root_ms > 0.0 { duration / root_ms } else { 0.0 };
+        let bar = "█".repeat(((share * BAR_WIDTH).round() as usize).max(1));
+
+        out.push_str(&format!(
+            "{:<32} {:>10.1}ms {:>5.1}% (propio {:>8.1}ms) {}{}\n",
+            bar, duration, share * 100.0, self_ms, "  ".repeat(depth), label(span),
+        ));
+        for kid in kids {
+            walk(kid, depth + 1, root_ms, children, out);
+        }
+    }
+
+    let mut out = String::new();
+    for root in roots {
+        walk(root, 0, root.duration_ms(), &children, &mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_spans_nest_and_round_trip_through_otlp() {
+        let tracer = install_tracer(Tracer::new());
+
+        let mut task = Span::start("task test_spans_nest", SpanKind::Task);
+        task.set_attribute("model", "Gemini15Flash");
+        let trace_id = task.context().unwrap().trace_id;
+        task.scope(async {
+            let mut adapter = Span::start("adapter gemini", SpanKind::Adapter);
+            adapter.set_attribute("tokens.total", 42u32);
+            adapter.scope(async {
+                let mut tool = Span::start("tool file_read", SpanKind::Tool);
+                tool.set_error("no existe");
+            }).await;
+        }).await;
+        drop(task);
+
+        let spans: Vec<SpanRecord> = tracer.spans().into_iter().filter(|s| s.trace_id == trace_id).collect();
+        assert_eq!(spans.len(), 3);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.json");
+        std::fs::write(&path, serde_json::to_string(&to_otlp(&spans)).unwrap()).unwrap();
+        let loaded = load_otlp(&path).unwrap();
+
+        let find = |kind: SpanKind| loaded.iter().find(|s| s.kind == kind).unwrap();
+        let (task, adapter, tool) = (find(SpanKind::Task), find(SpanKind::Adapter), find(SpanKind::Tool));
+        assert_eq!(adapter.parent_span_id.as_deref(), Some(task.span_id.as_str()));
+        assert_eq!(tool.parent_span_id.as_deref(), Some(adapter.span_id.as_str()));
+        assert_eq!(adapter.attributes.get("tokens.total"), Some(&AttributeValue::Int(42)));
+        assert_eq!(tool.error.as_deref(), Some("no existe"));
+
+        let flame = render_flame(&loaded);
+        assert!(flame.lines().next().unwrap().contains("task test_spans_nest [model=Gemini15Flash]"));
+        assert_eq!(flame.lines().count(), 3);
+    }
+}
@@ -0,0 +1,107 @@
+// ============================================================================
+// BLACKBOARD - Pizarra compartida entre agentes, indexada por paso del plan
+// ============================================================================
+// Las escrituras usan versionado optimista: quien escribe indica la versión
+// que leyó y la escritura falla si otro agente la modificó entretanto.
+// ============================================================================
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlackboardEntry {
+    pub step_id: u32,
+    pub value: serde_json::Value,
+    /// Versión actual; 0 significa "no existe todavía"
+    pub version: u64,
+    pub written_by: String,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlackboardError {
+    #[error("Conflicto de versión en el paso {step_id}: esperada {expected}, actual {actual}")]
+    VersionConflict { step_id: u32, expected: u64, actual: u64 },
+
+    #[error("Blackboard inaccesible: {0}")]
+    Poisoned(String),
+}
+
+#[derive(Default)]
+pub struct Blackboard {
+    entries: RwLock<BTreeMap<u32, BlackboardEntry>>,
+}
+
+impl Blackboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lee la entrada de un paso
+    pub fn read(&self, step_id: u32) -> Option<BlackboardEntry> {
+        self.entries.read().ok().and_then(|entries| entries.get(&step_id).cloned())
+    }
+
+    /// Versión actual de un paso (0 si no hay entrada)
+    pub fn version(&self, step_id: u32) -> u64 {
+        self.read(step_id).map(|entry| entry.version).unwrap_or(0)
+    }
+
+    /// Escribe un valor si la versión actual coincide con `expected_version`.
+    /// Devuelve la nueva versión.
+    pub fn write(
+        &self,
+        step_id: u32,
+        value: serde_json::Value,
+        author: &str,
+        expected_version: u64,
+    ) -> Result<u64, BlackboardError> {
+        let mut entries = self.entries
+            .write()
+            .map_err(|e| BlackboardError::Poisoned(e.to_string()))?;
+
+        let actual = entries.get(&step_id).map(|entry| entry.version).unwrap_or(0);
+        if actual != expected_version {
+            return Err(BlackboardError::VersionConflict { step_id, expected: expected_version, actual });
+        }
+
+        let version = actual + 1;
+        entries.insert(step_id, BlackboardEntry {
+            step_id,
+            value,
+            version,
+            written_by: author.to_string(),
+            updated_at: chrono::Utc::now(),
+        });
+        Ok(version)
+    }
+
+    /// Todas las entradas, ordenadas por paso
+    pub fn snapshot(&self) -> Vec<BlackboardEntry> {
+        self.entries
+            .read()
+            .map(|entries| entries.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_write_is_rejected() {
+        let blackboard = Blackboard::new();
+        let read_version = blackboard.version(1);
+        assert_eq!(blackboard.write(1, serde_json::json!("a"), "worker-1", read_version).unwrap(), 1);
+
+        // Un segundo agente que leyó la versión 0 no puede pisar la escritura
+        let conflict = blackboard.write(1, serde_json::json!("b"), "worker-2", read_version);
+        assert!(matches!(conflict, Err(BlackboardError::VersionConflict { step_id: 1, expected: 0, actual: 1 })));
+        assert_eq!(blackboard.read(1).unwrap().written_by, "worker-1");
+
+        // Releyendo la versión actual sí puede escribir
+        assert_eq!(blackboard.write(1, serde_json::json!("b"), "worker-2", blackboard.version(1)).unwrap(), 2);
+    }
+}
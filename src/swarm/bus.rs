@@ -0,0 +1,336 @@
+// ============================================================================
+// MESSAGE BUS - Comunicación en proceso entre agentes del enjambre
+// ============================================================================
+// Cada agente tiene un buzón propio y puede suscribirse a tópicos de
+// difusión. Todos los mensajes quedan registrados en un log ordenado que el
+// orquestador copia al journal de la ejecución, para poder reproducirla y
+// comparar los mensajes. El trait `MessageBus` permite
+// sustituir la implementación en memoria por otra (p. ej. un socket Unix
+// local para swarms multi-proceso) sin tocar el orquestador.
+// ============================================================================
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Tópico al que el orquestador publica el progreso de los planes
+pub const PLAN_TOPIC: &str = "plan";
+
+/// Mensajes que guarda como máximo cada buzón; al llenarse se descartan los
+/// más antiguos (siguen en el log)
+pub const DEFAULT_MAILBOX_CAPACITY: usize = 1024;
+
+// ============================================================================
+// MENSAJES
+// ============================================================================
+
+/// Contenido tipado de un mensaje entre agentes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MessagePayload {
+    /// Texto libre
+    Text(String),
+    /// Asignación de un paso del plan a un agente
+    TaskAssignment { step_id: u32, description: String },
+    /// Resultado de un paso del plan
+    StepResult { step_id: u32, success: bool, output: String },
+    /// Aviso de que una entrada del blackboard cambió
+    BlackboardUpdate { step_id: u32, version: u64 },
+    /// Datos estructurados arbitrarios
+    Data(serde_json::Value),
+}
+
+/// Destino de un mensaje: el buzón de un agente o un tópico de difusión
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Recipient {
+    Agent(String),
+    Topic(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentMessage {
+    pub id: String,
+    pub sender: String,
+    pub recipient: Recipient,
+    pub payload: MessagePayload,
+    pub correlation_id: Option<String>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl AgentMessage {
+    /// Mensaje directo al buzón de un agente
+    pub fn direct(sender: &str, recipient: &str, payload: MessagePayload) -> Self {
+        Self::new(sender, Recipient::Agent(recipient.to_string()), payload)
+    }
+
+    /// Mensaje de difusión a todos los suscriptores de un tópico
+    pub fn broadcast(sender: &str, topic: &str, payload: MessagePayload) -> Self {
+        Self::new(sender, Recipient::Topic(topic.to_string()), payload)
+    }
+
+    pub fn with_correlation_id(mut self, correlation_id: &str) -> Self {
+        self.correlation_id = Some(correlation_id.to_string());
+        self
+    }
+
+    fn new(sender: &str, recipient: Recipient, payload: MessagePayload) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            sender: sender.to_string(),
+            recipient,
+            payload,
+            correlation_id: None,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Entrada del log de mensajes, con número de secuencia global
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedMessage {
+    pub sequence: u64,
+    pub message: AgentMessage,
+    pub delivered_to: Vec<String>,
+}
+
+// ============================================================================
+// ERRORES
+// ============================================================================
+
+#[derive(Debug, thiserror::Error)]
+pub enum BusError {
+    #[error("Agente no registrado en el bus: {0}")]
+    UnknownAgent(String),
+
+    #[error("Agente ya registrado en el bus: {0}")]
+    AgentAlreadyRegistered(String),
+
+    #[error("Error de transporte del bus: {0}")]
+    Transport(String),
+}
+
+// ============================================================================
+// TRAIT: MessageBus
+// ============================================================================
+
+/// Contrato de transporte de mensajes entre agentes
+#[async_trait]
+pub trait MessageBus: Send + Sync {
+    /// Crea el buzón de un agente
+    async fn register_agent(&self, agent_id: &str) -> Result<(), BusError>;
+
+    /// Suscribe un agente registrado a un tópico de difusión
+    async fn subscribe(&self, agent_id: &str, topic: &str) -> Result<(), BusError>;
+
+    /// Entrega un mensaje (directo o de difusión) y lo registra en el log
+    async fn send(&self, message: AgentMessage) -> Result<(), BusError>;
+
+    /// Extrae el siguiente mensaje del buzón, si lo hay
+    async fn receive(&self, agent_id: &str) -> Result<Option<AgentMessage>, BusError>;
+
+    /// Vacía el buzón de un agente
+    async fn drain(&self, agent_id: &str) -> Result<Vec<AgentMessage>, BusError>;
+
+    /// Da de baja a un agente: elimina su buzón y sus suscripciones y
+    /// devuelve los mensajes que no llegó a leer
+    async fn unregister_agent(&self, agent_id: &str) -> Result<Vec<AgentMessage>, BusError>;
+
+    /// Copia del log completo de mensajes, en orden de envío
+    fn message_log(&self) -> Vec<LoggedMessage>;
+}
+
+// ============================================================================
+// IMPLEMENTACIÓN EN MEMORIA
+// ============================================================================
+
+#[derive(Default)]
+struct BusState {
+    mailboxes: HashMap<String, VecDeque<AgentMessage>>,
+    subscriptions: HashMap<String, HashSet<String>>,
+    log: Vec<LoggedMessage>,
+}
+
+/// Bus en proceso: buzones FIFO acotados por agente y tópicos con copia a
+/// cada suscriptor
+pub struct InMemoryBus {
+    state: Mutex<BusState>,
+    mailbox_capacity: usize,
+}
+
+impl Default for InMemoryBus {
+    fn default() -> Self {
+        Self { state: Mutex::default(), mailbox_capacity: DEFAULT_MAILBOX_CAPACITY }
+    }
+}
+
+impl InMemoryBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mailbox_capacity(mut self, capacity: usize) -> Self {
+        self.mailbox_capacity = capacity.max(1);
+        self
+    }
+
+    /// Reconstruye un bus reenviando los mensajes de un log previo.
+    /// Los agentes destinatarios se registran automáticamente.
+    pub async fn replay(log: &[LoggedMessage]) -> Result<Self, BusError> {
+        let bus = Self::new();
+        for entry in log {
+            for agent in &entry.delivered_to {
+                let _ = bus.register_agent(agent).await;
+                if let Recipient::Topic(topic) = &entry.message.recipient {
+                    bus.subscribe(agent, topic).await?;
+                }
+            }
+            bus.send(entry.message.clone()).await?;
+        }
+        Ok(bus)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, BusState>, BusError> {
+        self.state.lock().map_err(|_| BusError::Transport("Estado del bus envenenado".to_string()))
+    }
+}
+
+#[async_trait]
+impl MessageBus for InMemoryBus {
+    async fn register_agent(&self, agent_id: &str) -> Result<(), BusError> {
+        let mut state = self.lock()?;
+        if state.mailboxes.contains_key(agent_id) {
+            return Err(BusError::AgentAlreadyRegistered(agent_id.to_string()));
+        }
+        state.mailboxes.insert(agent_id.to_string(), VecDeque::new());
+        Ok(())
+    }
+
+    async fn subscribe(&self, agent_id: &str, topic: &str) -> Result<(), BusError> {
+        let mut state = self.lock()?;
+        if !state.mailboxes.contains_key(agent_id) {
+            return Err(BusError::UnknownAgent(agent_id.to_string()));
+        }
+        state.subscriptions
+            .entry(topic.to_string())
+            .or_default()
+            .insert(agent_id.to_string());
+        Ok(())
+    }
+
+    async fn send(&self, message: AgentMessage) -> Result<(), BusError> {
+        let mut state = self.lock()?;
+
+        let mut delivered_to: Vec<String> = match &message.recipient {
+            Recipient::Agent(agent_id) => {
+                if !state.mailboxes.contains_key(agent_id) {
+                    return Err(BusError::UnknownAgent(agent_id.clone()));
+                }
+                vec![agent_id.clone()]
+            }
+            Recipient::Topic(topic) => state.subscriptions
+                .get(topic)
+                .map(|subscribers| subscribers.iter().cloned().collect())
+                .unwrap_or_default(),
+        };
+        delivered_to.sort();
+
+        for agent_id in &delivered_to {
+            if let Some(mailbox) = state.mailboxes.get_mut(agent_id) {
+                if mailbox.len() >= self.mailbox_capacity {
+                    mailbox.pop_front();
+                    log::warn!("⚠️ Buzón de {} lleno, se descarta su mensaje más antiguo", agent_id);
+                }
+                mailbox.push_back(message.clone());
+            }
+        }
+
+        let sequence = state.log.len() as u64;
+        log::debug!("📨 Bus #{} {} -> {:?}", sequence, message.sender, message.recipient);
+        state.log.push(LoggedMessage { sequence, message, delivered_to });
+        Ok(())
+    }
+
+    async fn receive(&self, agent_id: &str) -> Result<Option<AgentMessage>, BusError> {
+        let mut state = self.lock()?;
+        state.mailboxes
+            .get_mut(agent_id)
+            .map(|mailbox| mailbox.pop_front())
+            .ok_or_else(|| BusError::UnknownAgent(agent_id.to_string()))
+    }
+
+    async fn drain(&self, agent_id: &str) -> Result<Vec<AgentMessage>, BusError> {
+        let mut state = self.lock()?;
+        state.mailboxes
+            .get_mut(agent_id)
+            .map(|mailbox| mailbox.drain(..).collect())
+            .ok_or_else(|| BusError::UnknownAgent(agent_id.to_string()))
+    }
+
+    async fn unregister_agent(&self, agent_id: &str) -> Result<Vec<AgentMessage>, BusError> {
+        let mut state = self.lock()?;
+        let mailbox = state.mailboxes
+            .remove(agent_id)
+            .ok_or_else(|| BusError::UnknownAgent(agent_id.to_string()))?;
+        for subscribers in state.subscriptions.values_mut() {
+            subscribers.remove(agent_id);
+        }
+        Ok(mailbox.into())
+    }
+
+    fn message_log(&self) -> Vec<LoggedMessage> {
+        self.state.lock().map(|state| state.log.clone()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_topic_delivery_and_replay() {
+        let bus = InMemoryBus::new();
+        bus.register_agent("a").await.unwrap();
+        bus.register_agent("b").await.unwrap();
+        bus.subscribe("b", PLAN_TOPIC).await.unwrap();
+
+        bus.send(AgentMessage::direct("a", "b", MessagePayload::Text("hola".to_string()))).await.unwrap();
+        bus.send(AgentMessage::broadcast("a", PLAN_TOPIC, MessagePayload::Text("todos".to_string()))).await.unwrap();
+
+        assert_eq!(bus.drain("b").await.unwrap().len(), 2);
+        assert!(bus.receive("a").await.unwrap().is_none());
+
+        let replayed = InMemoryBus::replay(&bus.message_log()).await.unwrap();
+        let log = replayed.message_log();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[1].delivered_to, vec!["b".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_mailboxes_are_bounded_and_released() {
+        let bus = InMemoryBus::new().with_mailbox_capacity(2);
+        bus.register_agent("a").await.unwrap();
+        bus.subscribe("a", PLAN_TOPIC).await.unwrap();
+        for i in 0..3 {
+            bus.send(AgentMessage::broadcast("q", PLAN_TOPIC, MessagePayload::Text(i.to_string()))).await.unwrap();
+        }
+
+        // Se conservan los más recientes; el log los tiene todos
+        let pending = bus.unregister_agent("a").await.unwrap();
+        assert_eq!(pending.iter().map(|m| m.payload.clone()).collect::<Vec<_>>(),
+            vec![MessagePayload::Text("1".to_string()), MessagePayload::Text("2".to_string())]);
+        assert_eq!(bus.message_log().len(), 3);
+
+        // Tras la baja ya no recibe difusiones y puede volver a registrarse
+        bus.send(AgentMessage::broadcast("q", PLAN_TOPIC, MessagePayload::Text("3".to_string()))).await.unwrap();
+        assert!(bus.message_log()[3].delivered_to.is_empty());
+        bus.register_agent("a").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unknown_agent_is_rejected() {
+        let bus = InMemoryBus::new();
+        let result = bus.send(AgentMessage::direct("a", "nadie", MessagePayload::Text(String::new()))).await;
+        assert!(matches!(result, Err(BusError::UnknownAgent(_))));
+    }
+}
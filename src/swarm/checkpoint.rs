@@ -0,0 +1,140 @@
+// ============================================================================
+// CHECKPOINTS - Estado persistente de un plan para poder reanudarlo
+// ============================================================================
+// Tras cada `TaskStep` completado, el orquestador guarda en el directorio de
+// la ejecución (`~/.enjambre/runs/<session_id>/checkpoint.json`) el plan, las
+// salidas de los pasos, los resultados de herramientas y el presupuesto
+// gastado, junto con las etiquetas de la ejecución.
+// `enjambre swarm resume <session_id>` continúa desde el último paso
+// correcto, opcionalmente con un plan editado.
+// ============================================================================
+
+use super::{ExecutionPlan, StepExecutionResult, TaskStep};
+use super::journal::RunJournal;
+use crate::{FlowError, tools::ToolResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Nombre del fichero de checkpoint dentro del directorio de la ejecución
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Llamada a herramienta hecha durante un paso
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    pub tool: String,
+    pub params: serde_json::Value,
+    pub result: Result<ToolResult, String>,
+}
+
+/// Paso completado con éxito
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointStep {
+    /// El paso tal como se ejecutó; si el plan editado cambia cualquier campo
+    /// (descripción, detalles, herramientas o sus parámetros), se repite
+    pub step: TaskStep,
+    pub execution: StepExecutionResult,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCallRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanCheckpoint {
+    pub session_id: String,
+    pub plan: ExecutionPlan,
+    pub completed: Vec<CheckpointStep>,
+    /// Costo acumulado del plan en USD: pasos completados, pasos fallidos y
+    /// pasos que un plan editado obligó a repetir
+    pub spent_usd: f64,
+    /// Etiquetas de la ejecución (`[labels]` y `--label`), para que la
+    /// reanudación impute el gasto a los mismos equipos
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PlanCheckpoint {
+    pub fn new(session_id: &str, plan: &ExecutionPlan) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            plan: plan.clone(),
+            completed: Vec::new(),
+            spent_usd: 0.0,
+            labels: BTreeMap::new(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    /// Ruta del checkpoint de una sesión
+    pub fn path_for(session_id: &str) -> Option<PathBuf> {
+        RunJournal::runs_dir().map(|runs| runs.join(session_id).join(CHECKPOINT_FILE))
+    }
+
+    /// Carga el checkpoint de una sesión por id
+    pub fn load(session_id: &str) -> Result<Self, FlowError> {
+        let path = Self::path_for(session_id)
+            .ok_or_else(|| FlowError::InvalidPrompt("No se encontró el directorio home".to_string()))?;
+        Self::load_from(&path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, FlowError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            FlowError::InvalidPrompt(format!("No se pudo leer el checkpoint {}: {}", path.display(), e))
+        })?;
+        serde_json::from_str(&content)
+            .map_err(|e| FlowError::InvalidResponse(format!("Checkpoint inválido: {}", e)))
+    }
+
+    /// Guarda el checkpoint de forma atómica (fichero temporal + rename)
+    pub fn save_in(&self, dir: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let content = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        let tmp = dir.join(format!("{}.tmp", CHECKPOINT_FILE));
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(tmp, dir.join(CHECKPOINT_FILE))
+    }
+
+    /// Registra un paso completado y actualiza el presupuesto gastado
+    pub fn complete_step(&mut self, step: &TaskStep, execution: StepExecutionResult, tool_calls: Vec<ToolCallRecord>) {
+        self.spent_usd += execution.result.cost_actual;
+        self.completed.retain(|done| done.execution.step_id != execution.step_id);
+        self.completed.push(CheckpointStep { step: step.clone(), execution, tool_calls });
+        self.updated_at = chrono::Utc::now();
+    }
+
+    /// Suma lo facturado por un paso que falló; se repetirá al reanudar
+    pub fn record_failed_step(&mut self, cost_usd: f64) {
+        self.spent_usd += cost_usd;
+        self.updated_at = chrono::Utc::now();
+    }
+
+    /// Adapta el checkpoint a un plan editado. Se conservan los pasos
+    /// completados que siguen en el plan sin cambios y cuyas dependencias
+    /// también se conservan; el resto se volverá a ejecutar.
+    pub fn with_plan(mut self, plan: ExecutionPlan) -> Result<Self, FlowError> {
+        let order = plan.execution_order()?;
+
+        let mut kept: Vec<CheckpointStep> = Vec::new();
+        for step in order {
+            let Some(done) = self.completed.iter().find(|done| done.execution.step_id == step.id) else {
+                continue;
+            };
+            let dependencies_kept = step.depends_on.iter()
+                .all(|dep| kept.iter().any(|k| k.execution.step_id == *dep));
+            if done.step == *step && dependencies_kept {
+                kept.push(done.clone());
+            } else {
+                log::info!("✏️ El paso {} cambió en el plan editado, se volverá a ejecutar", step.id);
+            }
+        }
+
+        self.completed = kept;
+        self.plan = plan;
+        Ok(self)
+    }
+
+    /// Paso completado con este id, si lo hay
+    pub fn completed_step(&self, step_id: u32) -> Option<&CheckpointStep> {
+        self.completed.iter().find(|step| step.execution.step_id == step_id)
+    }
+}
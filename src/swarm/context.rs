@@ -0,0 +1,471 @@
+// ============================================================================
+// CONTEXT BUDGET - Presupuesto de contexto y compresión de prompts
+// ============================================================================
+// El contexto de una tarea (ficheros, turnos de conversación, salidas de
+// pasos previos, memorias) viaja en `Task.context` con una prioridad. Antes
+// de enviar la tarea, el orquestador calcula cuántos tokens de prompt caben
+// en `max_cost_usd` con el modelo elegido (o usa `max_context_tokens`) y, si
+// el prompt no cabe, aplica por orden hasta que quepa:
+//   1. quitar contenidos repetidos
+//   2. colapsar espacios en blanco del código
+//   3. resumir los turnos antiguos con un modelo barato (`Summarizer`)
+//   4. descartar el contexto de menor prioridad, empezando por el más antiguo
+// El contexto `Required` nunca se descarta. `ContextReport` detalla qué se
+// hizo con cada elemento.
+// ============================================================================
+
+use crate::cost_optimizer::{estimate_tokens, ModelChoice, MIN_EXPECTED_OUTPUT_TOKENS};
+use crate::{CodeGenerationFlow, FlowError};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Turnos más recientes que nunca se resumen
+pub const RECENT_TURNS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ContextPriority {
+    Low,
+    Normal,
+    High,
+    /// No se descarta aunque no quepa en el presupuesto
+    Required,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ContextKind {
+    File { path: String },
+    Turn { role: String },
+    StepOutput { step_id: u32 },
+    Memory,
+    Note,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextItem {
+    pub kind: ContextKind,
+    pub content: String,
+    pub priority: ContextPriority,
+}
+
+impl ContextItem {
+    pub fn file(path: &str, content: &str) -> Self {
+        Self::new(ContextKind::File { path: path.to_string() }, content, ContextPriority::Normal)
+    }
+
+    pub fn turn(role: &str, content: &str) -> Self {
+        Self::new(ContextKind::Turn { role: role.to_string() }, content, ContextPriority::Normal)
+    }
+
+    pub fn step_output(step_id: u32, content: &str) -> Self {
+        Self::new(ContextKind::StepOutput { step_id }, content, ContextPriority::High)
+    }
+
+    pub fn memory(content: &str) -> Self {
+        Self::new(ContextKind::Memory, content, ContextPriority::Low)
+    }
+
+    pub fn note(content: &str) -> Self {
+        Self::new(ContextKind::Note, content, ContextPriority::Normal)
+    }
+
+    fn new(kind: ContextKind, content: &str, priority: ContextPriority) -> Self {
+        Self { kind, content: content.to_string(), priority }
+    }
+
+    pub fn with_priority(mut self, priority: ContextPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Nombre legible del elemento para el informe
+    pub fn label(&self) -> String {
+        match &self.kind {
+            ContextKind::File { path } => format!("fichero {}", path),
+            ContextKind::Turn { role } => format!("turno de {}", role),
+            ContextKind::StepOutput { step_id } => format!("resultado del paso {}", step_id),
+            ContextKind::Memory => "memoria".to_string(),
+            ContextKind::Note => "nota".to_string(),
+        }
+    }
+
+    fn is_code(&self) -> bool {
+        matches!(self.kind, ContextKind::File { .. } | ContextKind::StepOutput { .. })
+    }
+
+    fn render(&self) -> String {
+        format!("--- {} ---\n{}\n", self.label(), self.content)
+    }
+
+    pub fn tokens(&self) -> u32 {
+        estimate_tokens(&self.render())
+    }
+}
+
+/// Bloque de contexto que se añade al prompt (vacío si no hay contexto)
+pub fn render(items: &[ContextItem]) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+    let mut block = String::from("\n\nContexto:\n");
+    for item in items {
+        block.push_str(&item.render());
+    }
+    block
+}
+
+/// Caracteres de contexto, para las señales de complejidad
+pub fn context_chars(items: &[ContextItem]) -> usize {
+    items.iter().map(|item| item.content.len()).sum()
+}
+
+/// Tokens de prompt que caben en `max_cost_usd` con `model`, suponiendo
+/// (como el control de presupuesto) una salida del tamaño del prompt
+pub fn token_budget(model: &ModelChoice, max_cost_usd: f64) -> u32 {
+    let (input, output) = model.price_per_million();
+    let micro_usd = max_cost_usd * 1_000_000.0;
+    let with_min_output = (micro_usd - output * MIN_EXPECTED_OUTPUT_TOKENS as f64) / input;
+    if with_min_output <= MIN_EXPECTED_OUTPUT_TOKENS as f64 {
+        return with_min_output.max(0.0) as u32;
+    }
+    (micro_usd / (input + output)) as u32
+}
+
+// ============================================================================
+// RESÚMENES
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub text: String,
+    pub cost_usd: f64,
+}
+
+/// Resume texto con un modelo (normalmente uno barato)
+#[async_trait]
+pub trait Summarizer: Send + Sync {
+    fn model(&self) -> String;
+    async fn summarize(&self, text: &str, max_tokens: u32) -> Result<Summary, FlowError>;
+}
+
+/// Resumidor sobre un adaptador configurado con `model`
+pub struct AdapterSummarizer {
+    adapter: Arc<dyn CodeGenerationFlow>,
+    model: ModelChoice,
+}
+
+impl AdapterSummarizer {
+    pub fn new(adapter: Arc<dyn CodeGenerationFlow>, model: ModelChoice) -> Self {
+        Self { adapter, model }
+    }
+}
+
+#[async_trait]
+impl Summarizer for AdapterSummarizer {
+    fn model(&self) -> String {
+        format!("{:?}", self.model)
+    }
+
+    async fn summarize(&self, text: &str, max_tokens: u32) -> Result<Summary, FlowError> {
+        let prompt = format!(
+            "Resume la siguiente conversación en menos de {} tokens. Conserva decisiones, \
+             requisitos, nombres de ficheros y fragmentos de código imprescindibles. \
+             Responde solo con el resumen.\n\n{}",
+            max_tokens, text
+        );
+        let result = self.adapter.execute(&prompt).await?;
+        Ok(Summary {
+            cost_usd: result.cost_estimate.as_ref().map(|estimate| estimate.estimated_cost_usd).unwrap_or(0.0),
+            text: result.code,
+        })
+    }
+}
+
+/// Resumen local, sin modelo: la primera línea de cada turno
+fn local_summary(turns: &[ContextItem]) -> String {
+    turns.iter()
+        .map(|turn| {
+            let first_line = turn.content.lines().find(|line| !line.trim().is_empty()).unwrap_or_default().trim();
+            let short: String = first_line.chars().take(160).collect();
+            let ellipsis = if short.len() < turn.content.trim().len() { "…" } else { "" };
+            format!("- {}: {}{}", turn.label(), short, ellipsis)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// ============================================================================
+// INFORME
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ContextAction {
+    Deduplicated { item: String, duplicate_of: String, saved_tokens: u32 },
+    Collapsed { item: String, saved_tokens: u32 },
+    Summarized { turns: usize, model: String, saved_tokens: u32 },
+    Dropped { item: String, priority: ContextPriority, tokens: u32 },
+}
+
+impl ContextAction {
+    pub fn saved_tokens(&self) -> u32 {
+        match self {
+            ContextAction::Deduplicated { saved_tokens, .. }
+            | ContextAction::Collapsed { saved_tokens, .. }
+            | ContextAction::Summarized { saved_tokens, .. } => *saved_tokens,
+            ContextAction::Dropped { tokens, .. } => *tokens,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            ContextAction::Deduplicated { item, duplicate_of, saved_tokens } => {
+                format!("{} repetido de {} (-{} tokens)", item, duplicate_of, saved_tokens)
+            }
+            ContextAction::Collapsed { item, saved_tokens } => format!("{} compactado (-{} tokens)", item, saved_tokens),
+            ContextAction::Summarized { turns, model, saved_tokens } => {
+                format!("{} turnos antiguos resumidos con {} (-{} tokens)", turns, model, saved_tokens)
+            }
+            ContextAction::Dropped { item, priority, tokens } => {
+                format!("{} descartado, prioridad {:?} (-{} tokens)", item, priority, tokens)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContextReport {
+    pub budget_tokens: u32,
+    pub original_tokens: u32,
+    pub final_tokens: u32,
+    pub actions: Vec<ContextAction>,
+    /// Costo de las llamadas de resumen
+    pub summary_cost_usd: f64,
+}
+
+impl ContextReport {
+    pub fn saved_tokens(&self) -> u32 {
+        self.original_tokens.saturating_sub(self.final_tokens)
+    }
+
+    pub fn over_budget(&self) -> bool {
+        self.final_tokens > self.budget_tokens
+    }
+
+    pub fn dropped(&self) -> impl Iterator<Item = &ContextAction> {
+        self.actions.iter().filter(|action| matches!(action, ContextAction::Dropped { .. }))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut detail = format!(
+            "{} -> {} tokens (presupuesto {})",
+            self.original_tokens, self.final_tokens, self.budget_tokens
+        );
+        for action in &self.actions {
+            detail.push_str("; ");
+            detail.push_str(&action.describe());
+        }
+        if self.over_budget() {
+            detail.push_str("; el contexto obligatorio no cabe");
+        }
+        detail
+    }
+}
+
+// ============================================================================
+// BUDGETER
+// ============================================================================
+
+/// Ajusta el contexto de un prompt a un presupuesto de tokens
+#[derive(Default)]
+pub struct ContextBudgeter {
+    summarizer: Option<Arc<dyn Summarizer>>,
+}
+
+impl ContextBudgeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_summarizer(mut self, summarizer: Arc<dyn Summarizer>) -> Self {
+        self.summarizer = Some(summarizer);
+        self
+    }
+
+    /// Comprime `items` para que `base_tokens` (el prompt sin contexto) más
+    /// el contexto quepan en `budget_tokens`
+    pub async fn fit(&self, base_tokens: u32, items: &mut Vec<ContextItem>, budget_tokens: u32) -> ContextReport {
+        let total = |items: &[ContextItem]| base_tokens + estimate_tokens(&render(items));
+        let mut report = ContextReport {
+            budget_tokens,
+            original_tokens: total(items),
+            ..ContextReport::default()
+        };
+        let fits = |items: &[ContextItem]| total(items) <= budget_tokens;
+
+        if !fits(items) {
+            deduplicate(items, &mut report.actions);
+        }
+        if !fits(items) {
+            collapse_whitespace(items, &mut report.actions);
+        }
+        if !fits(items) {
+            self.summarize_old_turns(items, &mut report).await;
+        }
+        while !fits(items) {
+            let Some(index) = items.iter().enumerate()
+                .filter(|(_, item)| item.priority != ContextPriority::Required)
+                .min_by_key(|(index, item)| (item.priority, *index))
+                .map(|(index, _)| index)
+            else {
+                break;
+            };
+            let item = items.remove(index);
+            report.actions.push(ContextAction::Dropped { item: item.label(), priority: item.priority, tokens: item.tokens() });
+        }
+
+        report.final_tokens = total(items);
+        report
+    }
+
+    /// Sustituye los turnos anteriores a los `RECENT_TURNS` últimos por un resumen
+    async fn summarize_old_turns(&self, items: &mut Vec<ContextItem>, report: &mut ContextReport) {
+        let turn_indexes: Vec<usize> = items.iter().enumerate()
+            .filter(|(_, item)| matches!(item.kind, ContextKind::Turn { .. }) && item.priority != ContextPriority::Required)
+            .map(|(index, _)| index)
+            .collect();
+        if turn_indexes.len() <= RECENT_TURNS {
+            return;
+        }
+        let old = &turn_indexes[..turn_indexes.len() - RECENT_TURNS];
+        let turns: Vec<ContextItem> = old.iter().map(|index| items[*index].clone()).collect();
+        let before: u32 = turns.iter().map(ContextItem::tokens).sum();
+        let transcript: String = turns.iter().map(ContextItem::render).collect();
+
+        let local = || (local_summary(&turns), "resumen local".to_string(), 0.0);
+        let (text, model, cost) = match &self.summarizer {
+            Some(summarizer) => match summarizer.summarize(&transcript, (before / 4).max(64)).await {
+                Ok(summary) => (summary.text, summarizer.model(), summary.cost_usd),
+                Err(e) => {
+                    log::warn!("⚠️ No se pudo resumir el historial ({}); se usa un resumen local", e);
+                    local()
+                }
+            },
+            None => local(),
+        };
+        let summary = ContextItem::new(ContextKind::Turn { role: "resumen".to_string() }, &text, ContextPriority::Normal);
+        let saved = before.saturating_sub(summary.tokens());
+        report.summary_cost_usd += cost;
+        if saved == 0 {
+            return;
+        }
+
+        let first = old[0];
+        for index in old.iter().rev() {
+            items.remove(*index);
+        }
+        items.insert(first, summary);
+        report.actions.push(ContextAction::Summarized { turns: old.len(), model, saved_tokens: saved });
+    }
+}
+
+fn deduplicate(items: &mut Vec<ContextItem>, actions: &mut Vec<ContextAction>) {
+    let mut kept: Vec<ContextItem> = Vec::with_capacity(items.len());
+    for item in items.drain(..) {
+        match kept.iter().find(|other| other.content.trim() == item.content.trim()) {
+            Some(original) => actions.push(ContextAction::Deduplicated {
+                item: item.label(),
+                duplicate_of: original.label(),
+                saved_tokens: item.tokens(),
+            }),
+            None => kept.push(item),
+        }
+    }
+    *items = kept;
+}
+
+/// Quita espacios al final de línea, espacios repetidos dentro de la línea
+/// y líneas en blanco repetidas. La sangría se conserva.
+fn collapse_whitespace(items: &mut [ContextItem], actions: &mut Vec<ContextAction>) {
+    for item in items.iter_mut().filter(|item| item.is_code()) {
+        let before = item.tokens();
+        let mut collapsed = String::with_capacity(item.content.len());
+        let mut blank_run = false;
+        for line in item.content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                if !blank_run && !collapsed.is_empty() {
+                    collapsed.push('\n');
+                }
+                blank_run = true;
+                continue;
+            }
+            blank_run = false;
+            let indent = line.len() - line.trim_start().len();
+            collapsed.push_str(&line[..indent]);
+            collapsed.push_str(&squeeze_spaces(line.trim_start()));
+            collapsed.push('\n');
+        }
+        item.content = collapsed.trim_end().to_string();
+        let saved = before.saturating_sub(item.tokens());
+        if saved > 0 {
+            actions.push(ContextAction::Collapsed { item: item.label(), saved_tokens: saved });
+        }
+    }
+}
+
+/// Reduce a uno los espacios seguidos fuera de la sangría, salvo en literales
+fn squeeze_spaces(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_string: Option<char> = None;
+    let mut previous = '\0';
+    for c in line.chars() {
+        match in_string {
+            Some(quote) if c == quote && previous != '\\' => in_string = None,
+            None if c == '"' || c == '\'' || c == '`' => in_string = Some(c),
+            None if c == ' ' && previous == ' ' => continue,
+            _ => {}
+        }
+        out.push(c);
+        previous = c;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fits_context_in_budget_and_reports_actions() {
+        let code = "fn main() {\n\n\n    let x  =  1;    \n}\n".repeat(20);
+        let mut items = vec![
+            ContextItem::memory(&"nota antigua ".repeat(100)),
+            ContextItem::file("src/main.rs", &code),
+            ContextItem::file("copia/main.rs", &code),
+            ContextItem::step_output(1, "fn paso() {}").with_priority(ContextPriority::Required),
+        ];
+        for i in 0..8 {
+            items.push(ContextItem::turn("usuario", &format!("petición {}\n{}", i, "detalle ".repeat(60))));
+        }
+
+        // Sin presión de presupuesto no se toca nada
+        let mut untouched = items.clone();
+        let report = ContextBudgeter::new().fit(100, &mut untouched, 100_000).await;
+        assert!(report.actions.is_empty());
+        assert_eq!(untouched, items);
+
+        let report = ContextBudgeter::new().fit(100, &mut items, 900).await;
+        assert!(!report.over_budget(), "{}", report.describe());
+        assert!(report.actions.iter().any(|a| matches!(a, ContextAction::Deduplicated { .. })));
+        assert!(report.actions.iter().any(|a| matches!(a, ContextAction::Collapsed { .. })));
+        assert!(report.actions.iter().any(|a| matches!(a, ContextAction::Summarized { turns: 4, .. })));
+        // Se descarta antes lo de menor prioridad; lo obligatorio se queda
+        assert!(report.dropped().next().is_some_and(|a| a.describe().starts_with("memoria")));
+        assert!(items.iter().any(|item| item.kind == ContextKind::StepOutput { step_id: 1 }));
+        assert!(items.iter().all(|item| !item.content.contains("  =  ")));
+
+        assert_eq!(token_budget(&ModelChoice::Gemini15Pro, 0.5), 44_444);
+        assert_eq!(token_budget(&ModelChoice::Gemini15Pro, 0.001), 0);
+    }
+}
@@ -0,0 +1,252 @@
+// ============================================================================
+// DRY RUN - Estimación de costo previa a la ejecución
+// ============================================================================
+// Construye el prompt de cada tarea (o de cada paso del plan), cuenta sus
+// tokens, elige modelo como lo haría la ejecución real (override de
+// configuración o elección del `CostOptimizer`, pasando por el control de
+// presupuesto diario global y por etiqueta) y lo valora con el precio del
+// adaptador que lo serviría. El peor caso supone todos los reintentos por
+// umbral de calidad y, dentro de cada uno, todos los refinados del adaptador
+// (`AdapterConfig.max_attempts`), con el prompt creciendo en cada llamada.
+// No se llama a ningún adaptador.
+// ============================================================================
+
+use super::{routing, ExecutionPlan, StepSignals, SwarmOrchestrator, Task, TaskBuilder, MAX_QUALITY_ATTEMPTS};
+use crate::cost_optimizer::{cost_at, estimate_tokens, ModelChoice, TaskComplexity, MIN_EXPECTED_OUTPUT_TOKENS};
+use crate::FlowError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Estimación de una tarea o paso de plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepEstimate {
+    pub step_id: Option<u32>,
+    pub description: String,
+    pub complexity: TaskComplexity,
+    pub model: ModelChoice,
+    /// Modelo elegido antes de que el presupuesto lo degradara
+    pub downgraded_from: Option<ModelChoice>,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Llamadas facturadas en el peor caso (reintentos de calidad por
+    /// refinados del adaptador)
+    pub max_attempts: u32,
+    pub expected_cost_usd: f64,
+    pub worst_case_cost_usd: f64,
+    pub cost_limit_usd: Option<f64>,
+    /// Motivo por el que la ejecución real rechazaría la tarea
+    pub refused: Option<String>,
+}
+
+/// Estimación de una ejecución completa
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunEstimate {
+    pub steps: Vec<StepEstimate>,
+    pub expected_total_usd: f64,
+    pub worst_case_total_usd: f64,
+    pub daily_budget_usd: Option<f64>,
+    pub spent_today_usd: f64,
+}
+
+impl RunEstimate {
+    fn from_steps(orchestrator: &SwarmOrchestrator, steps: Vec<StepEstimate>) -> Self {
+        Self {
+            expected_total_usd: steps.iter().fold(0.0, |total, step| total + step.expected_cost_usd),
+            worst_case_total_usd: steps.iter().fold(0.0, |total, step| total + step.worst_case_cost_usd),
+            daily_budget_usd: orchestrator.config.cost_constraints.daily_budget,
+            spent_today_usd: orchestrator.ledger.spent_today(),
+            steps,
+        }
+    }
+
+    /// Avisos para revisar antes de aprobar la ejecución
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = self.steps.iter()
+            .filter_map(|step| {
+                let label = step.step_id.map(|id| format!("Paso {}", id)).unwrap_or_else(|| "La tarea".to_string());
+                if let Some(reason) = &step.refused {
+                    return Some(format!("{} se rechazaría: {}", label, reason));
+                }
+                step.cost_limit_usd
+                    .filter(|limit| step.worst_case_cost_usd > *limit)
+                    .map(|limit| format!(
+                        "{} puede superar su límite de ${:.4} si necesita {} intentos (${:.4})",
+                        label, limit, step.max_attempts, step.worst_case_cost_usd
+                    ))
+            })
+            .collect();
+        if let Some(budget) = self.daily_budget_usd {
+            let remaining = (budget - self.spent_today_usd).max(0.0);
+            if self.worst_case_total_usd > remaining {
+                warnings.push(format!(
+                    "El peor caso (${:.4}) supera lo que queda del presupuesto diario (${:.4} de ${:.2})",
+                    self.worst_case_total_usd, remaining, budget
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+impl SwarmOrchestrator {
+    /// Estima el costo de una tarea sin ejecutarla
+    pub fn estimate_task(&self, task: &Task) -> RunEstimate {
+        let step = self.estimate_step(task, None, StepSignals::default(), 0.0);
+        RunEstimate::from_steps(self, vec![step])
+    }
+
+    /// Estima el costo de un plan sin ejecutarlo. Las salidas de las
+    /// dependencias, que se añaden al prompt de cada paso, se suponen del
+    /// tamaño de salida esperado de esos pasos.
+    pub fn estimate_plan(&self, plan: &ExecutionPlan) -> Result<RunEstimate, FlowError> {
+        let mut output_tokens: HashMap<u32, u32> = HashMap::new();
+        let mut steps = Vec::new();
+        let mut projected_spend = 0.0;
+
+        for step in plan.execution_order()? {
+            let mut prompt = step.task.clone();
+            if let Some(details) = &step.details {
+                prompt.push_str(&format!("\n\nDetalles: {}", details));
+            }
+            let context_tokens: u32 = step.depends_on.iter().filter_map(|dep| output_tokens.get(dep)).sum();
+            let signals = StepSignals {
+                plan_steps: plan.steps.len(),
+                tool_count: step.tools.len(),
+                context_chars: context_tokens as usize * 4,
+            };
+
+            let task = TaskBuilder::new(routing::infer_task_type(&step.task), prompt).build();
+            let mut estimate = self.estimate_step(&task, Some(step.id), signals, projected_spend);
+            estimate.description = step.task.clone();
+            output_tokens.insert(step.id, estimate.output_tokens);
+            projected_spend += estimate.expected_cost_usd;
+            steps.push(estimate);
+        }
+        Ok(RunEstimate::from_steps(self, steps))
+    }
+
+    fn estimate_step(&self, task: &Task, step_id: Option<u32>, signals: StepSignals, projected_spend: f64) -> StepEstimate {
+        let assessment = self.cost_optimizer.assess_complexity(&self.complexity_input(task, signals));
+        let chosen_model = self.overridden_model(&task.task_type, &assessment.complexity).unwrap_or_else(|| {
+            self.cost_optimizer.optimize_model_selection(assessment.complexity.clone(), &self.config.cost_constraints)
+        });
+
+        let handler = self.task_router.route(&task.task_type);
+        let input_tokens = estimate_tokens(&handler.build_prompt(task)) + signals.context_chars.div_ceil(4) as u32;
+        let output_tokens = input_tokens.max(MIN_EXPECTED_OUTPUT_TOKENS);
+        let cost_limit = task.requirements.max_cost_usd.or(self.config.cost_constraints.max_cost_per_request);
+
+        // El presupuesto diario más restrictivo: el global o el de alguna etiqueta
+        let labels = self.ledger.resolve_labels(&self.task_labels(task));
+        let (daily_budget, spent_today, _) = self.daily_limit(&labels);
+        let budget = self.cost_optimizer.enforce_budget(
+            chosen_model.clone(),
+            |model| self.serving_price(model),
+            input_tokens,
+            cost_limit,
+            daily_budget,
+            spent_today + projected_spend,
+        );
+        let (model, downgraded_from, refused) = match budget {
+            Ok(decision) => (decision.model, decision.downgraded_from, None),
+            Err(e) => (chosen_model, None, Some(e.to_string())),
+        };
+
+        // Cada llamada reenvía el prompt con el código anterior y el feedback
+        let quality_attempts = if task.requirements.quality_threshold.is_some() { MAX_QUALITY_ATTEMPTS } else { 1 };
+        let max_attempts = quality_attempts * self.serving_attempts(&model);
+        let price = self.serving_price(&model);
+        let expected_cost = cost_at(price, input_tokens, output_tokens);
+        let worst_case_cost = (0..max_attempts)
+            .map(|attempt| cost_at(price, input_tokens + attempt * output_tokens, output_tokens))
+            .sum();
+
+        StepEstimate {
+            step_id,
+            description: task.description.clone(),
+            complexity: assessment.complexity,
+            model,
+            downgraded_from,
+            input_tokens,
+            output_tokens,
+            max_attempts,
+            expected_cost_usd: if refused.is_some() { 0.0 } else { expected_cost },
+            worst_case_cost_usd: if refused.is_some() { 0.0 } else { worst_case_cost },
+            cost_limit_usd: cost_limit,
+            refused,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swarm::{SwarmConfig, TaskStep};
+
+    #[test]
+    fn test_estimates_plan_steps_with_dependency_context() {
+        let orchestrator = SwarmOrchestrator::new(SwarmConfig { enable_adaptive_learning: false, ..SwarmConfig::default() });
+        let plan = ExecutionPlan {
+            original_objective: "api".to_string(),
+            steps: vec![
+                TaskStep { id: 1, task: "crear el modelo de datos".to_string(), tools: vec![], tool_params: HashMap::new(), depends_on: vec![], details: None },
+                TaskStep { id: 2, task: "crear los endpoints".to_string(), tools: vec![], tool_params: HashMap::new(), depends_on: vec![1], details: None },
+            ],
+        };
+
+        let estimate = orchestrator.estimate_plan(&plan).unwrap();
+        assert_eq!(estimate.steps.len(), 2);
+        // El paso 2 recibe la salida esperada del paso 1 como contexto
+        assert!(estimate.steps[1].input_tokens > estimate.steps[0].output_tokens);
+        assert!(estimate.worst_case_total_usd >= estimate.expected_total_usd);
+
+        let task = TaskBuilder::new(crate::swarm::TaskType::CodeGeneration, "hola".to_string())
+            .with_quality_threshold(0.9)
+            .with_max_cost(0.000_001)
+            .build();
+        let estimate = orchestrator.estimate_task(&task);
+        assert!(estimate.steps[0].refused.is_some());
+        assert!(!estimate.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_label_budgets_apply_to_the_estimate() {
+        let mut config = SwarmConfig { enable_adaptive_learning: false, ..SwarmConfig::default() };
+        config.cost_constraints.label_budgets = vec![crate::cost_optimizer::LabelBudget {
+            label: "ticket".to_string(),
+            value: "OPS-1".to_string(),
+            daily_usd: 0.0,
+        }];
+        let orchestrator = SwarmOrchestrator::new(config);
+
+        let labeled = TaskBuilder::new(crate::swarm::TaskType::CodeGeneration, "hola".to_string()).with_label("ticket", "OPS-1").build();
+        assert!(orchestrator.estimate_task(&labeled).steps[0].refused.is_some());
+        let other = TaskBuilder::new(crate::swarm::TaskType::CodeGeneration, "hola".to_string()).with_label("ticket", "OPS-2").build();
+        assert!(orchestrator.estimate_task(&other).steps[0].refused.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_worst_case_includes_adapter_refinements() {
+        let mut orchestrator = SwarmOrchestrator::new(SwarmConfig { enable_adaptive_learning: false, ..SwarmConfig::default() });
+        let config = crate::adapters::AdapterConfig {
+            api_key: "test".to_string(),
+            base_url: None,
+            timeout_seconds: 5,
+            max_attempts: 3,
+            enable_verification: true,
+            project_id: None,
+            location: None,
+        };
+        orchestrator.initialize(HashMap::from([("gemini".to_string(), config)])).await.unwrap();
+        orchestrator.override_model(crate::swarm::TaskType::CodeGeneration, ModelChoice::Gemini15Flash);
+
+        let task = TaskBuilder::new(crate::swarm::TaskType::CodeGeneration, "hola".to_string())
+            .with_quality_threshold(0.9)
+            .build();
+        let step = &orchestrator.estimate_task(&task).steps[0];
+        assert_eq!(step.max_attempts, MAX_QUALITY_ATTEMPTS * 3);
+        // Precio del adaptador dedicado a Flash, no el de Pro del genérico
+        let flash = ModelChoice::Gemini15Flash;
+        assert!((step.expected_cost_usd - flash.cost(step.input_tokens, step.output_tokens)).abs() < 1e-12);
+    }
+}
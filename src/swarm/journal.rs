@@ -0,0 +1,552 @@
+// ============================================================================
+// RUN JOURNAL - Registro estructurado de cada ejecución del swarm
+// ============================================================================
+// Cada ejecución escribe un journal JSON Lines en
+// `~/.enjambre/runs/<session_id>/journal.jsonl` con prompts, respuestas del
+// modelo, verificaciones, llamadas a herramientas, mensajes del bus entre
+// agentes, decisiones, tiempos y costos. A partir de ese journal,
+// `enjambre swarm replay <id>` vuelve a ejecutar la orquestación sustituyendo
+// el modelo y las herramientas por las respuestas grabadas, de modo que los
+// fallos de planificación o de despacho de herramientas se pueden reproducir
+// sin conexión, y compara los mensajes del bus de la reproducción con los
+// grabados.
+// ============================================================================
+
+use super::{ExecutionPlan, SwarmConfig, SwarmExecutionResult, SwarmOrchestrator, Task};
+use super::bus::LoggedMessage;
+use crate::{
+    AdapterCapabilities, CodeGenerationFlow, CodeGenerationResult, FlowError, VerificationResult,
+    tools::ToolResult,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Nombre del fichero de journal dentro del directorio de la ejecución
+pub const JOURNAL_FILE: &str = "journal.jsonl";
+
+// ============================================================================
+// EVENTOS
+// ============================================================================
+
+/// Entrada de la ejecución que se puede volver a lanzar en un replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RunInput {
+    Task(Task),
+    Plan(ExecutionPlan),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JournalEvent {
+    RunStarted {
+        session_id: String,
+        config: Box<SwarmConfig>,
+        input: Box<RunInput>,
+        /// Sesión original si esta ejecución es un replay
+        replay_of: Option<String>,
+    },
+    AdapterRegistered {
+        adapter: String,
+        capabilities: AdapterCapabilities,
+    },
+    /// Una ejecución interrumpida continúa en este mismo journal
+    RunResumed {
+        session_id: String,
+        completed_steps: Vec<u32>,
+        spent_usd: f64,
+    },
+    TaskStarted {
+        task: Task,
+    },
+    Decision {
+        task_id: Option<String>,
+        kind: String,
+        detail: String,
+    },
+    Prompt {
+        adapter: String,
+        prompt: String,
+    },
+    Response {
+        adapter: String,
+        prompt: String,
+        result: Result<CodeGenerationResult, String>,
+        duration_ms: u64,
+    },
+    Verification {
+        adapter: String,
+        code: String,
+        result: RecordedVerification,
+    },
+    ToolCall {
+        tool: String,
+        params: serde_json::Value,
+        result: Result<ToolResult, String>,
+        duration_ms: u64,
+    },
+    /// Mensaje entre agentes, tal como quedó en el log del bus
+    BusMessage {
+        message: LoggedMessage,
+    },
+    TaskFinished {
+        result: Box<SwarmExecutionResult>,
+    },
+    RunFinished {
+        success: bool,
+        duration_ms: u64,
+        total_cost: f64,
+    },
+}
+
+/// Copia serializable de un `VerificationResult`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedVerification {
+    pub is_valid: bool,
+    pub compilation_success: bool,
+    pub tests_passed: bool,
+    pub quality_score: f64,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl From<&VerificationResult> for RecordedVerification {
+    fn from(result: &VerificationResult) -> Self {
+        Self {
+            is_valid: result.is_valid,
+            compilation_success: result.compilation_success,
+            tests_passed: result.tests_passed,
+            quality_score: result.quality_score,
+            errors: result.errors.clone(),
+            warnings: result.warnings.clone(),
+        }
+    }
+}
+
+impl From<RecordedVerification> for VerificationResult {
+    fn from(recorded: RecordedVerification) -> Self {
+        Self {
+            is_valid: recorded.is_valid,
+            compilation_success: recorded.compilation_success,
+            tests_passed: recorded.tests_passed,
+            quality_score: recorded.quality_score,
+            errors: recorded.errors,
+            warnings: recorded.warnings,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub sequence: u64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Milisegundos desde el inicio de la ejecución
+    pub elapsed_ms: u64,
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+// ============================================================================
+// JOURNAL
+// ============================================================================
+
+struct JournalWriter {
+    file: std::fs::File,
+    sequence: u64,
+}
+
+pub struct RunJournal {
+    dir: PathBuf,
+    started: std::time::Instant,
+    writer: Mutex<JournalWriter>,
+}
+
+impl RunJournal {
+    /// Directorio raíz de los journals: `~/.enjambre/runs`
+    pub fn runs_dir() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".enjambre").join("runs"))
+    }
+
+    /// Crea el journal de una sesión en `~/.enjambre/runs/<session_id>/`
+    pub fn create(session_id: &str) -> std::io::Result<Self> {
+        let runs_dir = Self::runs_dir().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "No se encontró el directorio home")
+        })?;
+        Self::create_in(&runs_dir.join(session_id))
+    }
+
+    /// Crea el journal en un directorio concreto. Si ya existe (reanudación)
+    /// se sigue añadiendo a continuación de la última secuencia escrita.
+    pub fn create_in(dir: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(JOURNAL_FILE);
+        let sequence = Self::load_from(&path).ok()
+            .and_then(|entries| entries.iter().map(|entry| entry.sequence + 1).max())
+            .unwrap_or(0);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            started: std::time::Instant::now(),
+            writer: Mutex::new(JournalWriter { file, sequence }),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Añade un evento al journal. Los errores de escritura solo se registran
+    /// en el log: el journal nunca interrumpe una ejecución.
+    pub fn record(&self, event: JournalEvent) {
+        let Ok(mut writer) = self.writer.lock() else {
+            log::warn!("⚠️ Journal inaccesible, se descarta un evento");
+            return;
+        };
+        let entry = JournalEntry {
+            sequence: writer.sequence,
+            timestamp: chrono::Utc::now(),
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            event,
+        };
+        writer.sequence += 1;
+
+        let written = serde_json::to_string(&entry)
+            .map_err(std::io::Error::other)
+            .and_then(|line| writeln!(writer.file, "{}", line));
+        if let Err(e) = written {
+            log::warn!("⚠️ No se pudo escribir en el journal {}: {}", self.dir.display(), e);
+        }
+    }
+
+    /// Carga el journal de una sesión por id
+    pub fn load(session_id: &str) -> Result<Vec<JournalEntry>, FlowError> {
+        let runs_dir = Self::runs_dir()
+            .ok_or_else(|| FlowError::InvalidPrompt("No se encontró el directorio home".to_string()))?;
+        Self::load_from(&runs_dir.join(session_id).join(JOURNAL_FILE))
+    }
+
+    /// Carga un journal desde un fichero
+    pub fn load_from(path: &Path) -> Result<Vec<JournalEntry>, FlowError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            FlowError::InvalidPrompt(format!("No se pudo leer el journal {}: {}", path.display(), e))
+        })?;
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line)
+                .map_err(|e| FlowError::InvalidResponse(format!("Entrada de journal inválida: {}", e))))
+            .collect()
+    }
+}
+
+// ============================================================================
+// ADAPTADOR CON JOURNAL
+// ============================================================================
+
+/// Decorador que registra en el journal cada prompt, respuesta y verificación
+pub struct JournalingAdapter {
+    name: String,
+    inner: Arc<dyn CodeGenerationFlow>,
+    journal: Arc<RunJournal>,
+}
+
+impl JournalingAdapter {
+    pub fn new(name: &str, inner: Arc<dyn CodeGenerationFlow>, journal: Arc<RunJournal>) -> Self {
+        journal.record(JournalEvent::AdapterRegistered {
+            adapter: name.to_string(),
+            capabilities: inner.get_capabilities(),
+        });
+        Self { name: name.to_string(), inner, journal }
+    }
+}
+
+#[async_trait]
+impl CodeGenerationFlow for JournalingAdapter {
+    async fn execute(&self, problem_description: &str) -> Result<CodeGenerationResult, FlowError> {
+        self.execute_with_tools(problem_description, &[]).await
+    }
+
+    async fn execute_with_tools(
+        &self,
+        problem_description: &str,
+        tools: &[serde_json::Value],
+    ) -> Result<CodeGenerationResult, FlowError> {
+        self.journal.record(JournalEvent::Prompt {
+            adapter: self.name.clone(),
+            prompt: problem_description.to_string(),
+        });
+
+        let start = std::time::Instant::now();
+        let result = self.inner.execute_with_tools(problem_description, tools).await;
+
+        self.journal.record(JournalEvent::Response {
+            adapter: self.name.clone(),
+            prompt: problem_description.to_string(),
+            result: result.as_ref().map(|r| r.clone()).map_err(|e| e.to_string()),
+            duration_ms: start.elapsed().as_millis() as u64,
+        });
+        result
+    }
+
+    fn verify_code(&self, code: &str) -> VerificationResult {
+        let result = self.inner.verify_code(code);
+        self.journal.record(JournalEvent::Verification {
+            adapter: self.name.clone(),
+            code: code.to_string(),
+            result: RecordedVerification::from(&result),
+        });
+        result
+    }
+
+    fn get_capabilities(&self) -> AdapterCapabilities {
+        self.inner.get_capabilities()
+    }
+
+    fn cache_fingerprint(&self) -> String {
+        self.inner.cache_fingerprint()
+    }
+}
+
+// ============================================================================
+// REPLAY
+// ============================================================================
+
+/// Adaptador que devuelve, en orden, las respuestas grabadas en un journal
+pub struct ReplayAdapter {
+    name: String,
+    capabilities: AdapterCapabilities,
+    responses: Mutex<VecDeque<(String, Result<CodeGenerationResult, String>)>>,
+    verifications: Mutex<VecDeque<RecordedVerification>>,
+    divergences: Arc<Mutex<Vec<String>>>,
+}
+
+impl ReplayAdapter {
+    /// Construye el adaptador con los eventos grabados para `adapter`
+    pub fn from_journal(adapter: &str, entries: &[JournalEntry], divergences: Arc<Mutex<Vec<String>>>) -> Option<Self> {
+        let mut capabilities = None;
+        let mut responses = VecDeque::new();
+        let mut verifications = VecDeque::new();
+
+        for entry in entries {
+            match &entry.event {
+                JournalEvent::AdapterRegistered { adapter: name, capabilities: caps } if name == adapter => {
+                    capabilities = Some(caps.clone());
+                }
+                JournalEvent::Response { adapter: name, prompt, result, .. } if name == adapter => {
+                    responses.push_back((prompt.clone(), result.clone()));
+                }
+                JournalEvent::Verification { adapter: name, result, .. } if name == adapter => {
+                    verifications.push_back(result.clone());
+                }
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            name: adapter.to_string(),
+            capabilities: capabilities?,
+            responses: Mutex::new(responses),
+            verifications: Mutex::new(verifications),
+            divergences,
+        })
+    }
+
+    fn diverge(&self, message: String) {
+        log::warn!("🔀 Replay divergente en '{}': {}", self.name, message);
+        if let Ok(mut divergences) = self.divergences.lock() {
+            divergences.push(message);
+        }
+    }
+}
+
+#[async_trait]
+impl CodeGenerationFlow for ReplayAdapter {
+    async fn execute(&self, problem_description: &str) -> Result<CodeGenerationResult, FlowError> {
+        let next = self.responses.lock().ok().and_then(|mut responses| responses.pop_front());
+        let Some((prompt, result)) = next else {
+            self.diverge("se pidieron más respuestas de las grabadas".to_string());
+            return Err(FlowError::ApiError("Replay agotado: no quedan respuestas grabadas".to_string()));
+        };
+
+        if prompt != problem_description {
+            self.diverge(format!(
+                "el prompt difiere del grabado ({} vs {} caracteres)",
+                problem_description.len(), prompt.len()
+            ));
+        }
+        result.map_err(FlowError::ApiError)
+    }
+
+    fn verify_code(&self, code: &str) -> VerificationResult {
+        match self.verifications.lock().ok().and_then(|mut verifications| verifications.pop_front()) {
+            Some(recorded) => recorded.into(),
+            None => {
+                self.diverge("se pidieron más verificaciones de las grabadas".to_string());
+                VerificationResult {
+                    is_valid: !code.trim().is_empty(),
+                    compilation_success: false,
+                    tests_passed: false,
+                    quality_score: 0.0,
+                    errors: vec!["Verificación no grabada en el journal".to_string()],
+                    warnings: Vec::new(),
+                }
+            }
+        }
+    }
+
+    fn get_capabilities(&self) -> AdapterCapabilities {
+        self.capabilities.clone()
+    }
+}
+
+/// Llamadas a herramientas grabadas, consumidas en orden por herramienta
+#[derive(Default)]
+pub struct ToolReplay {
+    calls: Mutex<HashMap<String, VecDeque<Result<ToolResult, String>>>>,
+}
+
+impl ToolReplay {
+    pub fn from_journal(entries: &[JournalEntry]) -> Self {
+        let mut calls: HashMap<String, VecDeque<_>> = HashMap::new();
+        for entry in entries {
+            if let JournalEvent::ToolCall { tool, result, .. } = &entry.event {
+                calls.entry(tool.clone()).or_default().push_back(result.clone());
+            }
+        }
+        Self { calls: Mutex::new(calls) }
+    }
+
+    /// Siguiente resultado grabado para una herramienta
+    pub fn next(&self, tool: &str) -> Option<Result<ToolResult, String>> {
+        self.calls.lock().ok()?.get_mut(tool)?.pop_front()
+    }
+}
+
+/// Resultado de reproducir una ejecución grabada
+#[derive(Debug, Clone)]
+pub struct ReplayReport {
+    pub original_session: String,
+    pub replay_session: String,
+    pub recorded: Vec<SwarmExecutionResult>,
+    pub replayed: Vec<SwarmExecutionResult>,
+    /// Mensajes del bus grabados y los de la reproducción
+    pub recorded_messages: Vec<LoggedMessage>,
+    pub replayed_messages: Vec<LoggedMessage>,
+    pub divergences: Vec<String>,
+}
+
+impl ReplayReport {
+    /// Si la reproducción obtuvo los mismos resultados que la ejecución grabada
+    pub fn matches(&self) -> bool {
+        self.divergences.is_empty()
+            && self.recorded.len() == self.replayed.len()
+            && self.recorded.iter().zip(&self.replayed).all(|(recorded, replayed)| {
+                recorded.success == replayed.success
+                    && recorded.result.as_ref().map(|r| &r.code) == replayed.result.as_ref().map(|r| &r.code)
+            })
+            && bus_divergence(&self.recorded_messages, &self.replayed_messages).is_none()
+    }
+}
+
+/// Primera diferencia entre dos logs del bus. Se comparan remitente,
+/// destino, contenido y entregas; no los ids, las horas ni la sesión.
+pub fn bus_divergence(recorded: &[LoggedMessage], replayed: &[LoggedMessage]) -> Option<String> {
+    for (index, (recorded, replayed)) in recorded.iter().zip(replayed).enumerate() {
+        let (a, b) = (&recorded.message, &replayed.message);
+        if a.sender != b.sender || a.recipient != b.recipient || a.payload != b.payload
+            || recorded.delivered_to != replayed.delivered_to
+        {
+            return Some(format!(
+                "mensaje {} del bus: grabado {} -> {:?} ({:?}), reproducido {} -> {:?} ({:?})",
+                index, a.sender, a.recipient, a.payload, b.sender, b.recipient, b.payload
+            ));
+        }
+    }
+    (recorded.len() != replayed.len()).then(|| format!(
+        "el bus grabó {} mensajes y la reproducción {}", recorded.len(), replayed.len()
+    ))
+}
+
+/// Vuelve a ejecutar la orquestación de una sesión grabada contra sus
+/// respuestas. Con `record`, la reproducción escribe su propio journal con
+/// `replay_of` apuntando a la sesión original.
+pub async fn replay_run(entries: &[JournalEntry], record: bool) -> Result<ReplayReport, FlowError> {
+    let Some((original_session, config, input)) = entries.iter().find_map(|entry| match &entry.event {
+        JournalEvent::RunStarted { session_id, config, input, .. } => {
+            Some((session_id.clone(), config.as_ref().clone(), input.as_ref().clone()))
+        }
+        _ => None,
+    }) else {
+        return Err(FlowError::InvalidResponse("El journal no contiene RunStarted".to_string()));
+    };
+
+    let recorded: Vec<SwarmExecutionResult> = entries.iter()
+        .filter_map(|entry| match &entry.event {
+            JournalEvent::TaskFinished { result } => Some(result.as_ref().clone()),
+            _ => None,
+        })
+        .collect();
+    let recorded_messages: Vec<LoggedMessage> = entries.iter()
+        .filter_map(|entry| match &entry.event {
+            JournalEvent::BusMessage { message } => Some(message.clone()),
+            _ => None,
+        })
+        .collect();
+
+    // El aprendizaje es aleatorio: en el replay se fuerzan los modelos grabados
+    let config = SwarmConfig { enable_adaptive_learning: false, ..config };
+    let mut orchestrator = SwarmOrchestrator::new(config);
+    orchestrator.set_model_replay(recorded.iter().map(|result| result.selected_model.clone()).collect());
+    let divergences = Arc::new(Mutex::new(Vec::new()));
+
+    if record {
+        match RunJournal::create(orchestrator.session_id()) {
+            Ok(journal) => orchestrator.enable_journal(Arc::new(journal)),
+            Err(e) => log::warn!("⚠️ No se pudo crear el journal del replay: {}", e),
+        }
+    }
+    orchestrator.record_run_started(&input, Some(&original_session));
+
+    let adapters: Vec<String> = entries.iter()
+        .filter_map(|entry| match &entry.event {
+            JournalEvent::AdapterRegistered { adapter, .. } => Some(adapter.clone()),
+            _ => None,
+        })
+        .collect();
+    for name in adapters {
+        if let Some(adapter) = ReplayAdapter::from_journal(&name, entries, Arc::clone(&divergences)) {
+            orchestrator.register_adapter(&name, Arc::new(adapter));
+        }
+    }
+    orchestrator.set_tool_replay(ToolReplay::from_journal(entries));
+
+    let start = std::time::Instant::now();
+    let replayed = match input {
+        RunInput::Task(task) => vec![orchestrator.execute_task(task).await],
+        RunInput::Plan(plan) => orchestrator.execute_plan(&plan).await?
+            .steps
+            .into_iter()
+            .map(|step| step.result)
+            .collect(),
+    };
+    orchestrator.record_run_finished(replayed.iter().all(|r| r.success), start.elapsed().as_millis() as u64);
+
+    let replayed_messages = orchestrator.message_bus().message_log();
+    let mut divergences = divergences.lock().map(|d| d.clone()).unwrap_or_default();
+    divergences.extend(bus_divergence(&recorded_messages, &replayed_messages));
+    Ok(ReplayReport {
+        original_session,
+        replay_session: orchestrator.session_id().to_string(),
+        recorded,
+        replayed,
+        recorded_messages,
+        replayed_messages,
+        divergences,
+    })
+}
@@ -0,0 +1,386 @@
+// ============================================================================
+// ADAPTIVE LEARNING - Selección de modelo aprendida del historial
+// ============================================================================
+// Cada `SwarmExecutionResult` actualiza las estadísticas del modelo usado para
+// la combinación (TaskType, TaskComplexity): éxitos, latencia y costo. Al
+// elegir modelo se aplica Thompson sampling sobre una Beta(éxitos, fallos),
+// penalizada por costo y latencia medios, con una tasa de exploración
+// aleatoria. La elección del `CostOptimizer` recibe un prior a favor, de modo
+// que el aprendizaje solo la sustituye cuando los datos lo justifican.
+// Las estadísticas se guardan en `~/.enjambre/learning/model_stats.json`;
+// como en el ledger de gasto, al guardar se toma un bloqueo sobre
+// `model_stats.lock` y se suman solo los resultados nuevos a lo que haya en
+// el fichero. Los fallos que no son culpa del modelo
+// (presupuesto, aprobación, timeout de la tarea) no cuentan.
+// ============================================================================
+
+use super::{SwarmExecutionResult, TaskType};
+use crate::cost_optimizer::{HistoricalOutcome, ModelChoice, TaskComplexity};
+use rand::Rng;
+use rand_distr::{Beta, Distribution};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Errores (`FlowError::kind`) ajenos al modelo: no cuentan como un fallo suyo
+const NON_MODEL_ERRORS: [&str; 6] = [
+    "cost_limit_exceeded",
+    "daily_budget_exceeded",
+    "approval_denied",
+    "timeout",
+    "invalid_prompt",
+    "adapter_not_found",
+];
+
+/// Modelos entre los que elige el aprendizaje
+const CANDIDATE_MODELS: [ModelChoice; 3] = [
+    ModelChoice::Gemini15Flash,
+    ModelChoice::Gemini15Pro,
+    ModelChoice::Gemini15ProExp,
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LearningConfig {
+    /// Probabilidad de elegir un modelo al azar
+    pub exploration_rate: f64,
+    /// Éxitos ficticios a favor del modelo elegido por el `CostOptimizer`
+    pub optimizer_prior: f64,
+    /// Penalización por cada USD de costo medio
+    pub cost_weight: f64,
+    /// Penalización por cada segundo de latencia media
+    pub latency_weight: f64,
+}
+
+impl Default for LearningConfig {
+    fn default() -> Self {
+        Self {
+            exploration_rate: 0.05,
+            optimizer_prior: 2.0,
+            cost_weight: 10.0,
+            latency_weight: 0.01,
+        }
+    }
+}
+
+/// Estadísticas acumuladas de un modelo para un tipo de tarea y complejidad
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelStats {
+    pub trials: u64,
+    pub successes: u64,
+    pub total_latency_ms: u64,
+    pub total_cost_usd: f64,
+}
+
+impl ModelStats {
+    pub fn success_rate(&self) -> f64 {
+        if self.trials == 0 { 0.0 } else { self.successes as f64 / self.trials as f64 }
+    }
+
+    pub fn mean_latency_ms(&self) -> f64 {
+        if self.trials == 0 { 0.0 } else { self.total_latency_ms as f64 / self.trials as f64 }
+    }
+
+    pub fn mean_cost_usd(&self) -> f64 {
+        if self.trials == 0 { 0.0 } else { self.total_cost_usd / self.trials as f64 }
+    }
+
+    fn add(&mut self, other: &ModelStats) {
+        self.trials += other.trials;
+        self.successes += other.successes;
+        self.total_latency_ms += other.total_latency_ms;
+        self.total_cost_usd += other.total_cost_usd;
+    }
+}
+
+/// Registro persistido de las estadísticas de un modelo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelStatsRecord {
+    pub task_type: TaskType,
+    pub complexity: TaskComplexity,
+    pub model: ModelChoice,
+    pub stats: ModelStats,
+}
+
+type StatsKey = (TaskType, TaskComplexity, ModelChoice);
+
+pub struct AdaptiveLearner {
+    config: LearningConfig,
+    stats: HashMap<StatsKey, ModelStats>,
+    /// Resultados registrados desde la última vez que se guardó
+    pending: HashMap<StatsKey, ModelStats>,
+    path: Option<PathBuf>,
+}
+
+impl AdaptiveLearner {
+    /// Aprendizaje en memoria, sin persistencia
+    pub fn new(config: LearningConfig) -> Self {
+        Self { config, stats: HashMap::new(), pending: HashMap::new(), path: None }
+    }
+
+    /// Ruta por defecto de las estadísticas aprendidas
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".enjambre").join("learning").join("model_stats.json"))
+    }
+
+    /// Carga las estadísticas de la ruta por defecto (vacías si no existen)
+    pub fn load_or_default() -> Self {
+        match Self::default_path() {
+            Some(path) => Self::load_from(&path),
+            None => Self::new(LearningConfig::default()),
+        }
+    }
+
+    /// Carga las estadísticas de un fichero; se guardarán en el mismo sitio
+    pub fn load_from(path: &Path) -> Self {
+        let mut learner = Self::new(LearningConfig::default());
+        learner.path = Some(path.to_path_buf());
+        learner.stats = read_stats(path);
+        learner
+    }
+
+    pub fn with_config(mut self, config: LearningConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Guarda los resultados pendientes si el aprendizaje es persistente
+    pub fn save(&mut self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Sumar lo nuevo a lo que haya escrito cualquier otra ejecución, sin
+        // que otra pueda guardar entre la lectura y el rename
+        let _lock = crate::cost_optimizer::ledger::lock_sibling(path)?;
+        let mut stats = read_stats(path);
+        for (key, delta) in std::mem::take(&mut self.pending) {
+            stats.entry(key).or_default().add(&delta);
+        }
+        let content = serde_json::to_string_pretty(&to_records(&stats)).map_err(std::io::Error::other)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(tmp, path)?;
+        self.stats = stats;
+        Ok(())
+    }
+
+    /// Todas las estadísticas, en formato persistible
+    pub fn records(&self) -> Vec<ModelStatsRecord> {
+        to_records(&self.stats)
+    }
+
+    pub fn stats_for(&self, task_type: &TaskType, complexity: &TaskComplexity, model: &ModelChoice) -> Option<&ModelStats> {
+        self.stats.get(&(task_type.clone(), complexity.clone(), model.clone()))
+    }
+
+    /// Resultados previos de un tipo de tarea, con cualquier modelo y complejidad
+    pub fn outcome(&self, task_type: &TaskType) -> Option<HistoricalOutcome> {
+        let (trials, successes) = self.stats.iter()
+            .filter(|((stats_type, _, _), _)| stats_type == task_type)
+            .fold((0, 0), |(trials, successes), (_, stats)| (trials + stats.trials, successes + stats.successes));
+        (trials > 0).then(|| HistoricalOutcome { trials, success_rate: successes as f64 / trials as f64 })
+    }
+
+    /// Actualiza las estadísticas con el resultado de una tarea; los fallos
+    /// ajenos al modelo se ignoran
+    pub fn record(&mut self, task_type: &TaskType, complexity: &TaskComplexity, result: &SwarmExecutionResult) {
+        if result.selected_model == ModelChoice::Auto {
+            return;
+        }
+        if !result.success && result.error_kind.as_deref().is_some_and(|kind| NON_MODEL_ERRORS.contains(&kind)) {
+            log::debug!("🧠 Fallo ajeno al modelo ({:?}), no se aprende de él", result.error_kind);
+            return;
+        }
+        let delta = ModelStats {
+            trials: 1,
+            successes: u64::from(result.success),
+            total_latency_ms: result.execution_time_ms,
+            total_cost_usd: result.cost_actual,
+        };
+        let key = (task_type.clone(), complexity.clone(), result.selected_model.clone());
+        for stats in [&mut self.stats, &mut self.pending] {
+            stats.entry(key.clone()).or_default().add(&delta);
+        }
+    }
+
+    /// Elige modelo partiendo de la elección del `CostOptimizer`
+    pub fn choose(&self, task_type: &TaskType, complexity: &TaskComplexity, optimizer_choice: ModelChoice) -> ModelChoice {
+        self.choose_with_rng(task_type, complexity, optimizer_choice, &mut rand::thread_rng())
+    }
+
+    pub fn choose_with_rng<R: Rng + ?Sized>(
+        &self,
+        task_type: &TaskType,
+        complexity: &TaskComplexity,
+        optimizer_choice: ModelChoice,
+        rng: &mut R,
+    ) -> ModelChoice {
+        let observed = CANDIDATE_MODELS.iter()
+            .filter_map(|model| self.stats_for(task_type, complexity, model))
+            .any(|stats| stats.trials > 0);
+        if !observed {
+            return optimizer_choice;
+        }
+
+        if rng.gen::<f64>() < self.config.exploration_rate {
+            let explored = CANDIDATE_MODELS[rng.gen_range(0..CANDIDATE_MODELS.len())].clone();
+            log::debug!("🎲 Exploración: probando {:?}", explored);
+            return explored;
+        }
+
+        let mut best = (optimizer_choice.clone(), f64::MIN);
+        for model in CANDIDATE_MODELS.iter() {
+            let stats = self.stats_for(task_type, complexity, model).cloned().unwrap_or_default();
+            let prior = if *model == optimizer_choice { self.config.optimizer_prior } else { 0.0 };
+            let alpha = 1.0 + stats.successes as f64 + prior;
+            let beta = 1.0 + stats.trials.saturating_sub(stats.successes) as f64;
+
+            let sampled = Beta::new(alpha, beta).map(|dist| dist.sample(rng)).unwrap_or(0.0);
+            let value = sampled
+                - self.config.cost_weight * stats.mean_cost_usd()
+                - self.config.latency_weight * stats.mean_latency_ms() / 1000.0;
+            if value > best.1 {
+                best = (model.clone(), value);
+            }
+        }
+
+        if best.0 != optimizer_choice {
+            log::info!("🧠 Aprendizaje adaptativo: {:?} en lugar de {:?}", best.0, optimizer_choice);
+        }
+        best.0
+    }
+}
+
+fn read_stats(path: &Path) -> HashMap<StatsKey, ModelStats> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    match serde_json::from_str::<Vec<ModelStatsRecord>>(&content) {
+        // El fichero se puede editar a mano: nunca más éxitos que intentos
+        Ok(records) => records.into_iter()
+            .map(|mut record| {
+                record.stats.successes = record.stats.successes.min(record.stats.trials);
+                ((record.task_type, record.complexity, record.model), record.stats)
+            })
+            .collect(),
+        Err(e) => {
+            log::warn!("⚠️ Estadísticas de aprendizaje inválidas en {}: {}", path.display(), e);
+            HashMap::new()
+        }
+    }
+}
+
+fn to_records(stats: &HashMap<StatsKey, ModelStats>) -> Vec<ModelStatsRecord> {
+    let mut records: Vec<ModelStatsRecord> = stats.iter()
+        .map(|((task_type, complexity, model), stats)| ModelStatsRecord {
+            task_type: task_type.clone(),
+            complexity: complexity.clone(),
+            model: model.clone(),
+            stats: stats.clone(),
+        })
+        .collect();
+    records.sort_by_key(|r| format!("{:?}/{:?}/{:?}", r.task_type, r.complexity, r.model));
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn result(model: ModelChoice, success: bool) -> SwarmExecutionResult {
+        SwarmExecutionResult {
+            task_id: "t".to_string(),
+            success,
+            result: None,
+            thinking_result: None,
+            error: None,
+            error_kind: None,
+            selected_adapter: "gemini".to_string(),
+            selected_model: model,
+            execution_time_ms: 1000,
+            performance_score: 0.0,
+            cost_actual: 0.001,
+            cost_saved: 0.0,
+            optimization_applied: true,
+            output: None,
+            savings: None,
+            context_report: None,
+            labels: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_learner_prefers_model_that_succeeds() {
+        let mut learner = AdaptiveLearner::new(LearningConfig { exploration_rate: 0.0, ..Default::default() });
+        let task_type = TaskType::CodeGeneration;
+        let complexity = TaskComplexity::Medium;
+
+        // Sin datos se respeta la elección del optimizador
+        assert_eq!(learner.choose(&task_type, &complexity, ModelChoice::Gemini15Pro), ModelChoice::Gemini15Pro);
+
+        for _ in 0..30 {
+            learner.record(&task_type, &complexity, &result(ModelChoice::Gemini15Pro, false));
+            learner.record(&task_type, &complexity, &result(ModelChoice::Gemini15Flash, true));
+            learner.record(&task_type, &complexity, &result(ModelChoice::Gemini15ProExp, false));
+        }
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        for _ in 0..10 {
+            let choice = learner.choose_with_rng(&task_type, &complexity, ModelChoice::Gemini15Pro, &mut rng);
+            assert_eq!(choice, ModelChoice::Gemini15Flash);
+        }
+        assert!((learner.stats_for(&task_type, &complexity, &ModelChoice::Gemini15Flash).unwrap().success_rate() - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_ignores_non_model_failures_and_merges_concurrent_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model_stats.json");
+        let (task_type, complexity) = (TaskType::CodeGeneration, TaskComplexity::Medium);
+
+        let mut first = AdaptiveLearner::load_from(&path);
+        let mut second = AdaptiveLearner::load_from(&path);
+        let mut refused = result(ModelChoice::Gemini15Pro, false);
+        refused.error_kind = Some("daily_budget_exceeded".to_string());
+        first.record(&task_type, &complexity, &refused);
+        assert!(first.stats_for(&task_type, &complexity, &ModelChoice::Gemini15Pro).is_none());
+
+        let mut failed = result(ModelChoice::Gemini15Pro, false);
+        failed.error_kind = Some("invalid_response".to_string());
+        first.record(&task_type, &complexity, &failed);
+        second.record(&task_type, &complexity, &result(ModelChoice::Gemini15Pro, true));
+        first.save().unwrap();
+        second.save().unwrap();
+
+        let stats = AdaptiveLearner::load_from(&path)
+            .stats_for(&task_type, &complexity, &ModelChoice::Gemini15Pro)
+            .cloned()
+            .unwrap();
+        assert_eq!((stats.trials, stats.successes), (2, 1));
+    }
+
+    #[test]
+    fn test_corrupted_stats_are_clamped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model_stats.json");
+        let (task_type, complexity) = (TaskType::CodeGeneration, TaskComplexity::Medium);
+        let mut learner = AdaptiveLearner::load_from(&path);
+        learner.record(&task_type, &complexity, &result(ModelChoice::Gemini15Flash, true));
+        learner.save().unwrap();
+        let edited = std::fs::read_to_string(&path).unwrap().replace("\"successes\": 1", "\"successes\": 9");
+        assert!(edited.contains("\"successes\": 9"));
+        std::fs::write(&path, edited).unwrap();
+
+        let learner = AdaptiveLearner::load_from(&path);
+        let stats = learner.stats_for(&task_type, &complexity, &ModelChoice::Gemini15Flash).unwrap();
+        assert_eq!((stats.trials, stats.successes), (1, 1));
+        learner.choose(&task_type, &complexity, ModelChoice::Gemini15Pro);
+    }
+}
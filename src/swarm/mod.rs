@@ -2,29 +2,57 @@
 // SWARM ORCHESTRATOR v2.0 - Orquestador de Agentes con Herramientas Nativas
 // ============================================================================
 
+pub mod blackboard;
+pub mod bus;
+pub mod checkpoint;
+pub mod context;
+pub mod estimate;
+pub mod journal;
+pub mod learning;
+pub mod routing;
+pub mod tool_calls;
+
 use crate::{
-    CodeGenerationFlow, CodeGenerationResult, FlowError, ThinkingResult, ThinkingMode,
-    adapters::{AdapterConfig, create_adapter},
-    cost_optimizer::{CostOptimizer, TaskComplexity, analyze_task_complexity, ModelChoice, CostConstraints, PriorityLevel},
-    performance::{PerformanceMonitor, AlertThresholds, PerformanceMetrics, PerformanceReport},
-    tools::{get_registry, ToolParams, ToolResult, ToolError},
+    CodeGenerationFlow, CodeGenerationResult, FlowError, ThinkingResult, ThinkingMode, VerificationResult,
+    adapters::{cache::{self, CachingAdapter, CacheStats, ResponseCache}, AdapterConfig, create_adapter, create_model_adapter, model_adapter_name},
+    cost_optimizer::{CostOptimizer, ComplexityInput, ModelObservation, OptimizationRecommendation, SpendLedger, CostSavings, MIN_EXPECTED_OUTPUT_TOKENS, TaskComplexity, estimate_tokens, projected_cost_at, ModelChoice, CostConstraints, PriorityLevel},
+    performance::{exporter, Span, SpanKind, PerformanceMonitor, AlertThresholds, PerformanceMetrics, PerformanceReport, MeteredAdapter, SampleInbox, SeriesKind, DEFAULT_SAMPLE_INTERVAL},
+    tools::{approval::approval_gate, caller::{self, ToolCaller}, get_registry, ToolParams, ToolRegistry, ToolResult, ToolError},
 };
+use blackboard::Blackboard;
+use checkpoint::{PlanCheckpoint, ToolCallRecord};
+use context::{AdapterSummarizer, ContextBudgeter, ContextItem, ContextReport, Summarizer};
+use bus::{AgentMessage, InMemoryBus, MessageBus, MessagePayload, PLAN_TOPIC};
+use journal::{JournalEvent, JournalingAdapter, RunInput, RunJournal, ToolReplay};
+use learning::AdaptiveLearner;
+use routing::{TaskHandler, TaskOutput, TaskRouter};
+use tool_calls::{CompletedToolCall, SwarmToolCaller};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use uuid::Uuid;
 use log::{info, error};
 
+/// Identificador del agente coordinador en el bus de mensajes
+pub const QUEEN_AGENT_ID: &str = "queen";
+
+/// Intentos máximos para alcanzar `TaskRequirements::quality_threshold`
+const MAX_QUALITY_ATTEMPTS: u32 = 3;
+
 // ============================================================================
 // ESTRUCTURAS DE DATOS
 // ============================================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TaskStep {
     pub id: u32,
     pub task: String,
     #[serde(default)]
     pub tools: Vec<String>,
+    /// Parámetros de cada herramienta del paso; sin entrada se le pasa
+    /// `{"objective": task}`
+    #[serde(default)]
+    pub tool_params: HashMap<String, serde_json::Value>,
     #[serde(default)]
     pub depends_on: Vec<u32>,
     pub details: Option<String>,
@@ -36,7 +64,48 @@ pub struct ExecutionPlan {
     pub steps: Vec<TaskStep>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ExecutionPlan {
+    /// Identificador del plan: huella de su objetivo y sus pasos, de modo
+    /// que un plan editado cuenta como otro plan
+    pub fn id(&self) -> String {
+        const OFFSET: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+        let material = serde_json::to_string(self).unwrap_or_default();
+        let hash = material.bytes().fold(OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME));
+        format!("{:016x}", hash)
+    }
+
+    /// Devuelve los pasos en un orden que respeta `depends_on`
+    pub fn execution_order(&self) -> Result<Vec<&TaskStep>, FlowError> {
+        let known: HashSet<u32> = self.steps.iter().map(|step| step.id).collect();
+        for step in &self.steps {
+            if let Some(missing) = step.depends_on.iter().find(|dep| !known.contains(dep)) {
+                return Err(FlowError::InvalidPrompt(format!(
+                    "Plan inválido: el paso {} depende del paso inexistente {}", step.id, missing
+                )));
+            }
+        }
+
+        let mut done: HashSet<u32> = HashSet::new();
+        let mut order = Vec::with_capacity(self.steps.len());
+        while order.len() < self.steps.len() {
+            let ready: Vec<&TaskStep> = self.steps.iter()
+                .filter(|step| !done.contains(&step.id))
+                .filter(|step| step.depends_on.iter().all(|dep| done.contains(dep)))
+                .collect();
+            if ready.is_empty() {
+                return Err(FlowError::InvalidPrompt("Plan inválido: dependencias cíclicas".to_string()));
+            }
+            for step in ready {
+                done.insert(step.id);
+                order.push(step);
+            }
+        }
+        Ok(order)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TaskType {
     CodeGeneration,
     DataAnalysis,
@@ -56,6 +125,12 @@ pub struct Task {
     pub requirements: TaskRequirements,
     pub created_at: std::time::SystemTime,
     pub thinking_mode: Option<ThinkingMode>,
+    /// Contexto adjunto, sujeto al presupuesto de contexto
+    #[serde(default)]
+    pub context: Vec<ContextItem>,
+    /// Etiquetas de imputación del gasto (`project`, `user`, `ticket`...)
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -86,6 +161,12 @@ pub struct TaskRequirements {
     pub use_neural_optimization: bool,
     pub max_cost_usd: Option<f64>,
     pub enable_thinking: bool,
+    /// No consultar ni llenar la caché de respuestas
+    #[serde(default)]
+    pub skip_cache: bool,
+    /// Tokens de prompt permitidos; por defecto se derivan de `max_cost_usd`
+    #[serde(default)]
+    pub max_context_tokens: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -113,6 +194,8 @@ impl Default for SwarmConfig {
                 max_cost_per_request: Some(0.50),
                 daily_budget: Some(100.0),
                 priority: PriorityLevel::Medium,
+                baseline_model: ModelChoice::Gemini15Pro,
+                label_budgets: Vec::new(),
             },
             alert_thresholds: AlertThresholds::default(),
         }
@@ -126,6 +209,9 @@ pub struct SwarmExecutionResult {
     pub result: Option<CodeGenerationResult>,
     pub thinking_result: Option<ThinkingResult>,
     pub error: Option<String>,
+    /// Variante del error (`FlowError::kind`) si la tarea falló
+    #[serde(default)]
+    pub error_kind: Option<String>,
     pub selected_adapter: String,
     pub selected_model: ModelChoice,
     pub execution_time_ms: u64,
@@ -133,6 +219,18 @@ pub struct SwarmExecutionResult {
     pub cost_actual: f64,
     pub cost_saved: f64,
     pub optimization_applied: bool,
+    /// Salida interpretada por el handler del tipo de tarea
+    #[serde(default)]
+    pub output: Option<TaskOutput>,
+    /// Desglose de `cost_saved` frente a `CostConstraints.baseline_model`
+    #[serde(default)]
+    pub savings: Option<CostSavings>,
+    /// Qué se comprimió o descartó del contexto para respetar el presupuesto
+    #[serde(default)]
+    pub context_report: Option<ContextReport>,
+    /// Etiquetas efectivas a las que se imputó el gasto
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
 }
 
 pub struct SwarmOrchestrator {
@@ -145,13 +243,46 @@ pub struct SwarmOrchestrator {
     performance_monitor: PerformanceMonitor,
     total_cost_saved: f64,
     tool_usage_stats: HashMap<String, ToolUsageStats>,
+    message_bus: Arc<dyn MessageBus>,
+    blackboard: Arc<Blackboard>,
+    task_router: TaskRouter,
+    journal: Option<Arc<RunJournal>>,
+    tool_replay: Option<Arc<ToolReplay>>,
+    /// Llamadas a herramientas del paso de plan en curso
+    step_tool_calls: Option<Vec<ToolCallRecord>>,
+    /// Señales de complejidad del paso de plan en curso
+    step_signals: Option<StepSignals>,
+    learner: Option<AdaptiveLearner>,
+    /// Muestras de llamadas a adaptadores pendientes de volcar en el monitor
+    call_samples: SampleInbox,
+    /// Modelos a usar en orden, en lugar de seleccionarlos (replay)
+    model_replay: Option<VecDeque<ModelChoice>>,
+    /// Modelo fijo por clase de tarea (config.toml)
+    model_overrides: HashMap<TaskType, ModelChoice>,
+    /// Modelo fijo por clase de tarea y complejidad, por encima de `model_overrides`
+    complexity_overrides: HashMap<(TaskType, TaskComplexity), ModelChoice>,
+    /// Gasto diario para aplicar `CostConstraints.daily_budget`
+    ledger: SpendLedger,
+    /// Caché de respuestas delante de los adaptadores
+    response_cache: Option<Arc<ResponseCache>>,
+    budgeter: ContextBudgeter,
+    /// Adaptador registrado con el que se resumen los turnos antiguos
+    summarizer_adapter: Option<String>,
+    /// Etiquetas por defecto de todas las tareas de la sesión
+    labels: BTreeMap<String, String>,
+    tools: Arc<ToolRegistry>,
+    /// Llamadas que puede facturar cada adaptador por petición
+    /// (`AdapterConfig.max_attempts`, su bucle interno de refinado)
+    adapter_attempts: HashMap<String, u32>,
 }
 
 impl SwarmOrchestrator {
     /// Constructor principal
     pub fn new(config: SwarmConfig) -> Self {
         let cost_optimizer = CostOptimizer::new();
-        let performance_monitor = PerformanceMonitor::with_thresholds(config.alert_thresholds.clone());
+        let mut performance_monitor = PerformanceMonitor::with_thresholds(config.alert_thresholds.clone());
+        performance_monitor.start_sampling(DEFAULT_SAMPLE_INTERVAL);
+        let learner = config.enable_adaptive_learning.then(AdaptiveLearner::load_or_default);
         
         Self {
             config,
@@ -163,9 +294,275 @@ impl SwarmOrchestrator {
             performance_monitor,
             total_cost_saved: 0.0,
             tool_usage_stats: HashMap::new(),
+            message_bus: Arc::new(InMemoryBus::new()),
+            blackboard: Arc::new(Blackboard::new()),
+            task_router: TaskRouter::new(),
+            journal: None,
+            tool_replay: None,
+            step_tool_calls: None,
+            step_signals: None,
+            learner,
+            call_samples: SampleInbox::default(),
+            model_replay: None,
+            model_overrides: HashMap::new(),
+            complexity_overrides: HashMap::new(),
+            ledger: SpendLedger::new(),
+            response_cache: None,
+            budgeter: ContextBudgeter::new(),
+            summarizer_adapter: None,
+            labels: BTreeMap::new(),
+            tools: get_registry(),
+            adapter_attempts: HashMap::new(),
+        }
+    }
+
+    /// Sustituye el aprendizaje adaptativo (p. ej. por uno sin persistencia)
+    pub fn set_learner(&mut self, learner: Option<AdaptiveLearner>) {
+        self.learner = learner;
+    }
+
+    pub fn learner(&self) -> Option<&AdaptiveLearner> {
+        self.learner.as_ref()
+    }
+
+    /// Fija los modelos de las próximas tareas, en orden (replay)
+    pub fn set_model_replay(&mut self, models: Vec<ModelChoice>) {
+        self.model_replay = Some(models.into());
+    }
+
+    /// Fija el modelo de una clase de tarea, por encima del aprendizaje
+    pub fn set_model_overrides(&mut self, overrides: HashMap<TaskType, ModelChoice>) {
+        self.model_overrides = overrides;
+    }
+
+    /// Fija el modelo de una clase de tarea para una complejidad concreta
+    pub fn set_complexity_overrides(&mut self, overrides: HashMap<(TaskType, TaskComplexity), ModelChoice>) {
+        self.complexity_overrides = overrides;
+    }
+
+    /// Modelo fijado para la tarea, si lo hay: primero por complejidad, luego por clase
+    fn overridden_model(&self, task_type: &TaskType, complexity: &TaskComplexity) -> Option<ModelChoice> {
+        self.complexity_overrides.get(&(task_type.clone(), complexity.clone()))
+            .or_else(|| self.model_overrides.get(task_type))
+            .cloned()
+    }
+
+    /// Fija el modelo de un tipo de tarea solo para esta ejecución
+    pub fn override_model(&mut self, task_type: TaskType, model: ModelChoice) {
+        self.model_overrides.insert(task_type, model);
+    }
+
+    /// Frente de Pareto costo/latencia/éxito de los modelos para una tarea
+    pub fn recommend_models(&self, task: &Task) -> Vec<OptimizationRecommendation> {
+        let input = self.complexity_input(task, StepSignals::default());
+        self.cost_optimizer.get_recommendations(&input, |complexity, model| {
+            self.learner.as_ref()
+                .and_then(|learner| learner.stats_for(&task.task_type, complexity, model))
+                .map(|stats| ModelObservation {
+                    trials: stats.trials,
+                    successes: stats.successes,
+                    mean_latency_ms: stats.mean_latency_ms(),
+                })
+        })
+    }
+
+    fn complexity_input(&self, task: &Task, signals: StepSignals) -> ComplexityInput {
+        let input = ComplexityInput::new(&task.description)
+            .with_context_chars(signals.context_chars.max(context::context_chars(&task.context)))
+            .with_plan_steps(signals.plan_steps)
+            .with_tool_count(signals.tool_count)
+            .with_history(self.learner.as_ref().and_then(|learner| learner.outcome(&task.task_type)));
+        match &task.requirements.preferred_language {
+            Some(language) => input.with_language(language),
+            None => input,
         }
     }
 
+    /// Comprime el contexto de la tarea para que el prompt quepa en su
+    /// presupuesto: `max_context_tokens` o lo que permite `max_cost_usd` con
+    /// `model`. Sin contexto o sin presupuesto no hace nada.
+    async fn fit_context(&self, task: &mut Task, handler: &dyn TaskHandler, model: &ModelChoice) -> Option<ContextReport> {
+        if task.context.is_empty() {
+            return None;
+        }
+        let budget = task.requirements.max_context_tokens.or_else(|| {
+            task.requirements.max_cost_usd
+                .or(self.config.cost_constraints.max_cost_per_request)
+                .map(|max_cost| context::token_budget(model, max_cost))
+        })?;
+
+        let mut items = std::mem::take(&mut task.context);
+        let base_tokens = estimate_tokens(&handler.build_prompt(task));
+        let report = self.budgeter.fit(base_tokens, &mut items, budget).await;
+        task.context = items;
+        Some(report)
+    }
+
+    /// Sustituye el registro compartido de herramientas (p. ej. por uno sin
+    /// ciertas categorías, construido con `ToolRegistry::builder()`)
+    pub fn set_tool_registry(&mut self, tools: Arc<ToolRegistry>) {
+        self.tools = tools;
+    }
+
+    /// Etiquetas que heredan todas las tareas; las de la tarea tienen prioridad
+    pub fn set_labels(&mut self, labels: BTreeMap<String, String>) {
+        self.labels = labels;
+    }
+
+    /// Etiquetas de una tarea junto con las heredadas del orquestador
+    fn task_labels(&self, task: &Task) -> BTreeMap<String, String> {
+        let mut labels = self.labels.clone();
+        labels.extend(task.labels.clone());
+        labels
+    }
+
+    /// Presupuesto diario más restrictivo para unas etiquetas: el global o
+    /// el de alguna etiqueta. Devuelve el límite, lo gastado hoy contra él y
+    /// su ámbito, para el journal.
+    fn daily_limit(&self, labels: &BTreeMap<String, String>) -> (Option<f64>, f64, String) {
+        let mut limit = (self.config.cost_constraints.daily_budget, self.ledger.spent_today(), "diario".to_string());
+        for budget in self.config.cost_constraints.label_budgets.iter().filter(|budget| budget.applies_to(labels)) {
+            let spent = self.ledger.spent_today_with_label(&budget.label, &budget.value);
+            let tighter = limit.0.is_none_or(|daily| budget.daily_usd - spent < daily - limit.1);
+            if tighter {
+                limit = (Some(budget.daily_usd), spent, format!("{}={}", budget.label, budget.value));
+            }
+        }
+        limit
+    }
+
+    /// Sustituye el ledger en memoria (p. ej. por el persistente de `~/.enjambre`)
+    pub fn set_ledger(&mut self, ledger: SpendLedger) {
+        self.ledger = ledger;
+    }
+
+    pub fn ledger(&self) -> &SpendLedger {
+        &self.ledger
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Continúa una sesión existente (p. ej. al reanudar un plan). Debe
+    /// llamarse antes de `enable_journal` para escribir en el mismo directorio.
+    pub fn set_session_id(&mut self, session_id: &str) {
+        self.session_id = session_id.to_string();
+    }
+
+    /// Pone una caché de respuestas delante de los adaptadores. Debe llamarse
+    /// antes de registrarlos; los ya registrados no la usan.
+    pub fn set_response_cache(&mut self, cache: Arc<ResponseCache>) {
+        self.response_cache = Some(cache);
+    }
+
+    /// Modelo barato con el que resumir los turnos antiguos del contexto
+    pub fn set_summarizer(&mut self, summarizer: Arc<dyn Summarizer>) {
+        self.budgeter = ContextBudgeter::new().with_summarizer(summarizer);
+        self.summarizer_adapter = None;
+    }
+
+    /// Resume con un adaptador ya registrado, con sus decoradores: el gasto
+    /// se mide y se imputa al presupuesto, y las llamadas quedan en el journal
+    pub fn summarize_with(&mut self, adapter_name: &str, model: ModelChoice) {
+        let Some(adapter) = self.adapters.get(adapter_name) else {
+            log::warn!("⚠️ Sin resumidor de contexto: no hay adaptador '{}'", adapter_name);
+            return;
+        };
+        self.set_summarizer(Arc::new(AdapterSummarizer::new(Arc::clone(adapter), model)));
+        self.summarizer_adapter = Some(adapter_name.to_string());
+    }
+
+    /// Aciertos y fallos de la caché de respuestas, si hay
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.response_cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Registra un adaptador ya construido (p. ej. uno de reproducción o de pruebas)
+    pub fn register_adapter(&mut self, name: &str, adapter: Arc<dyn CodeGenerationFlow>) {
+        let adapter: Arc<dyn CodeGenerationFlow> =
+            Arc::new(MeteredAdapter::new(name, adapter, Arc::clone(&self.call_samples)));
+        // Un acierto no llega al adaptador medido: no cuenta como llamada
+        let adapter: Arc<dyn CodeGenerationFlow> = match &self.response_cache {
+            Some(cache) => Arc::new(CachingAdapter::new(name, adapter, Arc::clone(cache))),
+            None => adapter,
+        };
+        let adapter = match &self.journal {
+            Some(journal) => Arc::new(JournalingAdapter::new(name, adapter, Arc::clone(journal))),
+            None => adapter,
+        };
+        self.adapters.insert(name.to_string(), adapter);
+    }
+
+    /// Activa el journal de la ejecución; los adaptadores ya registrados
+    /// pasan a grabar sus prompts y respuestas
+    pub fn enable_journal(&mut self, journal: Arc<RunJournal>) {
+        self.journal = Some(Arc::clone(&journal));
+        for (name, adapter) in self.adapters.iter_mut() {
+            *adapter = Arc::new(JournalingAdapter::new(name, Arc::clone(adapter), Arc::clone(&journal)));
+        }
+        // El resumidor guarda el adaptador sin journal: se vuelve a tomar
+        if let Some(name) = self.summarizer_adapter.clone() {
+            self.summarize_with(&name, ModelChoice::Gemini15Flash);
+        }
+    }
+
+    pub fn journal(&self) -> Option<Arc<RunJournal>> {
+        self.journal.clone()
+    }
+
+    /// Sirve las llamadas a herramientas desde un journal en lugar de ejecutarlas
+    pub fn set_tool_replay(&mut self, replay: ToolReplay) {
+        self.tool_replay = Some(Arc::new(replay));
+    }
+
+    /// Marca el inicio de la ejecución en el journal
+    pub fn record_run_started(&self, input: &RunInput, replay_of: Option<&str>) {
+        self.record(JournalEvent::RunStarted {
+            session_id: self.session_id.clone(),
+            config: Box::new(self.config.clone()),
+            input: Box::new(input.clone()),
+            replay_of: replay_of.map(|id| id.to_string()),
+        });
+    }
+
+    /// Marca el final de la ejecución en el journal
+    pub fn record_run_finished(&self, success: bool, duration_ms: u64) {
+        self.record(JournalEvent::RunFinished {
+            success,
+            duration_ms,
+            total_cost: self.performance_history.iter().map(|r| r.cost_actual).sum(),
+        });
+    }
+
+    fn record(&self, event: JournalEvent) {
+        if let Some(journal) = &self.journal {
+            journal.record(event);
+        }
+    }
+
+    /// Registra un handler para tareas `TaskType::CustomTask(name)`
+    pub fn register_task_handler(&mut self, name: &str, handler: Arc<dyn TaskHandler>) {
+        self.task_router.register_custom(name, handler);
+    }
+
+    pub fn task_router_mut(&mut self) -> &mut TaskRouter {
+        &mut self.task_router
+    }
+
+    /// Sustituye el bus de mensajes (p. ej. por un transporte multi-proceso)
+    pub fn set_message_bus(&mut self, bus: Arc<dyn MessageBus>) {
+        self.message_bus = bus;
+    }
+
+    pub fn message_bus(&self) -> Arc<dyn MessageBus> {
+        Arc::clone(&self.message_bus)
+    }
+
+    pub fn blackboard(&self) -> Arc<Blackboard> {
+        Arc::clone(&self.blackboard)
+    }
+
     pub async fn create_execution_plan(&self, objective: &str) -> Result<ExecutionPlan, FlowError> {
         let _available_tools = self.list_available_tools().join(", ");
         
@@ -175,13 +572,18 @@ impl SwarmOrchestrator {
                 id: 1,
                 task: format!("Analizar objetivo: {}", objective),
                 tools: vec!["safla_memory".to_string()],
+                tool_params: HashMap::from([(
+                    "safla_memory".to_string(),
+                    serde_json::json!({ "operation": "retrieve_memories", "query": objective }),
+                )]),
                 depends_on: vec![],
                 details: Some("Análisis inicial del objetivo usando SAFLA".to_string()),
             },
             TaskStep {
                 id: 2,
                 task: "Ejecutar plan con ruv-swarm".to_string(),
-                tools: vec!["ruv_swarm".to_string()],
+                tools: vec!["ruv_swarm_orchestrate".to_string()],
+                tool_params: HashMap::new(),
                 depends_on: vec![1],
                 details: Some("Delegar ejecución a ruv-swarm".to_string()),
             },
@@ -194,10 +596,28 @@ impl SwarmOrchestrator {
     }
 
     pub async fn initialize(&mut self, adapter_configs: HashMap<String, AdapterConfig>) -> Result<(), FlowError> {
+        let summarizer = adapter_configs.contains_key("gemini")
+            .then(|| model_adapter_name("gemini", &ModelChoice::Gemini15Flash))
+            .flatten();
         for (name, config) in adapter_configs {
+            let attempts = config.max_attempts.max(1);
+            // Un adaptador por modelo, para que degradar o fijar el modelo
+            // cambie de verdad el modelo que sirve la llamada
+            for model in [ModelChoice::Gemini15Flash, ModelChoice::Gemini15Pro, ModelChoice::Gemini15ProExp] {
+                let Some(model_name) = model_adapter_name(&name, &model) else { continue };
+                match create_model_adapter(&name, config.clone(), &model).await {
+                    Ok(Some(adapter)) => {
+                        self.register_adapter(&model_name, adapter);
+                        self.adapter_attempts.insert(model_name, attempts);
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::warn!("⚠️ Sin adaptador dedicado para {:?}: {}", model, e),
+                }
+            }
             match create_adapter(&name, config).await {
                 Ok(adapter) => {
-                    self.adapters.insert(name, adapter);
+                    self.register_adapter(&name, adapter);
+                    self.adapter_attempts.insert(name, attempts);
                 }
                 Err(e) => {
                     error!("Error inicializando adaptador {}: {}", name, e);
@@ -209,76 +629,700 @@ impl SwarmOrchestrator {
         if self.adapters.is_empty() {
             return Err(FlowError::AdapterNotFound("No se pudo inicializar ningún adaptador".to_string()));
         }
+
+        // Los turnos antiguos del contexto se resumen con el modelo más barato
+        if let Some(name) = summarizer {
+            self.summarize_with(&name, ModelChoice::Gemini15Flash);
+        }
         
         Ok(())
     }
 
-    pub async fn execute_task(&mut self, task: Task) -> SwarmExecutionResult {
+    pub async fn execute_task(&mut self, mut task: Task) -> SwarmExecutionResult {
         let start_time = std::time::Instant::now();
         let task_id = task.id.clone();
+        task.labels = self.task_labels(&task);
+        let labels = self.ledger.resolve_labels(&task.labels);
+        self.record(JournalEvent::TaskStarted { task: task.clone() });
+        exporter::task_started();
+        let mut span = Span::start(&format!("task {:?}", task.task_type), SpanKind::Task);
+        span.set_attribute("task.id", task_id.as_str());
+        span.set_attribute("task.type", format!("{:?}", task.task_type));
+        for (key, value) in &labels {
+            span.set_attribute(&format!("label.{}", key), value.as_str());
+        }
         
-        // Análisis y optimización simplificados
-        let task_complexity = analyze_task_complexity(&task.description);
-        let selected_model = self.cost_optimizer.optimize_model_selection(
-            task_complexity,
+        // Complejidad a partir del prompt, el paso de plan y el historial
+        let signals = self.step_signals.take().unwrap_or_default();
+        let complexity_input = self.complexity_input(&task, signals);
+        let assessment = self.cost_optimizer.assess_complexity(&complexity_input);
+        let task_complexity = assessment.complexity.clone();
+        self.record(JournalEvent::Decision {
+            task_id: Some(task_id.clone()),
+            kind: "complexity".to_string(),
+            detail: format!(
+                "{:?} (puntuación {:.2}, confianza {:.0}%): {}",
+                task_complexity, assessment.score, assessment.confidence * 100.0, assessment.explanation()
+            ),
+        });
+        let optimizer_choice = self.cost_optimizer.optimize_model_selection(
+            task_complexity.clone(),
             &self.config.cost_constraints,
         );
+        let replayed_model = self.model_replay.as_mut().and_then(|models| models.pop_front());
+        let overridden_model = self.overridden_model(&task.task_type, &task_complexity);
+        let chosen_model = match (replayed_model.or(overridden_model), &self.learner) {
+            (Some(model), _) => model,
+            (None, Some(learner)) => learner.choose(&task.task_type, &task_complexity, optimizer_choice),
+            (None, None) => optimizer_choice,
+        };
+        
+        // Enrutar según el tipo de tarea
+        let handler = self.task_router.route(&task.task_type);
+
+        // Ajustar el contexto al presupuesto de tokens del modelo elegido
+        let context_report = self.fit_context(&mut task, handler.as_ref(), &chosen_model).await;
+        if let Some(report) = context_report.as_ref().filter(|report| !report.actions.is_empty()) {
+            log::info!("✂️ Contexto ajustado: {}", report.describe());
+            self.record(JournalEvent::Decision {
+                task_id: Some(task_id.clone()),
+                kind: "context".to_string(),
+                detail: report.describe(),
+            });
+        }
+
+        // Presupuesto: proyectar el costo del prompt y degradar o rechazar
+        let input_tokens = estimate_tokens(&handler.build_prompt(&task));
+        let (daily_budget, spent_today, budget_scope) = self.daily_limit(&labels);
+        let budget = self.cost_optimizer.enforce_budget(
+            chosen_model.clone(),
+            |model| self.serving_price(model),
+            input_tokens,
+            task.requirements.max_cost_usd.or(self.config.cost_constraints.max_cost_per_request),
+            daily_budget,
+            spent_today,
+        );
+        let (selected_model, downgraded_from) = match &budget {
+            Ok(decision) => (decision.model.clone(), decision.downgraded_from.clone()),
+            Err(_) => (chosen_model, None),
+        };
+        match (&budget, &downgraded_from) {
+            (Ok(decision), Some(original)) => self.record(JournalEvent::Decision {
+                task_id: Some(task_id.clone()),
+                kind: "budget".to_string(),
+                detail: format!(
+                    "modelo {:?} -> {:?}: costo proyectado ${:.4}, gastado hoy ${:.4} ({})",
+                    original, decision.model, decision.projected_cost_usd, spent_today, budget_scope
+                ),
+            }),
+            (Err(e), _) => {
+                log::warn!("💸 Tarea rechazada por presupuesto: {}", e);
+                self.record(JournalEvent::Decision {
+                    task_id: Some(task_id.clone()),
+                    kind: "budget".to_string(),
+                    detail: format!("rechazada: {} (gastado hoy ${:.4}, {})", e, spent_today, budget_scope),
+                });
+            }
+            _ => {}
+        }
         
         let selected_adapter = self.select_adapter_for_model(&selected_model);
+        self.record(JournalEvent::Decision {
+            task_id: Some(task_id.clone()),
+            kind: "model_selection".to_string(),
+            detail: format!("complejidad {:?} -> modelo {:?} en '{}'", task_complexity, selected_model, selected_adapter),
+        });
         
-        // Ejecutar tarea
-        let result = if let Some(adapter) = self.adapters.get(&selected_adapter) {
-            adapter.execute(&task.description).await
-        } else {
-            Err(FlowError::AdapterNotFound(selected_adapter.clone()))
+        // Ejecutar tarea respetando el tiempo máximo de la tarea. Lo facturado
+        // se acumula fuera del futuro para no perderlo si falla o se aborta.
+        let billed = std::sync::Mutex::new(0.0);
+        // Las herramientas que pida el modelo pasan por el mismo camino que `execute_tool`
+        let tool_caller = Arc::new(self.tool_caller());
+        let result = match (budget, self.adapters.get(&selected_adapter).cloned()) {
+            (Err(e), _) => {
+                self.ledger.record_refusal(&labels);
+                Err(e)
+            }
+            (Ok(_), Some(adapter)) => {
+                // Solo se ofrecen al modelo las herramientas del tipo de tarea
+                let tools = self.get_function_schemas_for_task(&task.task_type);
+                let generation = span.scope(caller::scope(
+                    Arc::clone(&tool_caller) as Arc<dyn ToolCaller>,
+                    cache::scope(
+                        task.requirements.skip_cache,
+                        Self::generate_with_requirements(&task, handler, &tools, adapter, self.journal.clone(), daily_budget.map(|budget| (budget, spent_today)), &billed),
+                    ),
+                ));
+                match task.requirements.max_execution_time_ms {
+                    Some(max_ms) => tokio::time::timeout(std::time::Duration::from_millis(max_ms), generation)
+                        .await
+                        .unwrap_or(Err(FlowError::TimeoutError)),
+                    None => generation.await,
+                }
+            }
+            (Ok(_), None) => Err(FlowError::AdapterNotFound(selected_adapter.clone())),
         };
+        self.account_tool_calls(tool_caller.take_completed());
         
         let execution_time = start_time.elapsed().as_millis() as u64;
         
         // Crear resultado
-        match result {
-            Ok(code_result) => {
+        let execution_result = match result {
+            Ok(mut outcome) => {
+                // El modelo que sirvió la llamada, según el propio adaptador
+                let selected_model = outcome.code_result.model_used.as_deref()
+                    .or(outcome.code_result.cost_estimate.as_ref().map(|estimate| estimate.model_used.as_str()))
+                    .and_then(ModelChoice::from_label)
+                    .unwrap_or(selected_model);
+                let cost_limit = task.requirements.max_cost_usd
+                    .or(self.config.cost_constraints.max_cost_per_request);
+                let performance_score = compute_performance_score(
+                    execution_time,
+                    self.performance_monitor.thresholds().response_time_ms,
+                    outcome.quality,
+                    outcome.cost,
+                    cost_limit,
+                );
+                // Ahorro frente al modelo de referencia con los mismos tokens
+                let compressed = context_report.as_ref().map(ContextReport::saved_tokens).unwrap_or(0);
+                let summary_cost = context_report.as_ref().map(|report| report.summary_cost_usd).unwrap_or(0.0);
+                if let Some(estimate) = outcome.code_result.cost_estimate.as_mut() {
+                    estimate.compressed_tokens = compressed;
+                }
+                let (input, output, cache_hit) = outcome.code_result.cost_estimate.as_ref()
+                    .map(|estimate| (estimate.input_tokens, estimate.output_tokens, estimate.cache_hit))
+                    .unwrap_or((input_tokens, input_tokens.max(MIN_EXPECTED_OUTPUT_TOKENS), false));
+                let mut savings = CostSavings::compute(
+                    &self.config.cost_constraints.baseline_model,
+                    self.adapter_price(&selected_adapter, &selected_model),
+                    input,
+                    output,
+                    compressed,
+                    cache_hit,
+                    outcome.cost,
+                );
+                // Los resúmenes se pagan: cuentan como gasto y restan al ahorro por compresión
+                savings.compression_usd -= summary_cost;
+                savings.actual_cost_usd += summary_cost;
                 SwarmExecutionResult {
                     task_id,
                     success: true,
-                    result: Some(code_result),
+                    result: Some(outcome.code_result),
                     thinking_result: None,
                     error: None,
+                    error_kind: None,
                     selected_adapter,
                     selected_model,
                     execution_time_ms: execution_time,
-                    performance_score: 0.85,
-                    cost_actual: 0.01,
-                    cost_saved: 0.0,
+                    performance_score,
+                    cost_actual: outcome.cost + summary_cost,
+                    cost_saved: savings.total_usd(),
                     optimization_applied: true,
+                    output: Some(outcome.output),
+                    savings: Some(savings),
+                    context_report,
+                    labels: labels.clone(),
                 }
             }
             Err(e) => {
+                exporter::error(&e);
                 SwarmExecutionResult {
                     task_id,
                     success: false,
                     result: None,
                     thinking_result: None,
                     error: Some(e.to_string()),
+                    error_kind: Some(e.kind().to_string()),
                     selected_adapter,
                     selected_model,
                     execution_time_ms: execution_time,
                     performance_score: 0.0,
-                    cost_actual: 0.0,
+                    // Los intentos fallidos o abortados también se facturan
+                    cost_actual: billed.lock().map(|billed| *billed).unwrap_or(0.0),
                     cost_saved: 0.0,
                     optimization_applied: false,
+                    output: None,
+                    savings: None,
+                    context_report,
+                    labels: labels.clone(),
+                }
+            }
+        };
+
+        self.record(JournalEvent::TaskFinished { result: Box::new(execution_result.clone()) });
+        span.set_attribute("complexity", format!("{:?}", task_complexity));
+        span.set_attribute("complexity.confidence", assessment.confidence);
+        span.set_attribute("model", format!("{:?}", execution_result.selected_model));
+        span.set_attribute("adapter", execution_result.selected_adapter.as_str());
+        span.set_attribute("cost_usd", execution_result.cost_actual);
+        span.set_attribute("cost_saved_usd", execution_result.cost_saved);
+        if let Some(result) = &execution_result.result {
+            span.set_attribute("cache.hit", result.cache_hit);
+        }
+        span.set_attribute("performance_score", execution_result.performance_score);
+        if let Some(estimate) = execution_result.result.as_ref().and_then(|r| r.cost_estimate.as_ref()) {
+            span.set_attribute("tokens.total", estimate.input_tokens + estimate.output_tokens);
+        }
+        if let Some(error) = &execution_result.error {
+            span.set_error(error.as_str());
+        }
+        exporter::task_finished(
+            &format!("{:?}", task.task_type),
+            &format!("{:?}", execution_result.selected_model),
+            execution_result.success,
+            std::time::Duration::from_millis(execution_time),
+            execution_result.cost_actual,
+            &labels,
+        );
+        self.performance_monitor.drain_samples(&self.call_samples);
+        self.performance_monitor.record_series(
+            SeriesKind::Model,
+            &format!("{:?}", execution_result.selected_model),
+            std::time::Duration::from_millis(execution_time),
+            execution_result.success,
+        );
+        self.performance_monitor.record_request(
+            std::time::Duration::from_millis(execution_time),
+            execution_result.success,
+        );
+        self.total_cost_saved += execution_result.cost_saved;
+        if execution_result.result.is_some() || execution_result.cost_actual > 0.0 {
+            let (input, output) = execution_result.result.as_ref()
+                .and_then(|result| result.cost_estimate.as_ref())
+                .map(|estimate| (estimate.input_tokens, estimate.output_tokens))
+                .unwrap_or((input_tokens, 0));
+            self.ledger.record(&labels, execution_result.cost_actual, execution_result.savings.as_ref(), input, output);
+        }
+        if let Err(e) = self.ledger.save() {
+            log::warn!("⚠️ No se pudo guardar el ledger de gasto: {}", e);
+        }
+        if let Some(learner) = self.learner.as_mut() {
+            learner.record(&task.task_type, &task_complexity, &execution_result);
+            if let Err(e) = learner.save() {
+                log::warn!("⚠️ No se pudieron guardar las estadísticas de aprendizaje: {}", e);
+            }
+        }
+        self.performance_history.push(execution_result.clone());
+        execution_result
+    }
+
+    /// Genera, interpreta y verifica una tarea aplicando sus `TaskRequirements`:
+    /// límite de costo (previo y acumulado), umbral de calidad con regeneración
+    /// guiada por el verificador y lenguaje preferido. Devuelve el costo gastado,
+    /// que además se va acumulando en `billed` aunque la generación falle.
+    /// `daily` es el presupuesto diario aplicable y lo gastado hoy antes de la tarea.
+    async fn generate_with_requirements(
+        task: &Task,
+        handler: Arc<dyn TaskHandler>,
+        tools: &[serde_json::Value],
+        adapter: Arc<dyn CodeGenerationFlow>,
+        journal: Option<Arc<RunJournal>>,
+        daily: Option<(f64, f64)>,
+        billed: &std::sync::Mutex<f64>,
+    ) -> Result<GenerationOutcome, FlowError> {
+        let requirements = &task.requirements;
+        let decide = |kind: &str, detail: String| {
+            if let Some(journal) = &journal {
+                journal.record(JournalEvent::Decision {
+                    task_id: Some(task.id.clone()),
+                    kind: kind.to_string(),
+                    detail,
+                });
+            }
+        };
+        let prompt = handler.build_prompt(task);
+        let capabilities = adapter.get_capabilities();
+        let price = (capabilities.cost_per_million_input, capabilities.cost_per_million_output);
+
+        let mut spent = 0.0;
+        let mut current_prompt = prompt.clone();
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+
+            // Chequeo previo a cada intento: el prompt crece con el feedback y
+            // cada regeneración se factura, así que se proyecta sobre lo gastado
+            let projected = projected_cost_at(price, estimate_tokens(&current_prompt));
+            if let Some(max_cost) = requirements.max_cost_usd {
+                if spent + projected > max_cost {
+                    log::warn!("💸 Costo proyectado ${:.4} supera el límite ${:.4}", spent + projected, max_cost);
+                    decide("cost_limit", format!("costo proyectado ${:.4} > límite ${:.4} (intento {})", spent + projected, max_cost, attempts));
+                    return Err(FlowError::CostLimitExceeded(max_cost));
+                }
+            }
+            if let Some((budget, spent_today)) = daily {
+                if spent_today + spent + projected > budget {
+                    decide("budget", format!(
+                        "intento {} rechazado: ${:.4} proyectados, ${:.4} gastados hoy de ${:.2}",
+                        attempts, projected, spent_today + spent, budget
+                    ));
+                    return Err(FlowError::DailyBudgetExceeded(budget));
+                }
+            }
+            let mut code_result = adapter.execute_with_tools(&current_prompt, tools).await?;
+
+            let cost = code_result.cost_estimate.as_ref().map(|c| c.estimated_cost_usd).unwrap_or(0.0);
+            spent += cost;
+            if let Ok(mut billed) = billed.lock() {
+                *billed += cost;
+            }
+            if let Some(max_cost) = requirements.max_cost_usd {
+                if spent > max_cost {
+                    decide("cost_limit", format!("costo acumulado ${:.4} > límite ${:.4}", spent, max_cost));
+                    return Err(FlowError::CostLimitExceeded(max_cost));
+                }
+            }
+
+            let output = handler.parse_output(&code_result.code, task)?;
+            if let TaskOutput::Code { language, .. } = &output {
+                code_result.language = language.clone();
+            }
+
+            if !requirements.enable_verification {
+                return Ok(GenerationOutcome { code_result, output, cost: spent, quality: None });
+            }
+
+            let mut verification = handler.verify(&output, task).unwrap_or_else(|| match &output {
+                TaskOutput::Code { code, .. } => adapter.verify_code(code),
+                _ => adapter.verify_code(&code_result.code),
+            });
+            check_preferred_language(&mut verification, &output, task);
+            code_result.verification_passed = verification.is_valid;
+
+            let quality = Some(if verification.is_valid { verification.quality_score } else { 0.0 });
+            let Some(threshold) = requirements.quality_threshold else {
+                return Ok(GenerationOutcome { code_result, output, cost: spent, quality });
+            };
+            if verification.is_valid && verification.quality_score >= threshold {
+                return Ok(GenerationOutcome { code_result, output, cost: spent, quality });
+            }
+            if attempts >= MAX_QUALITY_ATTEMPTS {
+                decide("quality_threshold", format!("calidad {:.2} < {:.2} tras {} intentos", verification.quality_score, threshold, attempts));
+                return Err(FlowError::MaxAttemptsReached(attempts));
+            }
+            decide("regenerate", format!("calidad {:.2} < {:.2}", verification.quality_score, threshold));
+
+            log::info!(
+                "🔁 Calidad {:.2} < {:.2}, regenerando (intento {}/{})",
+                verification.quality_score, threshold, attempts + 1, MAX_QUALITY_ATTEMPTS
+            );
+            current_prompt = build_feedback_prompt(&prompt, &code_result.code, &verification, threshold);
+        }
+    }
+
+    /// Ejecuta un plan paso a paso. Cada paso lo resuelve un agente worker que
+    /// recibe su asignación por el bus, lee del blackboard las salidas de los
+    /// pasos de los que depende y publica su resultado en el tópico del plan.
+    pub async fn execute_plan(&mut self, plan: &ExecutionPlan) -> Result<PlanExecutionResult, FlowError> {
+        let mut checkpoint = PlanCheckpoint::new(&self.session_id, plan);
+        checkpoint.labels = self.labels.clone();
+        self.run_plan(checkpoint).await
+    }
+
+    /// Reanuda un plan desde su checkpoint: los pasos completados no se
+    /// vuelven a ejecutar y sus salidas se restauran en el blackboard. Las
+    /// etiquetas guardadas en el checkpoint tienen prioridad sobre las
+    /// configuradas.
+    pub async fn resume_plan(&mut self, mut checkpoint: PlanCheckpoint) -> Result<PlanExecutionResult, FlowError> {
+        self.labels.extend(checkpoint.labels.clone());
+        checkpoint.labels = self.labels.clone();
+        self.record(JournalEvent::RunResumed {
+            session_id: self.session_id.clone(),
+            completed_steps: checkpoint.completed.iter().map(|step| step.execution.step_id).collect(),
+            spent_usd: checkpoint.spent_usd,
+        });
+        self.run_plan(checkpoint).await
+    }
+
+    async fn run_plan(&mut self, mut checkpoint: PlanCheckpoint) -> Result<PlanExecutionResult, FlowError> {
+        let start_time = std::time::Instant::now();
+        let plan = checkpoint.plan.clone();
+        let order: Vec<TaskStep> = plan.execution_order()?.into_iter().cloned().collect();
+        let bus = self.message_bus();
+        let plan_id = plan.id();
+        checkpoint.session_id = self.session_id.clone();
+        let mut plan_span = Span::start("plan", SpanKind::Plan);
+        plan_span.set_attribute("objective", plan.original_objective.as_str());
+        plan_span.set_attribute("session_id", self.session_id.as_str());
+        plan_span.set_attribute("steps", order.len() as u64);
+
+        self.join_bus(QUEEN_AGENT_ID).await;
+        // Mensajes del bus ya copiados al journal (los de planes anteriores no)
+        let mut journaled_messages = bus.message_log().len();
+
+        let mut steps = Vec::with_capacity(order.len());
+        let mut resumed_steps = Vec::new();
+        let mut success = true;
+
+        for step in order {
+            if let Some(done) = checkpoint.completed_step(step.id) {
+                info!("⏭️ Paso {} restaurado desde el checkpoint", step.id);
+                let execution = done.execution.clone();
+                let output = execution.result.result.as_ref()
+                    .map(|r| r.code.clone())
+                    .unwrap_or_default();
+                if self.blackboard.version(step.id) == 0 {
+                    if let Err(e) = self.blackboard.write(
+                        step.id,
+                        serde_json::json!({ "success": true, "output": output }),
+                        &execution.agent_id,
+                        0,
+                    ) {
+                        log::warn!("⚠️ No se pudo restaurar el paso {} en el blackboard: {}", step.id, e);
+                    }
                 }
+                resumed_steps.push(step.id);
+                steps.push(execution);
+                continue;
             }
+
+            let agent_id = format!("worker-{}", step.id);
+            self.join_bus(&agent_id).await;
+            self.step_tool_calls = Some(Vec::new());
+
+            self.post(AgentMessage::direct(QUEEN_AGENT_ID, &agent_id, MessagePayload::TaskAssignment {
+                step_id: step.id,
+                description: step.task.clone(),
+            }).with_correlation_id(&self.session_id)).await;
+
+            // El worker toma su asignación del buzón; sin ella el paso no se
+            // puede ejecutar y el plan no puede seguir
+            let assignment = match bus.receive(&agent_id).await {
+                Ok(Some(message)) => message,
+                Ok(None) => {
+                    return Err(FlowError::InvalidResponse(format!(
+                        "El worker {} no recibió la asignación del paso {}", agent_id, step.id
+                    )));
+                }
+                Err(e) => {
+                    return Err(FlowError::InvalidResponse(format!("Worker {} sin buzón: {}", agent_id, e)));
+                }
+            };
+
+            let mut step_task = self.build_step_task(&step, &assignment.payload);
+            approval_gate()
+                .check_plan_step(&plan_id, step.id, &step.task, serde_json::json!({
+                    "step_id": step.id,
+                    "agent": agent_id,
+                    "depends_on": step.depends_on,
+                    "prompt": format!("{}{}", step_task.description, context::render(&step_task.context)),
+                }))
+                .await
+                .map_err(|e| FlowError::ApprovalDenied(e.to_string()))?;
+            info!("🐝 {} ejecutando paso {}: {}", agent_id, step.id, step.task);
+            let tool_context = self.run_step_tools(&step).await;
+            step_task.context.extend(tool_context);
+            self.record(JournalEvent::Decision {
+                task_id: None,
+                kind: "plan_step".to_string(),
+                detail: format!("paso {} asignado a {}", step.id, agent_id),
+            });
+            let mut step_span = Span::start_with_parent(
+                &format!("step {}", step.id),
+                SpanKind::PlanStep,
+                plan_span.context().as_ref(),
+            );
+            step_span.set_attribute("step.id", step.id);
+            step_span.set_attribute("agent", agent_id.as_str());
+            self.step_signals = Some(StepSignals {
+                plan_steps: plan.steps.len(),
+                tool_count: step.tools.len(),
+                context_chars: context::context_chars(&step_task.context),
+            });
+            let result = step_span.scope(self.execute_task(step_task)).await;
+            if let Some(error) = &result.error {
+                step_span.set_error(error.as_str());
+            }
+            drop(step_span);
+
+            let output = result.result.as_ref()
+                .map(|r| r.code.clone())
+                .or_else(|| result.error.clone())
+                .unwrap_or_default();
+
+            let expected_version = self.blackboard.version(step.id);
+            let blackboard_version = match self.blackboard.write(
+                step.id,
+                serde_json::json!({ "success": result.success, "output": output }),
+                &agent_id,
+                expected_version,
+            ) {
+                Ok(version) => version,
+                Err(e) => {
+                    log::warn!("⚠️ No se pudo escribir el paso {} en el blackboard: {}", step.id, e);
+                    expected_version
+                }
+            };
+
+            self.post(AgentMessage::broadcast(&agent_id, PLAN_TOPIC, MessagePayload::StepResult {
+                step_id: step.id,
+                success: result.success,
+                output,
+            }).with_correlation_id(&self.session_id)).await;
+            self.post(AgentMessage::broadcast(&agent_id, PLAN_TOPIC, MessagePayload::BlackboardUpdate {
+                step_id: step.id,
+                version: blackboard_version,
+            }).with_correlation_id(&self.session_id)).await;
+            self.leave_bus(&agent_id).await;
+            // La reina consume el progreso publicado para que su buzón no crezca
+            if let Ok(updates) = bus.drain(QUEEN_AGENT_ID).await {
+                log::debug!("👑 La reina recibió {} mensajes del paso {}", updates.len(), step.id);
+            }
+            journaled_messages = self.journal_bus_messages(journaled_messages);
+
+            let step_failed = !result.success;
+            let execution = StepExecutionResult { step_id: step.id, agent_id, result, blackboard_version };
+
+            if step_failed {
+                self.step_tool_calls = None;
+                checkpoint.record_failed_step(execution.result.cost_actual);
+                self.save_checkpoint(&checkpoint);
+                steps.push(execution);
+                error!("❌ Paso {} falló, se detiene el plan", step.id);
+                success = false;
+                break;
+            }
+
+            let tool_calls = self.step_tool_calls.take().unwrap_or_default();
+            checkpoint.complete_step(&step, execution.clone(), tool_calls);
+            self.save_checkpoint(&checkpoint);
+            steps.push(execution);
+        }
+
+        plan_span.set_attribute("cost_usd", checkpoint.spent_usd);
+        if !success {
+            plan_span.set_error("un paso del plan falló");
+        }
+
+        Ok(PlanExecutionResult {
+            objective: plan.original_objective.clone(),
+            success,
+            steps,
+            execution_time_ms: start_time.elapsed().as_millis() as u64,
+            resumed_steps,
+            spent_usd: checkpoint.spent_usd,
+        })
+    }
+
+    /// Copia al journal los mensajes del bus a partir de `from`; devuelve
+    /// hasta dónde quedan copiados
+    fn journal_bus_messages(&self, from: usize) -> usize {
+        let log = self.message_bus.message_log();
+        for message in log.iter().skip(from) {
+            self.record(JournalEvent::BusMessage { message: message.clone() });
         }
+        log.len()
     }
 
+    /// Guarda el checkpoint en el directorio del journal, si está activo
+    fn save_checkpoint(&self, checkpoint: &PlanCheckpoint) {
+        let Some(journal) = &self.journal else {
+            return;
+        };
+        match checkpoint.save_in(journal.dir()) {
+            Ok(()) => log::debug!("💾 Checkpoint guardado ({} pasos)", checkpoint.completed.len()),
+            Err(e) => log::warn!("⚠️ No se pudo guardar el checkpoint: {}", e),
+        }
+    }
+
+    /// Tarea de un paso: su descripción, con el tipo que se infiere de ella,
+    /// y, como contexto, las salidas de los pasos de los que depende
+    fn build_step_task(&self, step: &TaskStep, assignment: &MessagePayload) -> Task {
+        let mut description = match assignment {
+            MessagePayload::TaskAssignment { description, .. } => description.clone(),
+            _ => step.task.clone(),
+        };
+        if let Some(details) = &step.details {
+            description.push_str(&format!("\n\nDetalles: {}", details));
+        }
+
+        let mut builder = TaskBuilder::new(routing::infer_task_type(&step.task), description);
+        for dependency in &step.depends_on {
+            if let Some(entry) = self.blackboard.read(*dependency) {
+                let output = entry.value.get("output").and_then(|v| v.as_str()).unwrap_or_default();
+                builder = builder.with_context(ContextItem::step_output(*dependency, output));
+            }
+        }
+        builder.build()
+    }
+
+    /// El worker llama a las herramientas de su paso antes de generar; lo que
+    /// devuelven entra como contexto. Un fallo se registra pero no detiene el paso.
+    async fn run_step_tools(&mut self, step: &TaskStep) -> Vec<ContextItem> {
+        let mut context = Vec::new();
+        for tool in &step.tools {
+            let params = step.tool_params.get(tool).cloned()
+                .unwrap_or_else(|| serde_json::json!({ "objective": step.task }));
+            let result = match self.create_tool_params(params) {
+                Ok(params) => self.execute_tool(tool, params).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(result) => context.push(ContextItem::note(&format!("Herramienta {}: {}\n{}", tool, result.message, result.data))),
+                Err(e) => log::warn!("⚠️ Herramienta '{}' del paso {} falló: {}", tool, step.id, e),
+            }
+        }
+        context
+    }
+
+    /// Registra un agente en el bus y lo suscribe al tópico del plan
+    async fn join_bus(&self, agent_id: &str) {
+        if self.message_bus.register_agent(agent_id).await.is_ok() {
+            if let Err(e) = self.message_bus.subscribe(agent_id, PLAN_TOPIC).await {
+                log::warn!("⚠️ No se pudo suscribir {} al plan: {}", agent_id, e);
+            }
+        }
+    }
+
+    /// Da de baja a un worker al terminar su paso: sus mensajes pendientes
+    /// quedan en el log del bus y no siguen acumulándose en su buzón
+    async fn leave_bus(&self, agent_id: &str) {
+        match self.message_bus.unregister_agent(agent_id).await {
+            Ok(pending) if !pending.is_empty() => {
+                log::debug!("📭 {} deja el bus con {} mensajes sin leer", agent_id, pending.len());
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("⚠️ No se pudo dar de baja a {} del bus: {}", agent_id, e),
+        }
+    }
+
+    async fn post(&self, message: AgentMessage) {
+        if let Err(e) = self.message_bus.send(message).await {
+            log::warn!("⚠️ Error enviando mensaje por el bus: {}", e);
+        }
+    }
+
+    /// Adaptador dedicado al modelo si está registrado; si no, el genérico
     fn select_adapter_for_model(&self, model: &ModelChoice) -> String {
-        match model {
-            ModelChoice::Gemini15Pro | ModelChoice::Gemini15Flash | ModelChoice::Gemini15ProExp => "gemini".to_string(),
-            ModelChoice::Auto => self.config.default_adapter.clone(),
+        match model_adapter_name("gemini", model) {
+            Some(name) if self.adapters.contains_key(&name) => name,
+            Some(_) => "gemini".to_string(),
+            None => self.config.default_adapter.clone(),
         }
     }
 
+    /// Llamadas que puede facturar por petición el adaptador que serviría el modelo
+    fn serving_attempts(&self, model: &ModelChoice) -> u32 {
+        self.adapter_attempts.get(&self.select_adapter_for_model(model)).copied().unwrap_or(1)
+    }
+
+    /// Precio por millón de tokens (entrada, salida) del adaptador que
+    /// serviría el modelo; sin adaptador, el precio de lista del modelo
+    fn serving_price(&self, model: &ModelChoice) -> (f64, f64) {
+        self.adapter_price(&self.select_adapter_for_model(model), model)
+    }
+
+    /// Precio por millón de tokens de un adaptador registrado
+    fn adapter_price(&self, adapter: &str, model: &ModelChoice) -> (f64, f64) {
+        self.adapters.get(adapter)
+            .map(|adapter| {
+                let capabilities = adapter.get_capabilities();
+                (capabilities.cost_per_million_input, capabilities.cost_per_million_output)
+            })
+            .unwrap_or_else(|| model.price_per_million())
+    }
+
     pub fn get_performance_metrics(&self) -> &PerformanceMetrics {
         self.performance_monitor.get_metrics()
     }
@@ -292,6 +1336,7 @@ impl SwarmOrchestrator {
         
         OptimizationStats {
             total_cost_saved: self.total_cost_saved,
+            lifetime_cost_saved: self.ledger.total_saved(),
             total_tasks_executed: self.performance_history.len(),
             average_cost_per_task: if !self.performance_history.is_empty() {
                 self.performance_history.iter().map(|r| r.cost_actual).sum::<f64>() / self.performance_history.len() as f64
@@ -304,7 +1349,7 @@ impl SwarmOrchestrator {
                 current_speed_improvement: 1.0,
                 performance_gap: (0.848 - performance_metrics.success_rate).max(0.0f64),
             },
-            recommendations: self.cost_optimizer.get_recommendations("tarea general"),
+            recommendations: self.recommend_models(&TaskBuilder::code_generation("tarea general")),
         }
     }
 
@@ -325,38 +1370,62 @@ impl SwarmOrchestrator {
 
     // Métodos de herramientas
     pub fn get_function_schemas(&self) -> Vec<serde_json::Value> {
-        let registry = get_registry();
-        registry.get_function_schemas()
+        self.tools.get_function_schemas()
+    }
+
+    /// Esquemas de función limitados a las herramientas del tipo de tarea
+    pub fn get_function_schemas_for_task(&self, task_type: &TaskType) -> Vec<serde_json::Value> {
+        let tools = self.task_router.route(task_type).tools();
+        self.get_function_schemas()
+            .into_iter()
+            .filter(|schema| {
+                schema.get("name")
+                    .and_then(|name| name.as_str())
+                    .map(|name| tools.iter().any(|tool| tool == name))
+                    .unwrap_or(false)
+            })
+            .collect()
     }
 
     pub async fn execute_tool(&mut self, tool_name: &str, params: ToolParams) -> Result<ToolResult, ToolError> {
-        let start_time = std::time::Instant::now();
-        
-        let registry = get_registry();
-        let result = registry.execute(tool_name, params).await;
-        
-        let execution_time = start_time.elapsed();
-        
-        // Actualizar estadísticas
-        let stats = self.tool_usage_stats.entry(tool_name.to_string())
-            .or_insert_with(|| ToolUsageStats {
-                total_calls: 0,
-                successful_calls: 0,
-                total_time: std::time::Duration::from_secs(0),
-                last_used: std::time::SystemTime::now(),
-            });
-        
-        stats.total_calls += 1;
-        stats.total_time += execution_time;
-        stats.last_used = std::time::SystemTime::now();
-        
-        if result.is_ok() {
-            stats.successful_calls += 1;
-        }
-        
+        let caller = self.tool_caller();
+        let result = caller.call(tool_name, params).await;
+        self.account_tool_calls(caller.take_completed());
         result
     }
 
+    /// Ejecutor de herramientas con el registro, el replay y el journal de la sesión
+    fn tool_caller(&self) -> SwarmToolCaller {
+        SwarmToolCaller::new(Arc::clone(&self.tools), self.tool_replay.clone(), self.journal.clone())
+    }
+
+    /// Vuelca llamadas terminadas en el monitor, el paso de plan en curso y
+    /// las estadísticas de uso
+    fn account_tool_calls(&mut self, calls: Vec<CompletedToolCall>) {
+        for CompletedToolCall { record, duration } in calls {
+            let success = record.result.is_ok();
+            self.performance_monitor.record_series(SeriesKind::Tool, &record.tool, duration, success);
+
+            let stats = self.tool_usage_stats.entry(record.tool.clone())
+                .or_insert_with(|| ToolUsageStats {
+                    total_calls: 0,
+                    successful_calls: 0,
+                    total_time: std::time::Duration::from_secs(0),
+                    last_used: std::time::SystemTime::now(),
+                });
+            stats.total_calls += 1;
+            stats.total_time += duration;
+            stats.last_used = std::time::SystemTime::now();
+            if success {
+                stats.successful_calls += 1;
+            }
+
+            if let Some(calls) = self.step_tool_calls.as_mut() {
+                calls.push(record);
+            }
+        }
+    }
+
     pub async fn execute_tools_parallel(&mut self, tool_calls: Vec<(String, ToolParams)>) -> Vec<Result<ToolResult, ToolError>> {
         let mut results = Vec::new();
         
@@ -373,13 +1442,11 @@ impl SwarmOrchestrator {
     }
 
     pub fn list_available_tools(&self) -> Vec<String> {
-        let registry = get_registry();
-        registry.list_all().into_iter().map(|s| s.to_string()).collect()
+        self.tools.list_all().into_iter().map(|s| s.to_string()).collect()
     }
 
     pub fn list_tools_by_category(&self, category: &crate::tools::ToolCategory) -> Vec<String> {
-        let registry = get_registry();
-        registry.list_by_category(category).into_iter().map(|s| s.to_string()).collect()
+        self.tools.list_by_category(category).into_iter().map(|s| s.to_string()).collect()
     }
 
     pub fn create_tool_params(&self, json_params: serde_json::Value) -> Result<ToolParams, ToolError> {
@@ -399,12 +1466,41 @@ impl SwarmOrchestrator {
 // FUNCIONES AUXILIARES
 // ============================================================================
 
+/// Añade al verificador un error si el código no está en el lenguaje preferido
+fn check_preferred_language(verification: &mut VerificationResult, output: &TaskOutput, task: &Task) {
+    let (Some(preferred), TaskOutput::Code { language, .. }) = (&task.requirements.preferred_language, output) else {
+        return;
+    };
+    if language != "unknown" && !language.eq_ignore_ascii_case(preferred) {
+        verification.is_valid = false;
+        verification.quality_score = 0.0;
+        verification.errors.push(format!(
+            "El código está en '{}' pero se pidió '{}'", language, preferred
+        ));
+    }
+}
+
+/// Prompt de regeneración con el feedback del verificador
+fn build_feedback_prompt(prompt: &str, previous: &str, verification: &VerificationResult, threshold: f64) -> String {
+    let mut feedback = format!(
+        "{}\n\nTu respuesta anterior no alcanzó la calidad requerida (puntuación {:.2}, mínimo {:.2}).",
+        prompt, verification.quality_score, threshold
+    );
+    for error in &verification.errors {
+        feedback.push_str(&format!("\n- Error: {}", error));
+    }
+    for warning in &verification.warnings {
+        feedback.push_str(&format!("\n- Advertencia: {}", warning));
+    }
+    feedback.push_str(&format!("\n\nRespuesta anterior:\n{}\n\nGenera una versión corregida.", previous));
+    feedback
+}
+
 fn extract_json_from_response(response: &str) -> String {
     if let Some(start) = response.find("```json") {
-        if let Some(end) = response[start..].find("```") {
-            let json_start = start + 7;
-            let json_end = start + end;
-            return response[json_start..json_end].trim().to_string();
+        let json_start = start + 7;
+        if let Some(end) = response[json_start..].find("```") {
+            return response[json_start..json_start + end].trim().to_string();
         }
     }
     
@@ -423,12 +1519,87 @@ fn extract_json_from_response(response: &str) -> String {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptimizationStats {
+    /// Ahorro de esta sesión frente al modelo de referencia
     pub total_cost_saved: f64,
+    /// Ahorro acumulado en el ledger, de esta y de anteriores ejecuciones
+    #[serde(default)]
+    pub lifetime_cost_saved: f64,
     pub total_tasks_executed: usize,
     pub average_cost_per_task: f64,
     pub success_rate: f64,
     pub claude_flow_comparison: ClaudeFlowComparison,
-    pub recommendations: Vec<crate::cost_optimizer::OptimizationRecommendation>,
+    pub recommendations: Vec<OptimizationRecommendation>,
+}
+
+/// Señales de complejidad que aporta un paso de plan a su tarea
+#[derive(Debug, Clone, Copy, Default)]
+struct StepSignals {
+    plan_steps: usize,
+    tool_count: usize,
+    context_chars: usize,
+}
+
+/// Resultado interno de generar una tarea
+struct GenerationOutcome {
+    code_result: CodeGenerationResult,
+    output: TaskOutput,
+    /// Costo gastado en USD
+    cost: f64,
+    /// Calidad verificada (None si la verificación está desactivada)
+    quality: Option<f64>,
+}
+
+/// Puntuación 0.0 - 1.0 de una tarea completada, a partir de su latencia
+/// frente al umbral de alertas, la calidad verificada y el costo frente al
+/// límite (o a una referencia de $0.01 si no hay límite).
+pub fn compute_performance_score(
+    execution_time_ms: u64,
+    response_time_threshold_ms: u64,
+    quality: Option<f64>,
+    cost_usd: f64,
+    cost_limit_usd: Option<f64>,
+) -> f64 {
+    const REFERENCE_COST_USD: f64 = 0.01;
+
+    let threshold = response_time_threshold_ms.max(1) as f64;
+    let latency = execution_time_ms as f64;
+    let latency_score = if latency <= threshold {
+        1.0 - 0.5 * latency / threshold
+    } else {
+        0.5 * threshold / latency
+    };
+
+    // Sin verificación no hay evidencia de calidad: puntuación neutra
+    let quality_score = quality.unwrap_or(0.5).clamp(0.0, 1.0);
+
+    let cost_score = match cost_limit_usd {
+        Some(limit) if limit > 0.0 => (1.0 - cost_usd / limit).clamp(0.0, 1.0),
+        _ => REFERENCE_COST_USD / (REFERENCE_COST_USD + cost_usd.max(0.0)),
+    };
+
+    0.4 * quality_score + 0.35 * latency_score + 0.25 * cost_score
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepExecutionResult {
+    pub step_id: u32,
+    pub agent_id: String,
+    pub result: SwarmExecutionResult,
+    pub blackboard_version: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanExecutionResult {
+    pub objective: String,
+    pub success: bool,
+    pub steps: Vec<StepExecutionResult>,
+    pub execution_time_ms: u64,
+    /// Pasos restaurados desde un checkpoint en lugar de ejecutarse
+    #[serde(default)]
+    pub resumed_steps: Vec<u32>,
+    /// Costo total del plan, incluidos los pasos restaurados
+    #[serde(default)]
+    pub spent_usd: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -459,6 +1630,8 @@ pub struct TaskBuilder {
     priority: TaskPriority,
     requirements: TaskRequirements,
     thinking_mode: Option<ThinkingMode>,
+    context: Vec<ContextItem>,
+    labels: BTreeMap<String, String>,
 }
 
 impl TaskBuilder {
@@ -475,8 +1648,12 @@ impl TaskBuilder {
                 use_neural_optimization: true,
                 max_cost_usd: None,
                 enable_thinking: false,
+                skip_cache: false,
+                max_context_tokens: None,
             },
             thinking_mode: None,
+            context: Vec::new(),
+            labels: BTreeMap::new(),
         }
     }
 
@@ -496,6 +1673,48 @@ impl TaskBuilder {
         self
     }
 
+    pub fn with_timeout_ms(mut self, max_execution_time_ms: u64) -> Self {
+        self.requirements.max_execution_time_ms = Some(max_execution_time_ms);
+        self
+    }
+
+    pub fn with_quality_threshold(mut self, threshold: f64) -> Self {
+        self.requirements.quality_threshold = Some(threshold);
+        self
+    }
+
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.requirements.preferred_language = Some(language.to_string());
+        self
+    }
+
+    pub fn with_verification(mut self, enable: bool) -> Self {
+        self.requirements.enable_verification = enable;
+        self
+    }
+
+    pub fn with_cache(mut self, enable: bool) -> Self {
+        self.requirements.skip_cache = !enable;
+        self
+    }
+
+    pub fn with_context(mut self, item: ContextItem) -> Self {
+        self.context.push(item);
+        self
+    }
+
+    /// Etiqueta de imputación del gasto, p. ej. `("ticket", "OPS-42")`
+    pub fn with_label(mut self, key: &str, value: &str) -> Self {
+        self.labels.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Tokens de prompt permitidos, en lugar de derivarlos de `max_cost_usd`
+    pub fn with_context_budget(mut self, max_tokens: u32) -> Self {
+        self.requirements.max_context_tokens = Some(max_tokens);
+        self
+    }
+
     pub fn build(self) -> Task {
         Task {
             id: Uuid::new_v4().to_string(),
@@ -505,6 +1724,8 @@ impl TaskBuilder {
             requirements: self.requirements,
             created_at: std::time::SystemTime::now(),
             thinking_mode: self.thinking_mode,
+            context: self.context,
+            labels: self.labels,
         }
     }
 
@@ -519,6 +1740,26 @@ impl TaskBuilder {
             .build()
     }
 
+    pub fn data_analysis(description: &str) -> Task {
+        Self::new(TaskType::DataAnalysis, description.to_string()).build()
+    }
+
+    pub fn forecasting(description: &str) -> Task {
+        Self::new(TaskType::Forecasting, description.to_string()).build()
+    }
+
+    pub fn text_processing(description: &str) -> Task {
+        Self::new(TaskType::TextProcessing, description.to_string()).build()
+    }
+
+    pub fn classification(description: &str) -> Task {
+        Self::new(TaskType::Classification, description.to_string()).build()
+    }
+
+    pub fn custom(name: &str, description: &str) -> Task {
+        Self::new(TaskType::CustomTask(name.to_string()), description.to_string()).build()
+    }
+
     pub fn budget_task(description: &str, max_cost: f64) -> Task {
         Self::new(TaskType::CodeGeneration, description.to_string())
             .with_max_cost(max_cost)
@@ -555,4 +1796,466 @@ impl ToolUsageStats {
             std::time::Duration::from_secs(0)
         }
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AdapterCapabilities;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Adaptador de pruebas que devuelve respuestas predefinidas en orden
+    struct ScriptedAdapter {
+        responses: Mutex<Vec<String>>,
+        delay_ms: u64,
+        /// Modelo que informa servir y su precio por millón de tokens
+        model: Option<ModelChoice>,
+        price: f64,
+        /// Nombres de las herramientas ofrecidas en cada llamada
+        offered_tools: Arc<Mutex<Vec<Vec<String>>>>,
+        /// Llamada a función que hace el "modelo" antes de responder
+        function_call: Option<(String, serde_json::Value)>,
+    }
+
+    impl ScriptedAdapter {
+        fn new(responses: &[&str]) -> Self {
+            Self {
+                responses: Mutex::new(responses.iter().rev().map(|r| r.to_string()).collect()),
+                delay_ms: 0,
+                model: None,
+                price: 1.0,
+                offered_tools: Arc::new(Mutex::new(Vec::new())),
+                function_call: None,
+            }
+        }
+
+        fn serving(model: ModelChoice, price: f64, responses: &[&str]) -> Self {
+            Self { model: Some(model), price, ..Self::new(responses) }
+        }
+    }
+
+    #[async_trait]
+    impl CodeGenerationFlow for ScriptedAdapter {
+        async fn execute_with_tools(&self, problem_description: &str, tools: &[serde_json::Value]) -> Result<CodeGenerationResult, FlowError> {
+            let names = tools.iter().filter_map(|tool| tool["name"].as_str().map(str::to_string)).collect();
+            self.offered_tools.lock().unwrap().push(names);
+            if let Some((name, args)) = &self.function_call {
+                let params = caller::params_from_args(args).map_err(|e| FlowError::ApiError(e.to_string()))?;
+                caller::call_tool(name, params).await.map_err(|e| FlowError::ApiError(e.to_string()))?;
+            }
+            self.execute(problem_description).await
+        }
+
+        async fn execute(&self, problem_description: &str) -> Result<CodeGenerationResult, FlowError> {
+            tokio::time::sleep(std::time::Duration::from_millis(self.delay_ms)).await;
+            let code = self.responses.lock().unwrap().pop()
+                .ok_or_else(|| FlowError::ApiError("sin respuestas".to_string()))?;
+            let cost_estimate = self.model.as_ref().map(|model| {
+                let (input_tokens, output_tokens) = (estimate_tokens(problem_description), estimate_tokens(&code));
+                crate::CostEstimate {
+                    input_tokens,
+                    output_tokens,
+                    estimated_cost_usd: self.price * (input_tokens + output_tokens) as f64 / 1_000_000.0,
+                    model_used: format!("{:?}", model),
+                    cache_hit: false,
+                    compressed_tokens: 0,
+                }
+            });
+            Ok(CodeGenerationResult {
+                code,
+                language: "unknown".to_string(),
+                confidence_score: 1.0,
+                attempts_made: 1,
+                execution_time_ms: 0,
+                verification_passed: true,
+                model_used: cost_estimate.as_ref().map(|estimate| estimate.model_used.clone()),
+                cost_estimate,
+                metrics: Default::default(),
+                attempt_history: Vec::new(),
+                cache_hit: false,
+            })
+        }
+
+        fn verify_code(&self, code: &str) -> VerificationResult {
+            let is_valid = code.contains("fn ");
+            VerificationResult {
+                is_valid,
+                compilation_success: is_valid,
+                tests_passed: is_valid,
+                quality_score: if is_valid { 1.0 } else { 0.2 },
+                errors: if is_valid { Vec::new() } else { vec!["falta una función".to_string()] },
+                warnings: Vec::new(),
+            }
+        }
+
+        fn get_capabilities(&self) -> AdapterCapabilities {
+            AdapterCapabilities {
+                name: "scripted".to_string(),
+                version: "0".to_string(),
+                supported_languages: vec!["rust".to_string()],
+                max_context_tokens: 1_000_000,
+                supports_function_calling: false,
+                supports_code_execution: false,
+                supports_thinking: false,
+                cost_per_million_input: self.price,
+                cost_per_million_output: self.price,
+            }
+        }
+    }
+
+    fn test_config() -> SwarmConfig {
+        SwarmConfig { enable_adaptive_learning: false, ..SwarmConfig::default() }
+    }
+
+    /// Ejecuta sin pedir aprobaciones, con un gate propio de la tarea
+    async fn without_approvals<F: std::future::Future>(future: F) -> F::Output {
+        let gate = crate::tools::approval::ApprovalGate::new(
+            crate::tools::approval::ApprovalMode::Bypass,
+            crate::tools::RiskLevel::Critical,
+        );
+        crate::tools::approval::scope(gate, future).await
+    }
+
+    fn orchestrator_with(adapter: ScriptedAdapter) -> SwarmOrchestrator {
+        let mut orchestrator = SwarmOrchestrator::new(test_config());
+        orchestrator.register_adapter("gemini", Arc::new(adapter));
+        orchestrator
+    }
+
+    #[tokio::test]
+    async fn test_quality_threshold_regenerates_with_feedback() {
+        let mut orchestrator = orchestrator_with(ScriptedAdapter::new(&["todo", "```rust\nfn main() {}\n```"]));
+        let task = TaskBuilder::new(TaskType::CodeGeneration, "hola mundo".to_string())
+            .with_quality_threshold(0.9)
+            .build();
+
+        let result = orchestrator.execute_task(task).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.result.unwrap().language, "rust");
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_serves_repeated_prompts() {
+        let mut orchestrator = SwarmOrchestrator::new(test_config());
+        let cache = Arc::new(ResponseCache::in_memory(Default::default()));
+        orchestrator.set_response_cache(Arc::clone(&cache));
+        orchestrator.register_adapter("gemini", Arc::new(ScriptedAdapter::new(&["fn main() {}"])));
+        let task = || TaskBuilder::new(TaskType::CodeGeneration, "hola".to_string()).with_quality_threshold(0.9);
+
+        let first = orchestrator.execute_task(task().build()).await;
+        assert!(!first.result.unwrap().cache_hit);
+        // El adaptador ya no tiene respuestas: la segunda sale de la caché y se verifica igual
+        let second = orchestrator.execute_task(task().build()).await;
+        assert!(second.success, "{:?}", second.error);
+        assert!(second.result.unwrap().cache_hit);
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 1, stores: 1, evictions: 0 });
+
+        let skipped = orchestrator.execute_task(task().with_cache(false).build()).await;
+        assert!(!skipped.success);
+        assert_eq!(cache.stats().hits, 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_cost_limits_are_enforced() {
+        let mut adapter = ScriptedAdapter::new(&["fn main() {}"]);
+        adapter.delay_ms = 200;
+        let mut orchestrator = orchestrator_with(adapter);
+        let task = TaskBuilder::new(TaskType::CodeGeneration, "lento".to_string())
+            .with_timeout_ms(10)
+            .build();
+        let result = orchestrator.execute_task(task).await;
+        assert_eq!(result.error.as_deref(), Some("Timeout alcanzado"));
+
+        let mut orchestrator = orchestrator_with(ScriptedAdapter::new(&["fn main() {}"]));
+        let task = TaskBuilder::new(TaskType::CodeGeneration, "x".repeat(10_000))
+            .with_max_cost(0.000_001)
+            .build();
+        let result = orchestrator.execute_task(task).await;
+        assert!(result.error.unwrap().starts_with("Límite de costo excedido"));
+    }
+
+    #[tokio::test]
+    async fn test_budget_downgrade_runs_on_the_cheaper_model_adapter() {
+        let mut orchestrator = SwarmOrchestrator::new(test_config());
+        orchestrator.register_adapter("gemini", Arc::new(ScriptedAdapter::serving(ModelChoice::Gemini15Pro, 10.0, &["fn pro() {}"])));
+        orchestrator.register_adapter("gemini-flash", Arc::new(ScriptedAdapter::serving(ModelChoice::Gemini15Flash, 0.1, &["fn flash() {}"])));
+        orchestrator.override_model(TaskType::CodeGeneration, ModelChoice::Gemini15Pro);
+
+        // 1k tokens: ~$0.013 en el adaptador de Pro, ~$0.0001 en el de Flash
+        let task = TaskBuilder::new(TaskType::CodeGeneration, "x".repeat(4_000))
+            .with_max_cost(0.001)
+            .build();
+        let result = orchestrator.execute_task(task).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.selected_adapter, "gemini-flash");
+        assert_eq!(result.selected_model, ModelChoice::Gemini15Flash);
+        assert!(result.result.unwrap().code.contains("fn flash"));
+        assert!(result.cost_actual > 0.0 && result.cost_actual < 0.001);
+    }
+
+    #[tokio::test]
+    async fn test_failed_quality_attempts_are_billed() {
+        let mut orchestrator = orchestrator_with(ScriptedAdapter::serving(ModelChoice::Gemini15Pro, 1.0, &["todo", "todo", "todo"]));
+        let task = TaskBuilder::new(TaskType::CodeGeneration, "hola mundo".to_string())
+            .with_quality_threshold(0.9)
+            .build();
+
+        let result = orchestrator.execute_task(task).await;
+        assert!(!result.success);
+        assert!(result.cost_actual > 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_quality_retries_stop_at_the_daily_budget() {
+        let mut config = test_config();
+        config.cost_constraints.daily_budget = Some(0.5);
+        let mut orchestrator = SwarmOrchestrator::new(config);
+        orchestrator.register_adapter("gemini", Arc::new(ScriptedAdapter::serving(ModelChoice::Gemini15Pro, 1_000.0, &["todo", "todo", "todo"])));
+        let task = TaskBuilder::new(TaskType::CodeGeneration, "hola mundo".to_string())
+            .with_quality_threshold(0.9)
+            .build();
+
+        // El primer intento cabe en el presupuesto; los reintentos ya no
+        let result = orchestrator.execute_task(task).await;
+        assert!(result.error.unwrap().starts_with("Presupuesto diario"), "{:?}", result.cost_actual);
+        assert!(result.cost_actual > 0.0 && result.cost_actual <= 0.5);
+    }
+
+    /// Herramienta de pruebas que devuelve sus parámetros
+    struct EchoTool;
+
+    #[async_trait]
+    impl crate::tools::Tool for EchoTool {
+        fn name(&self) -> &str {
+            "eco"
+        }
+
+        fn description(&self) -> &str {
+            "Devuelve sus parámetros"
+        }
+
+        fn parameters_schema(&self) -> serde_json::Value {
+            crate::tools::create_parameters_schema(serde_json::json!({ "objective": { "type": "string" } }), vec!["objective"])
+        }
+
+        fn category(&self) -> crate::tools::ToolCategory {
+            crate::tools::ToolCategory::Utils
+        }
+
+        async fn execute(&self, params: ToolParams) -> Result<ToolResult, ToolError> {
+            let objective = params.get::<String>("objective")?;
+            Ok(ToolResult::success(objective, "eco".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_step_tools_run_through_the_orchestrator() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tools = ToolRegistry::new();
+        tools.register(EchoTool);
+        let mut orchestrator = orchestrator_with(ScriptedAdapter::new(&["fn uno() {}"]));
+        orchestrator.set_tool_registry(Arc::new(tools));
+        orchestrator.enable_journal(Arc::new(journal::RunJournal::create_in(dir.path()).unwrap()));
+        let plan = ExecutionPlan {
+            original_objective: "eco".to_string(),
+            steps: vec![TaskStep {
+                id: 1,
+                task: "uno".to_string(),
+                tools: vec!["eco".to_string()],
+                tool_params: HashMap::new(),
+                depends_on: vec![],
+                details: None,
+            }],
+        };
+
+        let result = without_approvals(orchestrator.execute_plan(&plan)).await.unwrap();
+        assert!(result.success);
+        assert_eq!(orchestrator.get_tool_usage_stats()["eco"].successful_calls, 1);
+
+        let entries = journal::RunJournal::load_from(&dir.path().join(journal::JOURNAL_FILE)).unwrap();
+        assert!(entries.iter().any(|entry| matches!(&entry.event, JournalEvent::ToolCall { tool, .. } if tool == "eco")));
+        let checkpoint = PlanCheckpoint::load_from(&dir.path().join(checkpoint::CHECKPOINT_FILE)).unwrap();
+        assert_eq!(checkpoint.completed[0].tool_calls.len(), 1);
+        assert_eq!(checkpoint.completed[0].tool_calls[0].tool, "eco");
+    }
+
+    #[tokio::test]
+    async fn test_summarizer_goes_through_the_registered_adapter() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut orchestrator = SwarmOrchestrator::new(test_config());
+        orchestrator.register_adapter("gemini-flash", Arc::new(ScriptedAdapter::new(&["resumen"])));
+        orchestrator.summarize_with("gemini-flash", ModelChoice::Gemini15Flash);
+        orchestrator.enable_journal(Arc::new(journal::RunJournal::create_in(dir.path()).unwrap()));
+
+        let mut items: Vec<ContextItem> = (0..8)
+            .map(|turn| ContextItem::turn("user", &format!("turno {} {}", turn, "texto ".repeat(200))))
+            .collect();
+        orchestrator.budgeter.fit(0, &mut items, 1200).await;
+
+        let entries = journal::RunJournal::load_from(&dir.path().join(journal::JOURNAL_FILE)).unwrap();
+        assert!(entries.iter().any(|entry| matches!(&entry.event, JournalEvent::Response { adapter, .. } if adapter == "gemini-flash")));
+        assert_eq!(orchestrator.call_samples.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_model_function_calls_run_through_the_orchestrator() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tools = ToolRegistry::new();
+        tools.register(EchoTool);
+        let adapter = ScriptedAdapter {
+            function_call: Some(("eco".to_string(), serde_json::json!({ "objective": "hola" }))),
+            ..ScriptedAdapter::new(&["fn main() {}"])
+        };
+        let mut orchestrator = orchestrator_with(adapter);
+        orchestrator.set_tool_registry(Arc::new(tools));
+        orchestrator.enable_journal(Arc::new(journal::RunJournal::create_in(dir.path()).unwrap()));
+
+        let task = TaskBuilder::code_generation("hola mundo");
+        let result = without_approvals(orchestrator.execute_task(task)).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(orchestrator.get_tool_usage_stats()["eco"].successful_calls, 1);
+        let entries = journal::RunJournal::load_from(&dir.path().join(journal::JOURNAL_FILE)).unwrap();
+        assert!(entries.iter().any(|entry| matches!(&entry.event, JournalEvent::ToolCall { tool, .. } if tool == "eco")));
+    }
+
+    #[tokio::test]
+    async fn test_journal_replays_recorded_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut orchestrator = SwarmOrchestrator::new(test_config());
+        orchestrator.enable_journal(Arc::new(journal::RunJournal::create_in(dir.path()).unwrap()));
+        orchestrator.register_adapter("gemini", Arc::new(ScriptedAdapter::new(&["todo", "fn main() {}"])));
+
+        let task = TaskBuilder::new(TaskType::CodeGeneration, "hola".to_string())
+            .with_quality_threshold(0.9)
+            .build();
+        orchestrator.record_run_started(&RunInput::Task(task.clone()), None);
+        let recorded = orchestrator.execute_task(task).await;
+        orchestrator.record_run_finished(recorded.success, recorded.execution_time_ms);
+
+        let entries = journal::RunJournal::load_from(&dir.path().join(journal::JOURNAL_FILE)).unwrap();
+        let report = journal::replay_run(&entries, false).await.unwrap();
+        assert!(report.matches(), "{:?}", report.divergences);
+        assert_eq!(report.replayed[0].result.as_ref().unwrap().attempts_made, 1);
+        assert_eq!(report.replayed[0].result.as_ref().unwrap().code, "fn main() {}");
+    }
+
+    #[tokio::test]
+    async fn test_replayed_plan_sends_the_recorded_bus_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut orchestrator = orchestrator_with(ScriptedAdapter::new(&["fn uno() {}", "fn dos() {}"]));
+        orchestrator.enable_journal(Arc::new(journal::RunJournal::create_in(dir.path()).unwrap()));
+        let plan = ExecutionPlan {
+            original_objective: "dos pasos".to_string(),
+            steps: vec![
+                TaskStep { id: 1, task: "uno".to_string(), tools: vec![], tool_params: HashMap::new(), depends_on: vec![], details: None },
+                TaskStep { id: 2, task: "dos".to_string(), tools: vec![], tool_params: HashMap::new(), depends_on: vec![1], details: None },
+            ],
+        };
+        orchestrator.record_run_started(&RunInput::Plan(plan.clone()), None);
+        assert!(without_approvals(orchestrator.execute_plan(&plan)).await.unwrap().success);
+
+        let entries = journal::RunJournal::load_from(&dir.path().join(journal::JOURNAL_FILE)).unwrap();
+        let report = without_approvals(journal::replay_run(&entries, false)).await.unwrap();
+        // Asignación y dos difusiones por paso
+        assert_eq!(report.recorded_messages.len(), 6);
+        assert_eq!(journal::bus_divergence(&report.recorded_messages, &report.replayed_messages), None);
+        assert!(report.matches(), "{:?}", report.divergences);
+
+        let mut edited = report.recorded_messages.clone();
+        edited.pop();
+        assert!(journal::bus_divergence(&edited, &report.replayed_messages).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_plan_steps_get_their_task_type_tools() {
+        let adapter = ScriptedAdapter::new(&["[\"urgente\"]"]);
+        let offered = adapter.offered_tools.clone();
+        let mut orchestrator = orchestrator_with(adapter);
+        let plan = ExecutionPlan {
+            original_objective: "triage".to_string(),
+            steps: vec![TaskStep { id: 1, task: "Clasifica los tickets abiertos".to_string(), tools: vec![], tool_params: HashMap::new(), depends_on: vec![], details: None }],
+        };
+
+        let result = without_approvals(orchestrator.execute_plan(&plan)).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.steps[0].result.output, Some(TaskOutput::Labels(vec!["urgente".to_string()])));
+        // Solo las herramientas registradas del handler de clasificación
+        assert_eq!(offered.lock().unwrap()[0], vec!["read_file".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_plan_resumes_from_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let plan = ExecutionPlan {
+            original_objective: "dos pasos".to_string(),
+            steps: vec![
+                TaskStep { id: 1, task: "uno".to_string(), tools: vec![], tool_params: HashMap::new(), depends_on: vec![], details: None },
+                TaskStep { id: 2, task: "dos".to_string(), tools: vec![], tool_params: HashMap::new(), depends_on: vec![1], details: None },
+            ],
+        };
+
+        // Solo hay respuesta para el primer paso: el segundo falla
+        let mut orchestrator = orchestrator_with(ScriptedAdapter::new(&["fn uno() {}"]));
+        orchestrator.set_labels(BTreeMap::from([("ticket".to_string(), "OPS-7".to_string())]));
+        orchestrator.enable_journal(Arc::new(journal::RunJournal::create_in(dir.path()).unwrap()));
+        let result = without_approvals(orchestrator.execute_plan(&plan)).await.unwrap();
+        assert!(!result.success);
+
+        let checkpoint = PlanCheckpoint::load_from(&dir.path().join(checkpoint::CHECKPOINT_FILE)).unwrap();
+        assert_eq!(checkpoint.completed.len(), 1);
+        assert_eq!(checkpoint.labels.get("ticket").map(String::as_str), Some("OPS-7"));
+
+        let mut orchestrator = orchestrator_with(ScriptedAdapter::new(&["fn dos() {}"]));
+        orchestrator.enable_journal(Arc::new(journal::RunJournal::create_in(dir.path()).unwrap()));
+        let result = without_approvals(orchestrator.resume_plan(checkpoint)).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.resumed_steps, vec![1]);
+        assert_eq!(result.steps[1].result.result.as_ref().unwrap().code, "fn dos() {}");
+        // El gasto de la reanudación se imputa con las etiquetas de la ejecución original
+        assert_eq!(result.steps[1].result.labels.get("ticket").map(String::as_str), Some("OPS-7"));
+
+        // La reanudación sigue la numeración del journal y queda marcada
+        let entries = journal::RunJournal::load_from(&dir.path().join(journal::JOURNAL_FILE)).unwrap();
+        assert!(entries.windows(2).all(|pair| pair[1].sequence == pair[0].sequence + 1));
+        assert!(entries.iter().any(|entry| matches!(&entry.event, JournalEvent::RunResumed { completed_steps, .. } if completed_steps == &vec![1])));
+    }
+
+    #[tokio::test]
+    async fn test_resume_reruns_steps_whose_details_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let plan = ExecutionPlan {
+            original_objective: "dos pasos".to_string(),
+            steps: vec![
+                TaskStep { id: 1, task: "uno".to_string(), tools: vec![], tool_params: HashMap::new(), depends_on: vec![], details: None },
+                TaskStep { id: 2, task: "dos".to_string(), tools: vec![], tool_params: HashMap::new(), depends_on: vec![1], details: None },
+            ],
+        };
+        let mut orchestrator = orchestrator_with(ScriptedAdapter::new(&["fn uno() {}"]));
+        orchestrator.enable_journal(Arc::new(journal::RunJournal::create_in(dir.path()).unwrap()));
+        assert!(!without_approvals(orchestrator.execute_plan(&plan)).await.unwrap().success);
+
+        // Misma descripción, detalles distintos: la salida guardada ya no vale
+        let mut edited = plan.clone();
+        edited.steps[0].details = Some("Usa u64".to_string());
+        let checkpoint = PlanCheckpoint::load_from(&dir.path().join(checkpoint::CHECKPOINT_FILE)).unwrap()
+            .with_plan(edited)
+            .unwrap();
+        assert!(checkpoint.completed.is_empty());
+
+        let mut orchestrator = orchestrator_with(ScriptedAdapter::new(&["fn uno_u64() {}", "fn dos() {}"]));
+        let result = without_approvals(orchestrator.resume_plan(checkpoint)).await.unwrap();
+        assert!(result.success);
+        assert!(result.resumed_steps.is_empty());
+        assert_eq!(result.steps[0].result.result.as_ref().unwrap().code, "fn uno_u64() {}");
+    }
+
+    #[test]
+    fn test_performance_score_rewards_fast_cheap_verified_tasks() {
+        let good = compute_performance_score(500, 5000, Some(0.95), 0.001, Some(0.01));
+        let slow = compute_performance_score(20_000, 5000, Some(0.95), 0.001, Some(0.01));
+        let poor = compute_performance_score(500, 5000, Some(0.2), 0.009, Some(0.01));
+
+        assert!(good > slow && good > poor);
+        assert!((0.0..=1.0).contains(&good) && (0.0..=1.0).contains(&poor));
+    }
+}
@@ -0,0 +1,387 @@
+// ============================================================================
+// TASK ROUTING - Plantillas, herramientas, parsers y verificadores por TaskType
+// ============================================================================
+// Cada `TaskType` se resuelve con un `TaskHandler` que decide cómo se
+// construye el prompt, qué herramientas se ofrecen al modelo, cómo se
+// interpreta la respuesta y cómo se verifica. Los usuarios pueden registrar
+// handlers propios para `TaskType::CustomTask(nombre)`.
+// ============================================================================
+
+use super::{context, extract_json_from_response, Task, TaskType};
+use crate::{FlowError, VerificationResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+// ============================================================================
+// SALIDA TIPADA
+// ============================================================================
+
+/// Resultado interpretado según el tipo de tarea
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TaskOutput {
+    Code { language: String, code: String },
+    Text(String),
+    Labels(Vec<String>),
+    Series(Vec<f64>),
+    Structured(serde_json::Value),
+}
+
+// ============================================================================
+// TRAIT: TaskHandler
+// ============================================================================
+
+pub trait TaskHandler: Send + Sync {
+    /// Instrucciones de sistema que preceden a la descripción de la tarea
+    fn system_prompt(&self) -> String;
+
+    /// Herramientas que se ofrecen al modelo para este tipo de tarea
+    fn tools(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Construye el prompt final enviado al adaptador
+    fn build_prompt(&self, task: &Task) -> String {
+        let mut prompt = self.system_prompt();
+        let tools = self.tools();
+        if !tools.is_empty() {
+            prompt.push_str(&format!("\n\nHerramientas disponibles: {}", tools.join(", ")));
+        }
+        if let Some(language) = &task.requirements.preferred_language {
+            prompt.push_str(&format!("\n\nLenguaje requerido: {}", language));
+        }
+        prompt.push_str(&context::render(&task.context));
+        prompt.push_str(&format!("\n\nTarea:\n{}", task.description));
+        prompt
+    }
+
+    /// Interpreta la respuesta en bruto del modelo
+    fn parse_output(&self, raw: &str, task: &Task) -> Result<TaskOutput, FlowError>;
+
+    /// Verifica la salida interpretada. `None` delega en el verificador del adaptador.
+    fn verify(&self, _output: &TaskOutput, _task: &Task) -> Option<VerificationResult> {
+        None
+    }
+}
+
+// ============================================================================
+// HANDLERS INCORPORADOS
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputKind {
+    Code,
+    Text,
+    Labels,
+    Series,
+    Structured,
+}
+
+/// Handler genérico parametrizado por plantilla, herramientas y formato de salida
+pub struct BuiltinHandler {
+    system_prompt: &'static str,
+    tools: &'static [&'static str],
+    kind: OutputKind,
+}
+
+impl BuiltinHandler {
+    pub fn for_task_type(task_type: &TaskType) -> Self {
+        match task_type {
+            TaskType::CodeGeneration => Self {
+                system_prompt: "Eres un programador experto. Responde con el código completo en un bloque ``` con el lenguaje indicado, seguido de una breve explicación.",
+                tools: &["list_files", "read_file", "write_file"],
+                kind: OutputKind::Code,
+            },
+            TaskType::DataAnalysis => Self {
+                system_prompt: "Eres un analista de datos. Responde con un objeto JSON con las claves \"summary\" (texto) y \"findings\" (lista de hallazgos).",
+                tools: &["read_file", "json", "text_process"],
+                kind: OutputKind::Structured,
+            },
+            TaskType::Forecasting => Self {
+                system_prompt: "Eres un experto en series temporales. Responde únicamente con un array JSON de números con los valores pronosticados, en orden.",
+                tools: &["read_file", "json"],
+                kind: OutputKind::Series,
+            },
+            TaskType::Regression => Self {
+                system_prompt: "Eres un experto en modelos de regresión. Responde únicamente con un array JSON de números con los valores estimados, en orden.",
+                tools: &["read_file", "json"],
+                kind: OutputKind::Series,
+            },
+            TaskType::TextProcessing => Self {
+                system_prompt: "Eres un experto en procesamiento de texto. Responde solo con el texto resultante, sin comentarios adicionales.",
+                tools: &["text_process", "read_file"],
+                kind: OutputKind::Text,
+            },
+            TaskType::Classification => Self {
+                system_prompt: "Eres un clasificador. Responde únicamente con un array JSON de strings con las etiquetas asignadas.",
+                tools: &["read_file"],
+                kind: OutputKind::Labels,
+            },
+            TaskType::CustomTask(_) => Self {
+                system_prompt: "Resuelve la siguiente tarea de forma precisa.",
+                tools: &[],
+                kind: OutputKind::Text,
+            },
+        }
+    }
+}
+
+impl TaskHandler for BuiltinHandler {
+    fn system_prompt(&self) -> String {
+        self.system_prompt.to_string()
+    }
+
+    fn tools(&self) -> Vec<String> {
+        self.tools.iter().map(|tool| tool.to_string()).collect()
+    }
+
+    fn parse_output(&self, raw: &str, task: &Task) -> Result<TaskOutput, FlowError> {
+        match self.kind {
+            OutputKind::Code => {
+                let (language, code) = extract_code_block(raw);
+                let language = language
+                    .or_else(|| task.requirements.preferred_language.clone())
+                    .unwrap_or_else(|| "unknown".to_string());
+                Ok(TaskOutput::Code { language, code })
+            }
+            OutputKind::Text => Ok(TaskOutput::Text(raw.trim().to_string())),
+            OutputKind::Labels => parse_labels(raw).map(TaskOutput::Labels),
+            OutputKind::Series => parse_series(raw).map(TaskOutput::Series),
+            OutputKind::Structured => serde_json::from_str(&extract_json_from_response(raw))
+                .map(TaskOutput::Structured)
+                .map_err(|e| FlowError::InvalidResponse(format!("Se esperaba JSON: {}", e))),
+        }
+    }
+
+    fn verify(&self, output: &TaskOutput, _task: &Task) -> Option<VerificationResult> {
+        let errors = match output {
+            // El código lo verifica el adaptador, que conoce el lenguaje
+            TaskOutput::Code { .. } => return None,
+            TaskOutput::Text(text) if text.is_empty() => vec!["Respuesta vacía".to_string()],
+            TaskOutput::Labels(labels) if labels.is_empty() => vec!["No se asignó ninguna etiqueta".to_string()],
+            TaskOutput::Series(series) if series.is_empty() => vec!["La serie está vacía".to_string()],
+            TaskOutput::Series(series) if series.iter().any(|v| !v.is_finite()) => {
+                vec!["La serie contiene valores no finitos".to_string()]
+            }
+            TaskOutput::Structured(value) if value.is_null() => vec!["JSON vacío".to_string()],
+            _ => Vec::new(),
+        };
+        Some(verification_from_errors(errors))
+    }
+}
+
+// ============================================================================
+// ROUTER
+// ============================================================================
+
+/// Resuelve el handler de cada tipo de tarea
+pub struct TaskRouter {
+    handlers: HashMap<TaskType, Arc<dyn TaskHandler>>,
+}
+
+impl TaskRouter {
+    /// Router con los handlers incorporados para todos los tipos conocidos
+    pub fn new() -> Self {
+        let mut handlers: HashMap<TaskType, Arc<dyn TaskHandler>> = HashMap::new();
+        for task_type in [
+            TaskType::CodeGeneration,
+            TaskType::DataAnalysis,
+            TaskType::Forecasting,
+            TaskType::TextProcessing,
+            TaskType::Classification,
+            TaskType::Regression,
+        ] {
+            let handler = BuiltinHandler::for_task_type(&task_type);
+            handlers.insert(task_type, Arc::new(handler));
+        }
+        Self { handlers }
+    }
+
+    /// Registra (o reemplaza) el handler de un tipo de tarea
+    pub fn register(&mut self, task_type: TaskType, handler: Arc<dyn TaskHandler>) {
+        self.handlers.insert(task_type, handler);
+    }
+
+    /// Registra un handler para `TaskType::CustomTask(name)`
+    pub fn register_custom(&mut self, name: &str, handler: Arc<dyn TaskHandler>) {
+        self.register(TaskType::CustomTask(name.to_string()), handler);
+    }
+
+    /// Handler para un tipo de tarea; las tareas custom sin registrar usan el genérico
+    pub fn route(&self, task_type: &TaskType) -> Arc<dyn TaskHandler> {
+        self.handlers
+            .get(task_type)
+            .cloned()
+            .unwrap_or_else(|| Arc::new(BuiltinHandler::for_task_type(task_type)))
+    }
+}
+
+impl Default for TaskRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// INFERENCIA DEL TIPO DE TAREA
+// ============================================================================
+
+/// Raíces de palabra que delatan cada tipo de tarea. Las de código van
+/// primero: "implementa un clasificador" sigue siendo generación de código.
+const TASK_TYPE_STEMS: &[(&[&str], TaskType)] = &[
+    (&["código", "codigo", "code", "función", "funcion", "function", "implement", "programa", "refactoriz", "refactor", "endpoint", "compila"], TaskType::CodeGeneration),
+    (&["clasific", "classif", "categoriz", "etiquet"], TaskType::Classification),
+    (&["pronostic", "pronóstic", "forecast", "predic"], TaskType::Forecasting),
+    (&["regresi"], TaskType::Regression),
+    (&["analiz", "análisis", "analisis", "analys", "analyz", "estadístic", "statistic"], TaskType::DataAnalysis),
+    (&["resum", "summar", "traduc", "translat", "redact", "reescrib", "rewrit"], TaskType::TextProcessing),
+];
+
+/// Tipo de tarea de una descripción libre (p. ej. un paso de plan), por las
+/// palabras que empiezan con alguna raíz conocida; por defecto, código
+pub fn infer_task_type(description: &str) -> TaskType {
+    let description = description.to_lowercase();
+    let words: Vec<&str> = description.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    TASK_TYPE_STEMS.iter()
+        .find(|(stems, _)| words.iter().any(|word| stems.iter().any(|stem| word.starts_with(stem))))
+        .map(|(_, task_type)| task_type.clone())
+        .unwrap_or(TaskType::CodeGeneration)
+}
+
+// ============================================================================
+// PARSERS
+// ============================================================================
+
+/// Extrae el primer bloque ``` y su lenguaje; si no hay bloque devuelve el texto completo
+fn extract_code_block(raw: &str) -> (Option<String>, String) {
+    if let Some(start) = raw.find("```") {
+        let after_fence = &raw[start + 3..];
+        let (header, body) = after_fence.split_once('\n').unwrap_or(("", after_fence));
+        if let Some(end) = body.find("```") {
+            let language = header.trim();
+            let language = (!language.is_empty()).then(|| language.to_lowercase());
+            return (language, body[..end].trim_end().to_string());
+        }
+    }
+    (None, raw.trim().to_string())
+}
+
+fn parse_labels(raw: &str) -> Result<Vec<String>, FlowError> {
+    let json = extract_json_from_response(raw);
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(&json) {
+        let array = value.get("labels").cloned().unwrap_or(value);
+        if let Some(items) = array.as_array() {
+            return Ok(items.iter()
+                .filter_map(|item| item.as_str().map(|s| s.trim().to_string()))
+                .filter(|label| !label.is_empty())
+                .collect());
+        }
+    }
+
+    // Respaldo: una etiqueta por línea o separadas por comas
+    let labels: Vec<String> = raw
+        .split(['\n', ','])
+        .map(|label| label.trim().trim_start_matches(['-', '*', '•']).trim().trim_matches('"').to_string())
+        .filter(|label| !label.is_empty() && !label.starts_with("```"))
+        .collect();
+
+    if labels.is_empty() {
+        Err(FlowError::InvalidResponse("No se encontraron etiquetas en la respuesta".to_string()))
+    } else {
+        Ok(labels)
+    }
+}
+
+fn parse_series(raw: &str) -> Result<Vec<f64>, FlowError> {
+    let json = extract_json_from_response(raw);
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(&json) {
+        let array = value.get("series").cloned().unwrap_or(value);
+        if let Some(items) = array.as_array() {
+            return Ok(items.iter().filter_map(|item| item.as_f64()).collect());
+        }
+    }
+
+    // Respaldo: las palabras del texto que son un número completo; "Q3",
+    // "v2" o "2024:" no forman parte de la serie
+    static NUMBER: OnceLock<regex::Regex> = OnceLock::new();
+    let number = NUMBER.get_or_init(|| {
+        regex::Regex::new(r"^-?\d+(?:\.\d+)?(?:[eE][-+]?\d+)?$").expect("regex de número válida")
+    });
+    let series: Vec<f64> = raw
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '[' | ']' | '(' | ')'))
+        .map(|word| word.strip_suffix('.').unwrap_or(word))
+        .filter(|word| number.is_match(word))
+        .filter_map(|word| word.parse().ok())
+        .collect();
+
+    if series.is_empty() {
+        Err(FlowError::InvalidResponse("No se encontró ninguna serie numérica en la respuesta".to_string()))
+    } else {
+        Ok(series)
+    }
+}
+
+fn verification_from_errors(errors: Vec<String>) -> VerificationResult {
+    let is_valid = errors.is_empty();
+    VerificationResult {
+        is_valid,
+        compilation_success: true,
+        tests_passed: is_valid,
+        quality_score: if is_valid { 1.0 } else { 0.0 },
+        errors,
+        warnings: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swarm::TaskBuilder;
+
+    #[test]
+    fn test_classification_and_forecasting_outputs() {
+        let router = TaskRouter::new();
+
+        let task = TaskBuilder::classification("¿Es spam?");
+        let handler = router.route(&task.task_type);
+        let output = handler.parse_output("```json\n[\"spam\", \"phishing\"]\n```", &task).unwrap();
+        assert_eq!(output, TaskOutput::Labels(vec!["spam".to_string(), "phishing".to_string()]));
+
+        let task = TaskBuilder::forecasting("Ventas próximas 3 semanas");
+        let handler = router.route(&task.task_type);
+        let output = handler.parse_output("Pronóstico: [1.5, 2, 3.25]", &task).unwrap();
+        assert_eq!(output, TaskOutput::Series(vec![1.5, 2.0, 3.25]));
+        // Los números dentro de palabras no entran en la serie
+        let output = handler.parse_output("Ventas Q3 2024: 10 12.5 -3e2.", &task).unwrap();
+        assert_eq!(output, TaskOutput::Series(vec![10.0, 12.5, -300.0]));
+    }
+
+    #[test]
+    fn test_infers_task_type_from_step_description() {
+        assert_eq!(infer_task_type("Clasifica los tickets por prioridad"), TaskType::Classification);
+        assert_eq!(infer_task_type("Pronostica las ventas del próximo trimestre"), TaskType::Forecasting);
+        assert_eq!(infer_task_type("Resume el informe en tres frases"), TaskType::TextProcessing);
+        assert_eq!(infer_task_type("Implementa un clasificador de spam"), TaskType::CodeGeneration);
+        assert_eq!(infer_task_type("Crear los endpoints REST"), TaskType::CodeGeneration);
+    }
+
+    #[test]
+    fn test_custom_handler_registration() {
+        struct Upper;
+        impl TaskHandler for Upper {
+            fn system_prompt(&self) -> String {
+                "Responde en mayúsculas".to_string()
+            }
+            fn parse_output(&self, raw: &str, _task: &Task) -> Result<TaskOutput, FlowError> {
+                Ok(TaskOutput::Text(raw.to_uppercase()))
+            }
+        }
+
+        let mut router = TaskRouter::new();
+        router.register_custom("upper", Arc::new(Upper));
+
+        let task = TaskBuilder::custom("upper", "hola");
+        let output = router.route(&task.task_type).parse_output("hola", &task).unwrap();
+        assert_eq!(output, TaskOutput::Text("HOLA".to_string()));
+    }
+}
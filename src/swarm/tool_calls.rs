@@ -0,0 +1,89 @@
+// ============================================================================
+// TOOL CALLS - Ejecución de herramientas con replay, métricas y journal
+// ============================================================================
+// `SwarmToolCaller` es el único camino por el que el orquestador ejecuta una
+// herramienta: tanto las de los pasos de plan (`execute_tool`) como las que
+// pide el modelo durante una generación (function calling, vía
+// `tools::caller::scope`). Cada llamada pasa por el registro (esquema y
+// aprobación) o el replay, se exporta como métrica y se graba en el journal;
+// el orquestador vuelca después las llamadas en sus estadísticas de uso.
+// ============================================================================
+
+use super::checkpoint::ToolCallRecord;
+use super::journal::{JournalEvent, RunJournal, ToolReplay};
+use crate::performance::{exporter, Span, SpanKind};
+use crate::tools::{caller::ToolCaller, ToolError, ToolParams, ToolRegistry, ToolResult};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Llamada terminada, pendiente de volcar en las estadísticas del orquestador
+#[derive(Debug, Clone)]
+pub struct CompletedToolCall {
+    pub record: ToolCallRecord,
+    pub duration: Duration,
+}
+
+pub struct SwarmToolCaller {
+    tools: Arc<ToolRegistry>,
+    replay: Option<Arc<ToolReplay>>,
+    journal: Option<Arc<RunJournal>>,
+    completed: Mutex<Vec<CompletedToolCall>>,
+}
+
+impl SwarmToolCaller {
+    pub fn new(tools: Arc<ToolRegistry>, replay: Option<Arc<ToolReplay>>, journal: Option<Arc<RunJournal>>) -> Self {
+        Self { tools, replay, journal, completed: Mutex::new(Vec::new()) }
+    }
+
+    /// Llamadas hechas desde la última vez, en orden
+    pub fn take_completed(&self) -> Vec<CompletedToolCall> {
+        self.completed.lock().map(|mut completed| std::mem::take(&mut *completed)).unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl ToolCaller for SwarmToolCaller {
+    async fn call(&self, tool_name: &str, params: ToolParams) -> Result<ToolResult, ToolError> {
+        let start_time = Instant::now();
+        let recorded_params = serde_json::to_value(&params.data).unwrap_or(serde_json::Value::Null);
+        let mut span = Span::start(&format!("tool {}", tool_name), SpanKind::Tool);
+        span.set_attribute("tool", tool_name);
+        if let Some(tool) = self.tools.get(tool_name) {
+            span.set_attribute("risk_level", format!("{:?}", tool.risk_level()));
+        }
+        span.set_attribute("replayed", self.replay.is_some());
+
+        let result = match self.replay.as_ref().map(|replay| replay.next(tool_name)) {
+            // Reproducción: se devuelve el resultado grabado sin ejecutar la herramienta
+            Some(Some(recorded)) => recorded.map_err(ToolError::ExecutionError),
+            Some(None) => Err(ToolError::ExecutionError(format!(
+                "Replay agotado: no hay más llamadas grabadas a '{}'", tool_name
+            ))),
+            None => self.tools.execute(tool_name, params).await,
+        };
+
+        let duration = start_time.elapsed();
+        exporter::tool_call(tool_name, result.is_ok(), duration);
+        if let Err(e) = &result {
+            span.set_error(e.to_string());
+        }
+        let recorded_result = result.as_ref().map(|r| r.clone()).map_err(|e| e.to_string());
+        if let Some(journal) = &self.journal {
+            journal.record(JournalEvent::ToolCall {
+                tool: tool_name.to_string(),
+                params: recorded_params.clone(),
+                result: recorded_result.clone(),
+                duration_ms: duration.as_millis() as u64,
+            });
+        }
+        if let Ok(mut completed) = self.completed.lock() {
+            completed.push(CompletedToolCall {
+                record: ToolCallRecord { tool: tool_name.to_string(), params: recorded_params, result: recorded_result },
+                duration,
+            });
+        }
+
+        result
+    }
+}
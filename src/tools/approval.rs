@@ -0,0 +1,375 @@
+// ============================================================================
+// APPROVAL - Puntos de aprobación humana para herramientas y pasos de plan
+// ============================================================================
+// Antes de ejecutar una herramienta con riesgo igual o superior al umbral
+// configurado, o antes de aplicar un paso de un plan, la ejecución se detiene
+// y pide confirmación con `dialoguer`, mostrando los parámetros exactos.
+// En CI se usa un fichero de política (TOML) no interactivo, y
+// `--dangerously-skip-permissions` desactiva las comprobaciones de forma
+// explícita. El prompt se hace en un hilo bloqueante para no detener el
+// runtime, y `scope` permite usar un gate propio en una tarea sin tocar el
+// global del proceso.
+// ============================================================================
+
+use super::{RiskLevel, ToolError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::future::Future;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+// ============================================================================
+// SOLICITUDES Y POLÍTICA
+// ============================================================================
+
+/// Qué se quiere aprobar
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ApprovalSubject {
+    /// Ejecución de una herramienta del registro
+    Tool { name: String, risk_level: RiskLevel },
+    /// Aplicación de un paso de un plan del swarm
+    PlanStep { plan_id: String, step_id: u32, description: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    pub subject: ApprovalSubject,
+    /// Parámetros exactos con los que se ejecutaría
+    pub params: serde_json::Value,
+}
+
+impl ApprovalRequest {
+    pub fn tool(name: &str, risk_level: RiskLevel, params: serde_json::Value) -> Self {
+        Self {
+            subject: ApprovalSubject::Tool { name: name.to_string(), risk_level },
+            params,
+        }
+    }
+
+    pub fn plan_step(plan_id: &str, step_id: u32, description: &str, params: serde_json::Value) -> Self {
+        Self {
+            subject: ApprovalSubject::PlanStep {
+                plan_id: plan_id.to_string(),
+                step_id,
+                description: description.to_string(),
+            },
+            params,
+        }
+    }
+
+    /// Clave con la que se recuerda una aprobación durante la sesión: la de
+    /// un paso vale para el resto de pasos del mismo plan, no para otros planes
+    fn session_key(&self) -> String {
+        match &self.subject {
+            ApprovalSubject::Tool { name, .. } => format!("tool:{}", name),
+            ApprovalSubject::PlanStep { plan_id, .. } => format!("plan:{}", plan_id),
+        }
+    }
+
+    fn summary(&self) -> String {
+        match &self.subject {
+            ApprovalSubject::Tool { name, risk_level } => {
+                format!("Ejecutar la herramienta '{}' (riesgo {:?})", name, risk_level)
+            }
+            ApprovalSubject::PlanStep { step_id, description, .. } => {
+                format!("Aplicar el paso {} del plan: {}", step_id, description)
+            }
+        }
+    }
+}
+
+/// Política no interactiva, pensada para CI.
+///
+/// ```toml
+/// default = "deny"
+/// auto_approve_below = "High"
+/// allow_tools = ["write_file"]
+/// deny_tools = ["ruv_swarm"]
+/// allow_plan_steps = true
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    /// Decisión cuando ninguna regla aplica
+    #[serde(default)]
+    pub default: PolicyAction,
+    /// Herramientas con riesgo inferior a este nivel se aprueban solas
+    #[serde(default)]
+    pub auto_approve_below: Option<RiskLevel>,
+    #[serde(default)]
+    pub allow_tools: Vec<String>,
+    #[serde(default)]
+    pub deny_tools: Vec<String>,
+    #[serde(default)]
+    pub allow_plan_steps: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    #[default]
+    Deny,
+}
+
+impl ApprovalPolicy {
+    /// Carga una política desde un fichero TOML
+    pub fn load(path: &Path) -> Result<Self, ToolError> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content)
+            .map_err(|e| ToolError::ValidationError(format!("Política de aprobación inválida: {}", e)))
+    }
+
+    /// Decide una solicitud sin intervención humana
+    pub fn decide(&self, request: &ApprovalRequest) -> PolicyAction {
+        match &request.subject {
+            ApprovalSubject::Tool { name, risk_level } => {
+                if self.deny_tools.contains(name) {
+                    PolicyAction::Deny
+                } else if self.allow_tools.contains(name)
+                    || self.auto_approve_below.as_ref().is_some_and(|limit| risk_level < limit)
+                {
+                    PolicyAction::Allow
+                } else {
+                    self.default
+                }
+            }
+            ApprovalSubject::PlanStep { .. } => {
+                if self.allow_plan_steps { PolicyAction::Allow } else { self.default }
+            }
+        }
+    }
+}
+
+/// Cómo se resuelven las solicitudes de aprobación
+#[derive(Debug, Clone)]
+pub enum ApprovalMode {
+    /// Preguntar al usuario en la terminal
+    Interactive,
+    /// Resolver con una política no interactiva
+    Policy(ApprovalPolicy),
+    /// Aprobar todo (`--dangerously-skip-permissions`)
+    Bypass,
+}
+
+// ============================================================================
+// GATE DE APROBACIÓN
+// ============================================================================
+
+pub struct ApprovalGate {
+    mode: ApprovalMode,
+    /// Nivel de riesgo a partir del cual una herramienta requiere aprobación
+    risk_threshold: RiskLevel,
+    /// Si los pasos de un plan requieren aprobación
+    confirm_plan_steps: bool,
+    /// Aprobaciones recordadas durante la sesión
+    remembered: Mutex<HashSet<String>>,
+}
+
+impl Default for ApprovalGate {
+    fn default() -> Self {
+        Self::new(ApprovalMode::Interactive, RiskLevel::High)
+    }
+}
+
+impl ApprovalGate {
+    pub fn new(mode: ApprovalMode, risk_threshold: RiskLevel) -> Self {
+        Self {
+            mode,
+            risk_threshold,
+            confirm_plan_steps: true,
+            remembered: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn with_plan_step_confirmation(mut self, enabled: bool) -> Self {
+        self.confirm_plan_steps = enabled;
+        self
+    }
+
+    pub fn mode(&self) -> &ApprovalMode {
+        &self.mode
+    }
+
+    /// Si una herramienta con este riesgo debe pasar por el gate
+    pub fn requires_approval(&self, risk_level: &RiskLevel, requires_confirmation: bool) -> bool {
+        requires_confirmation || *risk_level >= self.risk_threshold
+    }
+
+    /// Pide aprobación para ejecutar una herramienta
+    pub async fn check_tool(
+        &self,
+        name: &str,
+        risk_level: RiskLevel,
+        requires_confirmation: bool,
+        params: serde_json::Value,
+    ) -> Result<(), ToolError> {
+        if !self.requires_approval(&risk_level, requires_confirmation) {
+            return Ok(());
+        }
+        self.check(&ApprovalRequest::tool(name, risk_level, params)).await
+    }
+
+    /// Pide aprobación para aplicar un paso del plan `plan_id`
+    pub async fn check_plan_step(
+        &self,
+        plan_id: &str,
+        step_id: u32,
+        description: &str,
+        params: serde_json::Value,
+    ) -> Result<(), ToolError> {
+        if !self.confirm_plan_steps {
+            return Ok(());
+        }
+        self.check(&ApprovalRequest::plan_step(plan_id, step_id, description, params)).await
+    }
+
+    /// Resuelve una solicitud según el modo configurado
+    pub async fn check(&self, request: &ApprovalRequest) -> Result<(), ToolError> {
+        let key = request.session_key();
+        if self.remembered.lock().map(|set| set.contains(&key)).unwrap_or(false) {
+            return Ok(());
+        }
+
+        match &self.mode {
+            ApprovalMode::Bypass => {
+                log::warn!("⚠️ Aprobación omitida (--dangerously-skip-permissions): {}", request.summary());
+                Ok(())
+            }
+            ApprovalMode::Policy(policy) => match policy.decide(request) {
+                PolicyAction::Allow => Ok(()),
+                PolicyAction::Deny => Err(ToolError::PermissionDenied(format!(
+                    "{} (rechazado por la política de aprobación)",
+                    request.summary()
+                ))),
+            },
+            ApprovalMode::Interactive => self.prompt(request, &key).await,
+        }
+    }
+
+    async fn prompt(&self, request: &ApprovalRequest, key: &str) -> Result<(), ToolError> {
+        if !std::io::stdin().is_terminal() {
+            return Err(ToolError::PermissionDenied(format!(
+                "{} requiere aprobación y no hay terminal interactiva; usa --approval-policy o --dangerously-skip-permissions",
+                request.summary()
+            )));
+        }
+
+        let summary = request.summary();
+        let params = serde_json::to_string_pretty(&request.params).unwrap_or_default();
+        let session_option = match &request.subject {
+            ApprovalSubject::Tool { .. } => "Aprobar durante esta sesión",
+            ApprovalSubject::PlanStep { .. } => "Aprobar todos los pasos de este plan",
+        };
+
+        // dialoguer bloquea hasta que el usuario responde: fuera del runtime
+        let choice = tokio::task::spawn_blocking(move || {
+            println!("\n⏸️  {}", summary);
+            println!("Parámetros:\n{}", params);
+            dialoguer::Select::new()
+                .with_prompt("¿Continuar?")
+                .items(&["Aprobar", session_option, "Rechazar"])
+                .default(0)
+                .interact()
+        })
+        .await
+        .map_err(|e| ToolError::InternalError(format!("Error en el prompt de aprobación: {}", e)))?
+        .map_err(|e| ToolError::InternalError(format!("Error en el prompt de aprobación: {}", e)))?;
+
+        match choice {
+            0 => Ok(()),
+            1 => {
+                if let Ok(mut remembered) = self.remembered.lock() {
+                    remembered.insert(key.to_string());
+                }
+                Ok(())
+            }
+            _ => Err(ToolError::PermissionDenied(format!("{} (rechazado por el usuario)", request.summary()))),
+        }
+    }
+}
+
+// ============================================================================
+// GATE GLOBAL
+// ============================================================================
+
+tokio::task_local! {
+    static SCOPED_GATE: Arc<ApprovalGate>;
+}
+
+/// Ejecuta `future` con `gate` en lugar del gate global, sin modificarlo
+pub async fn scope<F: Future>(gate: ApprovalGate, future: F) -> F::Output {
+    SCOPED_GATE.scope(Arc::new(gate), future).await
+}
+
+fn global_slot() -> &'static RwLock<Arc<ApprovalGate>> {
+    static GATE: OnceLock<RwLock<Arc<ApprovalGate>>> = OnceLock::new();
+    GATE.get_or_init(|| RwLock::new(Arc::new(ApprovalGate::default())))
+}
+
+/// Instala el gate que usarán el registro de herramientas y el orquestador
+pub fn install_gate(gate: ApprovalGate) {
+    if let Ok(mut slot) = global_slot().write() {
+        *slot = Arc::new(gate);
+    }
+}
+
+/// Gate de aprobación activo: el de la tarea (`scope`) o el global
+pub fn approval_gate() -> Arc<ApprovalGate> {
+    if let Ok(gate) = SCOPED_GATE.try_with(Arc::clone) {
+        return gate;
+    }
+    global_slot()
+        .read()
+        .map(|slot| slot.clone())
+        .unwrap_or_else(|_| Arc::new(ApprovalGate::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_policy_decisions() {
+        let policy: ApprovalPolicy = toml::from_str(r#"
+            default = "deny"
+            auto_approve_below = "High"
+            allow_tools = ["write_file"]
+            deny_tools = ["read_file"]
+        "#).unwrap();
+        let gate = ApprovalGate::new(ApprovalMode::Policy(policy), RiskLevel::Low);
+
+        assert!(gate.check_tool("write_file", RiskLevel::Critical, false, serde_json::json!({})).await.is_ok());
+        assert!(gate.check_tool("read_file", RiskLevel::Low, false, serde_json::json!({})).await.is_err());
+        assert!(gate.check_tool("hash", RiskLevel::Medium, false, serde_json::json!({})).await.is_ok());
+        assert!(gate.check_tool("ruv_swarm", RiskLevel::High, false, serde_json::json!({})).await.is_err());
+        assert!(gate.check_plan_step("plan", 1, "paso", serde_json::json!({})).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bypass_and_threshold() {
+        let gate = ApprovalGate::new(ApprovalMode::Bypass, RiskLevel::Low);
+        assert!(gate.check_tool("rm", RiskLevel::Critical, true, serde_json::json!({})).await.is_ok());
+
+        let gate = ApprovalGate::new(ApprovalMode::Interactive, RiskLevel::High);
+        assert!(!gate.requires_approval(&RiskLevel::Medium, false));
+        assert!(gate.requires_approval(&RiskLevel::Medium, true));
+    }
+
+    #[test]
+    fn test_plan_step_approvals_are_remembered_per_plan() {
+        let first = ApprovalRequest::plan_step("a1", 1, "paso", serde_json::json!({}));
+        let same_plan = ApprovalRequest::plan_step("a1", 2, "otro paso", serde_json::json!({}));
+        let other_plan = ApprovalRequest::plan_step("b2", 1, "paso", serde_json::json!({}));
+        assert_eq!(first.session_key(), same_plan.session_key());
+        assert_ne!(first.session_key(), other_plan.session_key());
+    }
+
+    #[tokio::test]
+    async fn test_scoped_gate_does_not_touch_the_global_one() {
+        let request = ApprovalRequest::plan_step("plan", 1, "paso", serde_json::json!({}));
+        let bypass = ApprovalGate::new(ApprovalMode::Bypass, RiskLevel::Critical);
+        assert!(scope(bypass, async { approval_gate().check(&request).await }).await.is_ok());
+        assert!(matches!(approval_gate().mode(), ApprovalMode::Interactive));
+    }
+}
@@ -0,0 +1,47 @@
+// ============================================================================
+// TOOL CALLER - Herramientas que pide el modelo (function calling)
+// ============================================================================
+// Cuando un adaptador recibe una llamada a función la ejecuta con
+// `call_tool`. Durante una tarea el orquestador instala con `scope` un
+// `ToolCaller` propio que, además del registro (validación del esquema y
+// aprobación), aplica el replay, las métricas y el journal de la ejecución.
+// Fuera de un `scope` se usa el registro compartido del proceso.
+// ============================================================================
+
+use super::{get_registry, ToolError, ToolParams, ToolResult};
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Ejecuta las llamadas a herramientas de una generación
+#[async_trait]
+pub trait ToolCaller: Send + Sync {
+    async fn call(&self, name: &str, params: ToolParams) -> Result<ToolResult, ToolError>;
+}
+
+tokio::task_local! {
+    static SCOPED_CALLER: Arc<dyn ToolCaller>;
+}
+
+/// Ejecuta `future` resolviendo sus llamadas a herramientas con `caller`
+pub async fn scope<F: Future>(caller: Arc<dyn ToolCaller>, future: F) -> F::Output {
+    SCOPED_CALLER.scope(caller, future).await
+}
+
+/// Ejecuta una herramienta pedida por el modelo: con el `ToolCaller` de la
+/// tarea o, si no hay, con el registro compartido
+pub async fn call_tool(name: &str, params: ToolParams) -> Result<ToolResult, ToolError> {
+    match SCOPED_CALLER.try_with(Arc::clone) {
+        Ok(caller) => caller.call(name, params).await,
+        Err(_) => get_registry().execute(name, params).await,
+    }
+}
+
+/// Parámetros de herramienta a partir de los argumentos de una llamada a función
+pub fn params_from_args(args: &serde_json::Value) -> Result<ToolParams, ToolError> {
+    match args {
+        serde_json::Value::Object(map) => Ok(ToolParams { data: map.clone().into_iter().collect() }),
+        serde_json::Value::Null => Ok(ToolParams::new()),
+        other => Err(ToolError::InvalidParameter("args".to_string(), format!("se esperaba un objeto: {}", other))),
+    }
+}
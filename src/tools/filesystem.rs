@@ -39,7 +39,8 @@ impl Tool for ListFilesTool {
             serde_json::json!({
                 "path": {
                     "type": "string",
-                    "description": "Ruta del directorio a listar (por defecto: directorio actual)"
+                    "description": "Ruta del directorio a listar (por defecto: directorio actual)",
+                    "default": "."
                 },
                 "pattern": {
                     "type": "string", 
@@ -55,7 +56,8 @@ impl Tool for ListFilesTool {
                 },
                 "max_depth": {
                     "type": "integer",
-                    "description": "Profundidad máxima para búsqueda recursiva"
+                    "description": "Profundidad máxima para búsqueda recursiva",
+                    "minimum": 0
                 }
             }),
             vec![]
@@ -187,19 +189,24 @@ impl Tool for ReadFileTool {
                 "encoding": {
                     "type": "string",
                     "description": "Encoding del archivo (utf-8, ascii, latin1, binary)",
-                    "enum": ["utf-8", "ascii", "latin1", "binary"]
+                    "enum": ["utf-8", "ascii", "latin1", "binary"],
+                    "default": "utf-8"
                 },
                 "max_size": {
                     "type": "integer",
-                    "description": "Tamaño máximo en bytes a leer (por defecto: 1MB)"
+                    "description": "Tamaño máximo en bytes a leer (por defecto: 1MB)",
+                    "minimum": 0,
+                    "default": 1048576
                 },
                 "start_byte": {
                     "type": "integer",
-                    "description": "Posición de inicio para lectura parcial"
+                    "description": "Posición de inicio para lectura parcial",
+                    "minimum": 0
                 },
                 "end_byte": {
                     "type": "integer",
-                    "description": "Posición de fin para lectura parcial"
+                    "description": "Posición de fin para lectura parcial",
+                    "minimum": 0
                 }
             }),
             vec!["path"]
@@ -309,7 +316,8 @@ impl Tool for WriteFileTool {
                 "encoding": {
                     "type": "string",
                     "description": "Encoding del contenido (utf-8, binary-base64)",
-                    "enum": ["utf-8", "binary-base64"]
+                    "enum": ["utf-8", "binary-base64"],
+                    "default": "utf-8"
                 },
                 "append": {
                     "type": "boolean",
@@ -50,7 +50,8 @@ impl Tool for MemoryStoreTool {
                 },
                 "namespace": {
                     "type": "string",
-                    "description": "Namespace para organizar los datos (por defecto: 'default')"
+                    "description": "Namespace para organizar los datos (por defecto: 'default')",
+                    "default": "default"
                 },
                 "ttl_hours": {
                     "type": "integer",
@@ -157,7 +158,8 @@ impl Tool for MemoryRetrieveTool {
                 },
                 "namespace": {
                     "type": "string",
-                    "description": "Namespace de los datos (por defecto: 'default')"
+                    "description": "Namespace de los datos (por defecto: 'default')",
+                    "default": "default"
                 },
                 "search": {
                     "type": "string",
@@ -174,7 +176,9 @@ impl Tool for MemoryRetrieveTool {
                 },
                 "limit": {
                     "type": "integer",
-                    "description": "Máximo número de resultados (por defecto: 10)"
+                    "description": "Máximo número de resultados (por defecto: 10)",
+                    "minimum": 1,
+                    "default": 10
                 }
             }),
             vec![]
@@ -4,7 +4,8 @@
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
 
 // Módulos de herramientas
 pub mod core;
@@ -17,6 +18,13 @@ pub mod memory;
 pub mod safla_tool;
 pub mod ruv_swarm_tool;
 pub mod utils;
+pub mod approval;
+pub mod caller;
+pub mod schema;
+
+/// Versión de los esquemas de parámetros de las herramientas. Forma parte de
+/// la clave de la caché de respuestas: súbela al cambiar un `parameters_schema`.
+pub const TOOL_SCHEMA_VERSION: u32 = 2;
 
 // ============================================================================
 // TRAIT PRINCIPAL: Tool
@@ -70,8 +78,8 @@ pub enum ToolCategory {
     AI,
 }
 
-/// Niveles de riesgo
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Niveles de riesgo, ordenados de menor a mayor
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum RiskLevel {
     Low,     // Operaciones de lectura
     Medium,  // Operaciones de escritura
@@ -206,18 +214,35 @@ impl ToolRegistry {
         }
     }
     
+    /// Constructor con todas las herramientas del crate, filtrables por categoría
+    pub fn builder() -> ToolRegistryBuilder {
+        ToolRegistryBuilder::default()
+    }
+    
     /// Registra una nueva herramienta
     pub fn register<T: Tool + 'static>(&mut self, tool: T) {
+        self.register_boxed(Box::new(tool));
+    }
+    
+    fn register_boxed(&mut self, tool: Box<dyn Tool>) {
         let name = tool.name().to_string();
-        let category = tool.category();
         
         // Agregar a categoría
-        self.categories.entry(category)
+        self.categories.entry(tool.category())
             .or_insert_with(Vec::new)
             .push(name.clone());
         
         // Registrar herramienta
-        self.tools.insert(name, Box::new(tool));
+        self.tools.insert(name, tool);
+    }
+    
+    /// Número de herramientas registradas
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
     }
     
     /// Obtiene una herramienta por nombre
@@ -253,11 +278,17 @@ impl ToolRegistry {
         let tool = self.get(name)
             .ok_or_else(|| ToolError::ToolNotFound(name.to_string()))?;
         
-        // Verificar si requiere confirmación
-        if tool.requires_confirmation() {
-            // TODO: Implementar sistema de confirmación
-            println!("⚠️  La herramienta '{}' requiere confirmación del usuario", name);
-        }
+        // Rechazar entradas inválidas antes de ejecutar y rellenar los defaults
+        let mut params = params;
+        validate_parameters(&mut params, &tool.parameters_schema())?;
+        
+        // Pedir aprobación si el riesgo supera el umbral configurado
+        approval::approval_gate().check_tool(
+            name,
+            tool.risk_level(),
+            tool.requires_confirmation(),
+            serde_json::to_value(&params.data).unwrap_or(serde_json::Value::Null),
+        ).await?;
         
         // Ejecutar
         tool.execute(params).await
@@ -265,44 +296,94 @@ impl ToolRegistry {
 }
 
 // ============================================================================
-// INSTANCIA GLOBAL SIMPLIFICADA
+// BUILDER Y REGISTRO COMPARTIDO
 // ============================================================================
+// El registro se construye una sola vez por proceso (las herramientas de MCP
+// crean sus clientes HTTP al construirse) y se comparte como
+// `Arc<ToolRegistry>`. Para limitar las categorías disponibles (flags
+// `--tools-include`/`--tools-exclude`), `main` instala un registro propio con
+// `install_registry` antes del primer `get_registry`.
 
-/// Inicializa el registro global de herramientas
-pub fn initialize_registry() -> ToolRegistry {
-    let mut registry = ToolRegistry::new();
-    
-    // Registrar herramientas de filesystem
-    registry.register(filesystem::ListFilesTool::new());
-    registry.register(filesystem::ReadFileTool::new());
-    registry.register(filesystem::WriteFileTool::new());
-    
-    // Registrar herramientas de memoria
-    registry.register(memory::MemoryStoreTool::new());
-    registry.register(memory::MemoryRetrieveTool::new());
-    registry.register(memory::MemoryListTool::new());
+/// Todas las herramientas implementadas en el crate
+fn all_tools() -> Vec<Box<dyn Tool>> {
+    vec![
+        // Filesystem
+        Box::new(filesystem::ListFilesTool::new()),
+        Box::new(filesystem::ReadFileTool::new()),
+        Box::new(filesystem::WriteFileTool::new()),
+        // Sistema y texto
+        Box::new(system::SystemInfoTool::new()),
+        Box::new(text::TextProcessTool::new()),
+        // Memoria
+        Box::new(memory::MemoryStoreTool::new()),
+        Box::new(memory::MemoryRetrieveTool::new()),
+        Box::new(memory::MemoryListTool::new()),
+        // Utilidades
+        Box::new(utils::Base64Tool::new()),
+        Box::new(utils::HashTool::new()),
+        Box::new(utils::UrlTool::new()),
+        Box::new(utils::JsonTool::new()),
+        // AI
+        Box::new(safla_tool::SaflaTool::new()),
+        Box::new(ruv_swarm_tool::RuvSwarmTool::new()),
+    ]
+}
+
+/// Selección de categorías para construir un `ToolRegistry`
+#[derive(Debug, Clone, Default)]
+pub struct ToolRegistryBuilder {
+    /// Si se indica, solo estas categorías
+    include: Option<HashSet<ToolCategory>>,
+    exclude: HashSet<ToolCategory>,
+}
+
+impl ToolRegistryBuilder {
+    /// Limita el registro a esta categoría (acumulable)
+    pub fn include(mut self, category: ToolCategory) -> Self {
+        self.include.get_or_insert_with(HashSet::new).insert(category);
+        self
+    }
     
-    // Registrar herramientas de utilidades
-    registry.register(utils::Base64Tool::new());
-    registry.register(utils::HashTool::new());
-    registry.register(utils::UrlTool::new());
-    registry.register(utils::JsonTool::new());
+    /// Quita una categoría; tiene prioridad sobre `include`
+    pub fn exclude(mut self, category: ToolCategory) -> Self {
+        self.exclude.insert(category);
+        self
+    }
     
-    // Registrar herramientas de AI
-    registry.register(safla_tool::SaflaTool::new());
-    registry.register(ruv_swarm_tool::RuvSwarmTool::new());
+    fn allows(&self, category: &ToolCategory) -> bool {
+        !self.exclude.contains(category)
+            && self.include.as_ref().is_none_or(|include| include.contains(category))
+    }
     
-    registry
+    pub fn build(self) -> ToolRegistry {
+        let mut registry = ToolRegistry::new();
+        for tool in all_tools().into_iter().filter(|tool| self.allows(&tool.category())) {
+            registry.register_boxed(tool);
+        }
+        registry
+    }
 }
 
-/// Obtiene un registro inicializado
-pub fn get_registry() -> ToolRegistry {
-    initialize_registry()
+static REGISTRY: OnceLock<Arc<ToolRegistry>> = OnceLock::new();
+
+/// Instala el registro compartido. Falla si ya se usó o se instaló otro,
+/// en lugar de descartar `registry` sin avisar.
+pub fn install_registry(registry: ToolRegistry) -> Result<Arc<ToolRegistry>, ToolError> {
+    let registry = Arc::new(registry);
+    REGISTRY.set(Arc::clone(&registry)).map_err(|_| {
+        ToolError::InternalError("El registro de herramientas ya está en uso; instálalo antes del primer uso".to_string())
+    })?;
+    Ok(registry)
 }
 
-/// Obtiene un registro inicializado mutable
-pub fn get_registry_mut() -> ToolRegistry {
-    initialize_registry()
+/// Registro compartido del proceso, con todas las herramientas si no se instaló otro
+pub fn get_registry() -> Arc<ToolRegistry> {
+    Arc::clone(REGISTRY.get_or_init(|| Arc::new(initialize_registry())))
+}
+
+/// Construye un registro nuevo con todas las herramientas
+pub fn initialize_registry() -> ToolRegistry {
+    ToolRegistry::builder().build()
 }
 
 // ============================================================================
@@ -318,8 +399,53 @@ pub fn create_parameters_schema(properties: serde_json::Value, required: Vec<&st
     })
 }
 
-/// Valida parámetros contra un esquema
-pub fn validate_parameters(_params: &ToolParams, _schema: &serde_json::Value) -> Result<(), ToolError> {
-    // TODO: Implementar validación real usando jsonschema
-    Ok(())
-} 
\ No newline at end of file
+/// Valida parámetros contra un esquema JSON y rellena los valores por defecto
+pub fn validate_parameters(params: &mut ToolParams, schema: &serde_json::Value) -> Result<(), ToolError> {
+    schema::validate_params(params, schema)
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Nombre (`fn name`) de cada `impl Tool for` de los fuentes de `src/tools`
+    fn implemented_tool_names() -> Vec<String> {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src").join("tools");
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let source = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            for (start, _) in source.match_indices("\nimpl Tool for ") {
+                let body = &source[start..];
+                let name_fn = body.find("fn name(&self)").expect("impl Tool sin fn name");
+                let literal = body[name_fn..].split('"').nth(1).expect("fn name sin literal");
+                names.push(literal.to_string());
+            }
+        }
+        names
+    }
+
+    #[test]
+    fn test_every_implemented_tool_is_registered() {
+        let implemented = implemented_tool_names();
+        let registry = initialize_registry();
+        for name in &implemented {
+            assert!(registry.get(name).is_some(), "'{}' implementa Tool pero no está en all_tools()", name);
+        }
+        // Sin nombres repetidos que se pisen en el registro
+        assert_eq!(registry.len(), implemented.len());
+        // Cada herramienta se ofrece al modelo con su esquema
+        assert_eq!(registry.get_function_schemas().len(), registry.len());
+
+        let registry = ToolRegistry::builder().include(ToolCategory::Utils).include(ToolCategory::Memory)
+            .exclude(ToolCategory::Memory).build();
+        assert_eq!(registry.len(), registry.list_by_category(&ToolCategory::Utils).len());
+        assert!(registry.get("system_info").is_none() && registry.get("base64").is_some());
+    }
+
+    #[test]
+    fn test_install_after_first_use_fails() {
+        let shared = get_registry();
+        assert!(Arc::ptr_eq(&shared, &get_registry()));
+        assert!(install_registry(ToolRegistry::new()).is_err());
+        assert_eq!(get_registry().len(), shared.len());
+    }
+}
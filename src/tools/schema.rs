@@ -0,0 +1,373 @@
+// ============================================================================
+// VALIDACIÓN DE PARÁMETROS - JSON Schema
+// ============================================================================
+// Valida los `ToolParams` contra el `parameters_schema()` de cada herramienta
+// antes de ejecutarla, de modo que las entradas inválidas se rechazan en el
+// registro con la ruta del campo culpable (p. ej. `/items/2/name`) en lugar
+// de fallar a mitad de la herramienta. Cubre las palabras clave de draft 7
+// que usan los esquemas de function calling: type, enum, const, required,
+// properties, patternProperties, additionalProperties, rangos numéricos,
+// longitudes, pattern, items, contains, uniqueItems y los combinadores
+// allOf/anyOf/oneOf/not. `format`, `title` y `description` son anotaciones
+// y no se validan.
+//
+// Los `default` de las propiedades ausentes se rellenan durante la
+// validación. Un `null` en una propiedad opcional cuenta como ausente, igual
+// que en `ToolParams::get_optional`.
+// ============================================================================
+
+use super::{ToolError, ToolParams};
+use regex::Regex;
+use serde_json::{Map, Value};
+
+/// Valida un valor contra un esquema, rellenando los valores por defecto
+pub fn validate(value: &mut Value, schema: &Value) -> Result<(), ToolError> {
+    check(value, schema, "").map_err(|violation| ToolError::ValidationError(violation.to_string()))
+}
+
+/// Valida los parámetros de una herramienta contra su esquema
+pub fn validate_params(params: &mut ToolParams, schema: &Value) -> Result<(), ToolError> {
+    let mut value = Value::Object(params.data.drain().collect());
+    let result = validate(&mut value, schema);
+    if let Value::Object(map) = value {
+        params.data.extend(map);
+    }
+    result
+}
+
+/// Campo inválido y el motivo
+#[derive(Debug)]
+struct Violation {
+    path: String,
+    message: String,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+type Checked = Result<(), Violation>;
+
+fn violation(path: &str, message: impl Into<String>) -> Violation {
+    Violation { path: path.to_string(), message: message.into() }
+}
+
+/// Segmento de ruta con el escape de JSON Pointer
+fn child(path: &str, segment: &str) -> String {
+    format!("{}/{}", path, segment.replace('~', "~0").replace('/', "~1"))
+}
+
+fn check(value: &mut Value, schema: &Value, path: &str) -> Checked {
+    let schema = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return Err(violation(path, "no se admite ningún valor")),
+        Value::Object(schema) => schema,
+        _ => return Ok(()),
+    };
+
+    if let Some(expected) = schema.get("type") {
+        check_type(value, expected, path)?;
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            return Err(violation(path, format!("{} no es uno de {}", value, Value::Array(allowed.clone()))));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            return Err(violation(path, format!("se esperaba {}", expected)));
+        }
+    }
+
+    match value {
+        Value::Number(_) => check_number(value, schema, path)?,
+        Value::String(text) => check_string(text, schema, path)?,
+        Value::Array(items) => check_array(items, schema, path)?,
+        Value::Object(object) => check_object(object, schema, path)?,
+        _ => {}
+    }
+
+    check_combinators(value, schema, path)
+}
+
+fn type_matches(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn check_type(value: &Value, expected: &Value, path: &str) -> Checked {
+    let names: Vec<&str> = match expected {
+        Value::String(name) => vec![name.as_str()],
+        Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+        _ => return Ok(()),
+    };
+    if names.iter().any(|name| type_matches(value, name)) {
+        Ok(())
+    } else {
+        Err(violation(path, format!("se esperaba {} y llegó {}", names.join(" o "), type_name(value))))
+    }
+}
+
+fn check_number(value: &Value, schema: &Map<String, Value>, path: &str) -> Checked {
+    let Some(n) = value.as_f64() else {
+        return Ok(());
+    };
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    if let Some(minimum) = bound("minimum").filter(|&minimum| n < minimum) {
+        return Err(violation(path, format!("{} es menor que el mínimo {}", value, minimum)));
+    }
+    if let Some(maximum) = bound("maximum").filter(|&maximum| n > maximum) {
+        return Err(violation(path, format!("{} es mayor que el máximo {}", value, maximum)));
+    }
+    if let Some(minimum) = bound("exclusiveMinimum").filter(|&minimum| n <= minimum) {
+        return Err(violation(path, format!("{} debe ser mayor que {}", value, minimum)));
+    }
+    if let Some(maximum) = bound("exclusiveMaximum").filter(|&maximum| n >= maximum) {
+        return Err(violation(path, format!("{} debe ser menor que {}", value, maximum)));
+    }
+    if let Some(divisor) = bound("multipleOf").filter(|&divisor| divisor > 0.0) {
+        let quotient = n / divisor;
+        if (quotient - quotient.round()).abs() > 1e-9 {
+            return Err(violation(path, format!("{} no es múltiplo de {}", value, divisor)));
+        }
+    }
+    Ok(())
+}
+
+fn check_string(text: &str, schema: &Map<String, Value>, path: &str) -> Checked {
+    let length = text.chars().count() as u64;
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64).filter(|&min| length < min) {
+        return Err(violation(path, format!("longitud {} menor que {}", length, min)));
+    }
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64).filter(|&max| length > max) {
+        return Err(violation(path, format!("longitud {} mayor que {}", length, max)));
+    }
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        let regex = Regex::new(pattern)
+            .map_err(|e| violation(path, format!("patrón inválido en el esquema '{}': {}", pattern, e)))?;
+        if !regex.is_match(text) {
+            return Err(violation(path, format!("'{}' no cumple el patrón '{}'", text, pattern)));
+        }
+    }
+    Ok(())
+}
+
+fn check_array(items: &mut [Value], schema: &Map<String, Value>, path: &str) -> Checked {
+    let count = items.len() as u64;
+    if let Some(min) = schema.get("minItems").and_then(Value::as_u64).filter(|&min| count < min) {
+        return Err(violation(path, format!("{} elementos, mínimo {}", count, min)));
+    }
+    if let Some(max) = schema.get("maxItems").and_then(Value::as_u64).filter(|&max| count > max) {
+        return Err(violation(path, format!("{} elementos, máximo {}", count, max)));
+    }
+
+    match schema.get("items") {
+        // Tupla: un esquema por posición y `additionalItems` para el resto
+        Some(Value::Array(positional)) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                let item_path = child(path, &index.to_string());
+                if let Some(item_schema) = positional.get(index).or_else(|| schema.get("additionalItems")) {
+                    check(item, item_schema, &item_path)?;
+                }
+            }
+        }
+        Some(item_schema) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                check(item, item_schema, &child(path, &index.to_string()))?;
+            }
+        }
+        None => {}
+    }
+
+    if schema.get("uniqueItems").and_then(Value::as_bool).unwrap_or(false) {
+        for (index, item) in items.iter().enumerate() {
+            if items[..index].contains(item) {
+                return Err(violation(&child(path, &index.to_string()), format!("{} está repetido", item)));
+            }
+        }
+    }
+    if let Some(contains) = schema.get("contains") {
+        let found = items.iter().any(|item| check(&mut item.clone(), contains, path).is_ok());
+        if !found {
+            return Err(violation(path, "ningún elemento cumple 'contains'"));
+        }
+    }
+    Ok(())
+}
+
+fn check_object(object: &mut Map<String, Value>, schema: &Map<String, Value>, path: &str) -> Checked {
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let required: Vec<&str> = schema.get("required").and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    for name in &required {
+        if !object.contains_key(*name) {
+            return Err(violation(&child(path, name), "campo requerido faltante"));
+        }
+    }
+
+    let count = object.len() as u64;
+    if let Some(min) = schema.get("minProperties").and_then(Value::as_u64).filter(|&min| count < min) {
+        return Err(violation(path, format!("{} propiedades, mínimo {}", count, min)));
+    }
+    if let Some(max) = schema.get("maxProperties").and_then(Value::as_u64).filter(|&max| count > max) {
+        return Err(violation(path, format!("{} propiedades, máximo {}", count, max)));
+    }
+
+    if let Some(properties) = properties {
+        for (name, property_schema) in properties {
+            // Un null opcional cuenta como ausente
+            if object.get(name).is_some_and(Value::is_null) && !required.contains(&name.as_str()) {
+                object.remove(name);
+            }
+            match object.get_mut(name) {
+                Some(value) => check(value, property_schema, &child(path, name))?,
+                None => {
+                    if let Some(default) = property_schema.get("default") {
+                        object.insert(name.clone(), default.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    let pattern_properties: Vec<(Regex, &Value)> = match schema.get("patternProperties").and_then(Value::as_object) {
+        Some(patterns) => patterns.iter()
+            .map(|(pattern, property_schema)| {
+                Regex::new(pattern)
+                    .map(|regex| (regex, property_schema))
+                    .map_err(|e| violation(path, format!("patrón inválido en el esquema '{}': {}", pattern, e)))
+            })
+            .collect::<Result<_, _>>()?,
+        None => Vec::new(),
+    };
+    let additional = schema.get("additionalProperties");
+    for (name, value) in object.iter_mut() {
+        let declared = properties.is_some_and(|properties| properties.contains_key(name));
+        let mut matched = false;
+        for (regex, property_schema) in &pattern_properties {
+            if regex.is_match(name) {
+                matched = true;
+                check(value, property_schema, &child(path, name))?;
+            }
+        }
+        if declared || matched {
+            continue;
+        }
+        match additional {
+            Some(Value::Bool(false)) => return Err(violation(&child(path, name), "propiedad no permitida")),
+            Some(additional_schema) => check(value, additional_schema, &child(path, name))?,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+fn check_combinators(value: &mut Value, schema: &Map<String, Value>, path: &str) -> Checked {
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        for sub_schema in all {
+            check(value, sub_schema, path)?;
+        }
+    }
+    // En anyOf/oneOf los defaults se toman de la primera alternativa válida
+    if let Some(any) = schema.get("anyOf").and_then(Value::as_array) {
+        let mut errors = Vec::new();
+        let matched = any.iter().find_map(|sub_schema| {
+            let mut candidate = value.clone();
+            match check(&mut candidate, sub_schema, path) {
+                Ok(()) => Some(candidate),
+                Err(e) => {
+                    errors.push(e.to_string());
+                    None
+                }
+            }
+        });
+        match matched {
+            Some(candidate) => *value = candidate,
+            None => return Err(violation(path, format!("no cumple ninguna alternativa de anyOf ({})", errors.join("; ")))),
+        }
+    }
+    if let Some(one) = schema.get("oneOf").and_then(Value::as_array) {
+        let mut matches: Vec<Value> = one.iter()
+            .filter_map(|sub_schema| {
+                let mut candidate = value.clone();
+                check(&mut candidate, sub_schema, path).ok().map(|_| candidate)
+            })
+            .collect();
+        match matches.len() {
+            1 => *value = matches.remove(0),
+            0 => return Err(violation(path, "no cumple ninguna alternativa de oneOf")),
+            n => return Err(violation(path, format!("cumple {} alternativas de oneOf, se esperaba una", n))),
+        }
+    }
+    if let Some(not) = schema.get("not") {
+        if check(&mut value.clone(), not, path).is_ok() {
+            return Err(violation(path, "cumple el esquema de 'not'"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reports_path_to_bad_field_and_fills_defaults() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "mode": {"type": "string", "enum": ["fast", "safe"], "default": "safe"},
+                "limit": {"type": "integer", "minimum": 1, "default": 10},
+                "items": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {"name": {"type": "string", "pattern": "^[a-z_]+$"}},
+                        "required": ["name"]
+                    }
+                }
+            },
+            "required": ["items"],
+            "additionalProperties": false
+        });
+
+        let mut params = ToolParams::new().insert("items", json!([{"name": "ok"}])).insert("limit", Value::Null);
+        validate_params(&mut params, &schema).unwrap();
+        assert_eq!(params.get::<String>("mode").unwrap(), "safe");
+        assert_eq!(params.get::<u32>("limit").unwrap(), 10);
+
+        let error = |value: Value| validate(&mut value.clone(), &schema).unwrap_err().to_string();
+        assert!(error(json!({"items": [{"name": "ok"}, {"name": "Mal"}]})).contains("/items/1/name"));
+        assert!(error(json!({"items": [{}]})).contains("/items/0/name: campo requerido faltante"));
+        assert!(error(json!({"items": [], "limit": 0})).contains("/limit"));
+        assert!(error(json!({"items": [], "mode": "slow"})).contains("/mode"));
+        assert!(error(json!({"items": "x"})).contains("/items: se esperaba array y llegó string"));
+        assert!(error(json!({"items": [], "extra": 1})).contains("/extra: propiedad no permitida"));
+        assert!(error(json!({})).contains("/items: campo requerido faltante"));
+    }
+}